@@ -2,6 +2,7 @@ extern crate ppm;
 
 use std::path::Path;
 use std::fs;
+use std::sync::Once;
 
 #[macro_use]
 extern crate bencher;
@@ -19,7 +20,7 @@ fn test_read_ppm_c(){
     let image_load : ppm::Image;
 
     unsafe{
-        image_load = ppm::readPPM_libc("bench_read_image.ppm".to_string());
+        image_load = ppm::readPPM_libc("bench_read_image.ppm".to_string()).unwrap();
     }
         
     assert_eq!(image, image_load);
@@ -46,7 +47,7 @@ fn test_write_ppm_c(){
     let image = ppm::Image::new(pixels, 1, 3, "P3".to_string(), 91);
 
     unsafe{
-        ppm::writePPM_libc("bench_write_image.ppm".to_string(), &image);
+        ppm::writePPM_libc("bench_write_image.ppm".to_string(), &image).unwrap();
     }
 
     let image_load = ppm::Image::new_with_file(Path::new("bench_write_image.ppm"));
@@ -68,6 +69,40 @@ fn test_new_with_file(){
     fs::remove_file(Path::new("bench_new_with_file_image.ppm")).unwrap();
 }
 
+/// a synthetic render of 256x256 pixels, with flat areas, gradients and noise
+fn render(file_type : &str) -> ppm::Image{
+    let mut pixels = Vec::new();
+    for y in 0 .. 256u16{
+        for x in 0 .. 256u16{
+            pixels.push(match (x / 64 + y / 64) % 3 {
+                0 => ppm::Pixels::new(200, 30, 30),
+                1 => ppm::Pixels::new(x, y, 128),
+                _ => ppm::Pixels::new((x * 37 + y * 91) % 256, (x * y) % 256, x ^ y),
+            });
+        }
+    }
+    ppm::Image::new(pixels, 256, 256, file_type.to_string(), 255)
+}
+
+/// the size of the pixels of the image, 3 bytes by pixel, as the throughput of the codecs
+fn raw_size(image : &ppm::Image) -> u64{
+    (image.pixels.len() * 3) as u64
+}
+
+static PRINT_SIZES : Once = Once::new();
+
+/// print the size of the render in P3, P6 and QOI, once for all the runs of the bench
+fn print_sizes(){
+    PRINT_SIZES.call_once(|| {
+        let image = render("P6");
+        let p3 = render("P3").to_bytes().unwrap().len();
+        let p6 = image.to_bytes().unwrap().len();
+        let (width, heigth) = (image.width, image.heigth);
+        let qoi = ppm::QoiImage::Rgb(image).to_bytes().unwrap().len();
+        println!("render of {}x{} pixels: P3 {} bytes, P6 {} bytes, QOI {} bytes", width, heigth, p3, p6, qoi);
+    });
+}
+
 
 // bench function
 
@@ -87,6 +122,50 @@ fn bench_write_ppm_libc(b : &mut Bencher){
     b.iter(|| test_write_ppm_c());
 }
 
-benchmark_group!(benches, bench_new_with_file, bench_save, bench_read_ppm_libc, bench_write_ppm_libc);
+// the throughput of the codecs is measured on the size of the pixels, given by `raw_size`
+
+fn bench_encode_p3(b : &mut Bencher){
+    let image = render("P3");
+    b.bytes = raw_size(&image);
+    b.iter(|| image.to_bytes().unwrap());
+}
+
+fn bench_encode_p6(b : &mut Bencher){
+    let image = render("P6");
+    b.bytes = raw_size(&image);
+    b.iter(|| image.to_bytes().unwrap());
+}
+
+fn bench_encode_qoi(b : &mut Bencher){
+    print_sizes();
+    let image = render("P6");
+    b.bytes = raw_size(&image);
+    let image = ppm::QoiImage::Rgb(image);
+    b.iter(|| image.to_bytes().unwrap());
+}
+
+fn bench_decode_p3(b : &mut Bencher){
+    let image = render("P3");
+    b.bytes = raw_size(&image);
+    let data = image.to_bytes().unwrap();
+    b.iter(|| ppm::Image::from_bytes(&data).unwrap());
+}
+
+fn bench_decode_p6(b : &mut Bencher){
+    let image = render("P6");
+    b.bytes = raw_size(&image);
+    let data = image.to_bytes().unwrap();
+    b.iter(|| ppm::Image::from_bytes(&data).unwrap());
+}
+
+fn bench_decode_qoi(b : &mut Bencher){
+    let image = render("P6");
+    b.bytes = raw_size(&image);
+    let data = ppm::QoiImage::Rgb(image).to_bytes().unwrap();
+    b.iter(|| ppm::QoiImage::from_bytes(&data).unwrap());
+}
+
+benchmark_group!(benches, bench_new_with_file, bench_save, bench_read_ppm_libc, bench_write_ppm_libc,
+    bench_encode_p3, bench_encode_p6, bench_encode_qoi, bench_decode_p3, bench_decode_p6, bench_decode_qoi);
 benchmark_main!(benches);
 
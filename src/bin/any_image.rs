@@ -0,0 +1,484 @@
+use images::Image;
+use gray_image::GrayImage;
+use bit_image::BitImage;
+use pam::PamImage;
+use rgba_image::RgbaImage;
+use float_image::FloatImage;
+use png::{self, PngImage};
+use bmp::BmpImage;
+use tga::TgaImage;
+use qoi::{self, QoiImage};
+use jpeg::{self, Subsampling};
+use farbfeld;
+use tiff::{self, TiffImage};
+use xbm;
+use xpm::{self, XpmImage};
+use gif::{self, GifFrame, GifPalette, Dithering, Repeat};
+use error::PpmError;
+
+use std::fs::File;
+use std::io::prelude::*;
+use std::path::Path;
+
+/// the formats of images that `open` and `save` know
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Format{
+    /// bilevel Netpbm, P1 and P4
+    Pbm,
+    /// grayscale Netpbm, P2 and P5
+    Pgm,
+    /// RGB Netpbm, P3 and P6
+    Ppm,
+    /// Netpbm with a tuple type, P7
+    Pam,
+    /// floating-point Netpbm, Pf and PF
+    Pfm,
+    /// Portable Network Graphics
+    Png,
+    /// Windows bitmap
+    Bmp,
+    /// Truevision TGA, found by the extension only because it has no magic number
+    Tga,
+    /// Quite OK Image
+    Qoi,
+    /// baseline JPEG with a JFIF header, saved only because there is no decoder
+    Jpeg,
+    /// 16 bits RGBA farbfeld
+    Farbfeld,
+    /// baseline Tagged Image File Format
+    Tiff,
+    /// X11 bitmap, written as C source
+    Xbm,
+    /// X11 pixmap with a palette, written as C source
+    Xpm,
+    /// Graphics Interchange Format, saved only because there is no decoder
+    Gif,
+}
+
+impl Format {
+
+    /// the format of a file from its first bytes, None if no format has this magic number
+    ///
+    /// # Arguments
+    ///
+    /// * `magic` - the first bytes of the file
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// assert_eq!(Some(Format::Ppm), Format::from_magic(b"P6\n"));
+    /// ```
+    pub fn from_magic(magic : &[u8]) -> Option<Format>{
+        if magic.starts_with(&png::SIGNATURE) {
+            return Some(Format::Png);
+        }
+        if magic.starts_with(qoi::MAGIC) {
+            return Some(Format::Qoi);
+        }
+        if magic.starts_with(farbfeld::MAGIC) {
+            return Some(Format::Farbfeld);
+        }
+        if magic.starts_with(tiff::MAGIC_LITTLE_ENDIAN) || magic.starts_with(tiff::MAGIC_BIG_ENDIAN) {
+            return Some(Format::Tiff);
+        }
+        if magic.starts_with(xpm::MAGIC) {
+            return Some(Format::Xpm);
+        }
+        if magic.starts_with(xbm::MAGIC) {
+            return Some(Format::Xbm);
+        }
+        if magic.starts_with(gif::SIGNATURE) || magic.starts_with(gif::SIGNATURE_87) {
+            return Some(Format::Gif);
+        }
+        if magic.starts_with(&[0xff, 0xd8, 0xff]) {
+            return Some(Format::Jpeg);
+        }
+        if magic.len() < 2 {
+            return None;
+        }
+        match &magic[.. 2] {
+            b"P1" | b"P4" => Some(Format::Pbm),
+            b"P2" | b"P5" => Some(Format::Pgm),
+            b"P3" | b"P6" => Some(Format::Ppm),
+            b"P7" => Some(Format::Pam),
+            b"Pf" | b"PF" => Some(Format::Pfm),
+            b"BM" => Some(Format::Bmp),
+            _ => None,
+        }
+    }
+
+    /// the format of a file from its extension, None if no format use this extension
+    ///
+    /// # Arguments
+    ///
+    /// * `filename` - the name of the file
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// assert_eq!(Some(Format::Pgm), Format::from_extension(Path::new("gray.PGM")));
+    /// ```
+    pub fn from_extension(filename : &Path) -> Option<Format>{
+        let extension = filename.extension()?.to_str()?.to_lowercase();
+        match &*extension {
+            "pbm" => Some(Format::Pbm),
+            "pgm" => Some(Format::Pgm),
+            "ppm" | "pnm" => Some(Format::Ppm),
+            "pam" => Some(Format::Pam),
+            "pfm" => Some(Format::Pfm),
+            "png" => Some(Format::Png),
+            "bmp" | "dib" => Some(Format::Bmp),
+            "tga" => Some(Format::Tga),
+            "qoi" => Some(Format::Qoi),
+            "jpg" | "jpeg" => Some(Format::Jpeg),
+            "ff" => Some(Format::Farbfeld),
+            "tif" | "tiff" => Some(Format::Tiff),
+            "xbm" => Some(Format::Xbm),
+            "xpm" => Some(Format::Xpm),
+            "gif" => Some(Format::Gif),
+            _ => None,
+        }
+    }
+}
+
+/// an image of any format, the kind of image is chosen by the file
+#[derive(Clone, Debug, PartialEq)]
+pub enum AnyImage{
+    Bit(BitImage),
+    Gray(GrayImage),
+    Rgb(Image),
+    Pam(PamImage),
+    Float(FloatImage),
+    Png(PngImage),
+    Bmp(BmpImage),
+    Tga(TgaImage),
+    Qoi(QoiImage),
+    Farbfeld(RgbaImage),
+    Tiff(TiffImage),
+    Xbm(BitImage),
+    Xpm(XpmImage),
+}
+
+impl AnyImage {
+
+    /// the format that keep the image without conversion
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// assert_eq!(Format::Ppm, AnyImage::Rgb(image).format());
+    /// ```
+    pub fn format(&self) -> Format{
+        match self {
+            AnyImage::Bit(_) => Format::Pbm,
+            AnyImage::Gray(_) => Format::Pgm,
+            AnyImage::Rgb(_) => Format::Ppm,
+            AnyImage::Pam(_) => Format::Pam,
+            AnyImage::Float(_) => Format::Pfm,
+            AnyImage::Png(_) => Format::Png,
+            AnyImage::Bmp(_) => Format::Bmp,
+            AnyImage::Tga(_) => Format::Tga,
+            AnyImage::Qoi(_) => Format::Qoi,
+            AnyImage::Farbfeld(_) => Format::Farbfeld,
+            AnyImage::Tiff(_) => Format::Tiff,
+            AnyImage::Xbm(_) => Format::Xbm,
+            AnyImage::Xpm(_) => Format::Xpm,
+        }
+    }
+
+    ///transform the image to a RGB image, the alpha channel is dropped and the
+    ///floating-point samples are clamped to 0 .. 255
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// let image = open(Path::new("image.pgm"))?.to_image();
+    /// ```
+    pub fn to_image(&self) -> Image{
+        match self {
+            AnyImage::Bit(image) => image.to_image(255),
+            AnyImage::Gray(image) => image.to_image(),
+            AnyImage::Rgb(image) => image.clone(),
+            AnyImage::Pam(PamImage::BlackAndWhite(image)) => image.to_image(255),
+            AnyImage::Pam(PamImage::Grayscale(image)) => image.to_image(),
+            AnyImage::Pam(PamImage::Rgb(image)) => image.clone(),
+            AnyImage::Pam(PamImage::GrayscaleAlpha(image)) => image.to_gray().to_image(),
+            AnyImage::Pam(PamImage::RgbAlpha(image)) => image.to_image(),
+            AnyImage::Float(image) => image.to_image(255),
+            AnyImage::Png(image) => image.to_image(),
+            AnyImage::Bmp(image) => image.to_image(),
+            AnyImage::Tga(image) => image.to_image(),
+            AnyImage::Qoi(image) => image.to_image(),
+            AnyImage::Farbfeld(image) => image.to_image(),
+            AnyImage::Tiff(image) => image.to_image(),
+            AnyImage::Xbm(image) => image.to_image(255),
+            AnyImage::Xpm(image) => image.to_image(),
+        }
+    }
+}
+
+///load an image of any known format, the format is found from the magic number of the file
+///
+/// # Arguments
+///
+/// * `filename` - the name of the file
+///
+/// # Example
+///
+/// ```
+/// match open(Path::new("image.pgm"))? {
+///     AnyImage::Gray(image) => println!("{} gray pixels", image.pixels.len()),
+///     image => println!("{:?} image", image.format()),
+/// }
+/// ```
+pub fn open(filename : &Path) -> Result<AnyImage, PpmError>{
+    let mut magic = Vec::new();
+    File::open(filename)?.take(16).read_to_end(&mut magic)?;
+    let format = match Format::from_magic(&magic).or_else(|| Format::from_extension(filename)) {
+        Some(format) => format,
+        None => return Err(PpmError::UnsupportedFormat(filename.to_string_lossy().into_owned())),
+    };
+
+    let image = match format {
+        Format::Pbm => AnyImage::Bit(BitImage::new_with_file(filename)?),
+        Format::Pgm => AnyImage::Gray(GrayImage::new_with_file(filename)?),
+        Format::Ppm => AnyImage::Rgb(Image::new_with_file(filename)?),
+        Format::Pam => AnyImage::Pam(PamImage::new_with_file(filename)?),
+        Format::Pfm => AnyImage::Float(FloatImage::new_with_file(filename)?),
+        Format::Png => AnyImage::Png(PngImage::new_with_file(filename)?),
+        Format::Bmp => AnyImage::Bmp(BmpImage::new_with_file(filename)?),
+        Format::Tga => AnyImage::Tga(TgaImage::new_with_file(filename)?),
+        Format::Qoi => AnyImage::Qoi(QoiImage::new_with_file(filename)?),
+        Format::Farbfeld => AnyImage::Farbfeld(RgbaImage::new_with_farbfeld(filename)?),
+        Format::Tiff => AnyImage::Tiff(TiffImage::new_with_file(filename)?),
+        Format::Xbm => AnyImage::Xbm(BitImage::new_with_xbm(filename)?),
+        Format::Xpm => AnyImage::Xpm(XpmImage::new_with_file(filename)?),
+        Format::Jpeg => return Err(PpmError::UnsupportedFormat(format!("JPEG decoding of {}", filename.to_string_lossy()))),
+        Format::Gif => return Err(PpmError::UnsupportedFormat(format!("GIF decoding of {}", filename.to_string_lossy()))),
+    };
+    Ok(image)
+}
+
+///save an image in the given format, or in the format of the extension of the file
+///
+/// the image is converted if the format can not keep it: a RGB image saved in PGM is
+/// transformed to gray, a gray image saved in PBM is thresholded at the half of the max value
+///
+/// # Arguments
+///
+/// * `image` - the image to save
+/// * `filename` - the name of the file
+/// * `format` - the format of the file, None to use the extension
+///
+/// # Example
+///
+/// ```
+/// let image = open(Path::new("image.ppm"))?;
+/// save(&image, Path::new("image.pgm"), None)?;
+/// save(&image, Path::new("image.out"), Some(Format::Pam))?;
+/// ```
+pub fn save(image : &AnyImage, filename : &Path, format : Option<Format>) -> Result<(), PpmError>{
+    let format = match format.or_else(|| Format::from_extension(filename)) {
+        Some(format) => format,
+        None => return Err(PpmError::UnsupportedFormat(filename.to_string_lossy().into_owned())),
+    };
+
+    match (format, image) {
+        (Format::Pbm, AnyImage::Bit(image)) => image.save(filename),
+        (Format::Pbm, AnyImage::Pam(PamImage::BlackAndWhite(image))) => image.save(filename),
+        (Format::Pbm, image) => {
+            let image = image.to_image();
+            BitImage::from_image(&image, (image.maxValue / 2 + 1) as u16).save(filename)
+        },
+        (Format::Pgm, AnyImage::Gray(image)) => image.save(filename),
+        (Format::Pgm, AnyImage::Pam(PamImage::Grayscale(image))) => image.save(filename),
+        (Format::Pgm, image) => image.to_image().grayscale().save(filename),
+        (Format::Ppm, image) => image.to_image().save(filename),
+        (Format::Pam, AnyImage::Pam(image)) => image.save(filename),
+        (Format::Pam, AnyImage::Bit(image)) => PamImage::BlackAndWhite(image.clone()).save(filename),
+        (Format::Pam, AnyImage::Gray(image)) => PamImage::Grayscale(image.clone()).save(filename),
+        (Format::Pam, image) => PamImage::Rgb(image.to_image()).save(filename),
+        (Format::Pfm, AnyImage::Float(image)) => image.save(filename),
+        (Format::Pfm, AnyImage::Gray(image)) => FloatImage::from_gray(image).save(filename),
+        (Format::Pfm, image) => FloatImage::from_image(&image.to_image()).save(filename),
+        (Format::Png, AnyImage::Png(image)) => image.save(filename),
+        (Format::Png, AnyImage::Gray(image)) => PngImage::Gray(image.clone()).save(filename),
+        (Format::Png, AnyImage::Pam(PamImage::Grayscale(image))) => PngImage::Gray(image.clone()).save(filename),
+        (Format::Png, AnyImage::Pam(PamImage::GrayscaleAlpha(image))) => PngImage::GrayAlpha(image.clone()).save(filename),
+        (Format::Png, AnyImage::Pam(PamImage::RgbAlpha(image))) => PngImage::Rgba(image.clone()).save(filename),
+        (Format::Png, image) => PngImage::Rgb(image.to_image()).save(filename),
+        (Format::Bmp, AnyImage::Bmp(image)) => image.save(filename),
+        (Format::Bmp, AnyImage::Pam(PamImage::RgbAlpha(image))) => BmpImage::Rgba(image.clone()).save(filename),
+        (Format::Bmp, image) => BmpImage::Rgb(image.to_image()).save(filename),
+        (Format::Tga, AnyImage::Tga(image)) => image.save(filename),
+        (Format::Tga, AnyImage::Gray(image)) => TgaImage::Gray(image.clone()).save(filename),
+        (Format::Tga, AnyImage::Pam(PamImage::Grayscale(image))) => TgaImage::Gray(image.clone()).save(filename),
+        (Format::Tga, AnyImage::Pam(PamImage::RgbAlpha(image))) => TgaImage::Rgba(image.clone()).save(filename),
+        (Format::Tga, image) => TgaImage::Rgb(image.to_image()).save(filename),
+        (Format::Qoi, AnyImage::Qoi(image)) => image.save(filename),
+        (Format::Qoi, AnyImage::Pam(PamImage::RgbAlpha(image))) => QoiImage::Rgba(image.clone()).save(filename),
+        (Format::Qoi, image) => QoiImage::Rgb(image.to_image()).save(filename),
+        (Format::Farbfeld, AnyImage::Farbfeld(image)) => image.save_farbfeld(filename),
+        (Format::Farbfeld, AnyImage::Pam(PamImage::RgbAlpha(image))) => image.save_farbfeld(filename),
+        (Format::Farbfeld, image) => RgbaImage::from_image(&image.to_image()).save_farbfeld(filename),
+        (Format::Tiff, AnyImage::Tiff(image)) => image.save(filename),
+        (Format::Tiff, AnyImage::Gray(image)) => TiffImage::Gray(image.clone()).save(filename),
+        (Format::Tiff, AnyImage::Pam(PamImage::Grayscale(image))) => TiffImage::Gray(image.clone()).save(filename),
+        (Format::Tiff, AnyImage::Pam(PamImage::GrayscaleAlpha(image))) => TiffImage::GrayAlpha(image.clone()).save(filename),
+        (Format::Tiff, AnyImage::Pam(PamImage::RgbAlpha(image))) => TiffImage::Rgba(image.clone()).save(filename),
+        (Format::Tiff, AnyImage::Farbfeld(image)) => TiffImage::Rgba(image.clone()).save(filename),
+        (Format::Tiff, image) => TiffImage::Rgb(image.to_image()).save(filename),
+        (Format::Xbm, AnyImage::Xbm(image)) | (Format::Xbm, AnyImage::Bit(image)) => image.save_xbm(filename),
+        (Format::Xbm, AnyImage::Pam(PamImage::BlackAndWhite(image))) => image.save_xbm(filename),
+        (Format::Xbm, image) => {
+            let image = image.to_image();
+            BitImage::from_image(&image, (image.maxValue / 2 + 1) as u16).save_xbm(filename)
+        },
+        (Format::Xpm, AnyImage::Xpm(image)) => image.save(filename),
+        (Format::Xpm, AnyImage::Pam(PamImage::RgbAlpha(image))) | (Format::Xpm, AnyImage::Farbfeld(image)) => XpmImage::Rgba(image.clone()).save(filename),
+        (Format::Xpm, image) => XpmImage::Rgb(image.to_image()).save(filename),
+        (Format::Jpeg, image) => image.to_image().save_jpeg(filename, jpeg::DEFAULT_QUALITY, Subsampling::Chroma420),
+        (Format::Gif, image) => gif::save_gif(filename, &[GifFrame::new(image.to_image(), 0)], Repeat::Once, GifPalette::Global, Dithering::FloydSteinberg),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pixels::Pixels;
+    use std::fs;
+
+    fn rgb() -> Image{
+        let pixels = vec![Pixels::new(255, 0, 0), Pixels::new(0, 255, 0), Pixels::new(0, 0, 255), Pixels::new(200, 200, 200)];
+        Image::new(pixels, 2, 2, "P6".to_string(), 255)
+    }
+
+    #[test]
+    fn test_from_magic(){
+        assert_eq!(Some(Format::Pbm), Format::from_magic(b"P1 2 2"));
+        assert_eq!(Some(Format::Pgm), Format::from_magic(b"P5"));
+        assert_eq!(Some(Format::Ppm), Format::from_magic(b"P3\n"));
+        assert_eq!(Some(Format::Pam), Format::from_magic(b"P7\nWIDTH"));
+        assert_eq!(Some(Format::Pfm), Format::from_magic(b"Pf\n"));
+        assert_eq!(Some(Format::Png), Format::from_magic(b"\x89PNG\r\n\x1a\n\0\0\0\rIHDR"));
+        assert_eq!(Some(Format::Bmp), Format::from_magic(b"BM6\0\0\0"));
+        assert_eq!(Some(Format::Qoi), Format::from_magic(b"qoif\0\0\0\x02"));
+        assert_eq!(Some(Format::Farbfeld), Format::from_magic(b"farbfeld\0\0\0\x02"));
+        assert_eq!(Some(Format::Tiff), Format::from_magic(b"II*\0\x08\0\0\0"));
+        assert_eq!(Some(Format::Tiff), Format::from_magic(b"MM\0*\0\0\0\x08"));
+        assert_eq!(Some(Format::Xbm), Format::from_magic(b"#define icon_width 16"));
+        assert_eq!(Some(Format::Xpm), Format::from_magic(b"/* XPM */\nstatic"));
+        assert_eq!(Some(Format::Jpeg), Format::from_magic(b"\xff\xd8\xff\xe0\0\x10JFIF"));
+        assert_eq!(Some(Format::Gif), Format::from_magic(b"GIF89a\x02\0"));
+        assert_eq!(Some(Format::Gif), Format::from_magic(b"GIF87a\x02\0"));
+        assert_eq!(None, Format::from_magic(b"P9"));
+        assert_eq!(None, Format::from_magic(b"P"));
+    }
+
+    #[test]
+    fn test_from_extension(){
+        assert_eq!(Some(Format::Pbm), Format::from_extension(Path::new("a/b.pbm")));
+        assert_eq!(Some(Format::Ppm), Format::from_extension(Path::new("image.PPM")));
+        assert_eq!(Some(Format::Tga), Format::from_extension(Path::new("image.tga")));
+        assert_eq!(Some(Format::Jpeg), Format::from_extension(Path::new("photo.JPEG")));
+        assert_eq!(None, Format::from_extension(Path::new("image")));
+        assert_eq!(None, Format::from_extension(Path::new("image.txt")));
+    }
+
+    #[test]
+    fn test_open() -> Result<(), PpmError>{
+        let image = rgb();
+        let files = [
+            ("test_open.pbm", Format::Pbm),
+            ("test_open.pgm", Format::Pgm),
+            ("test_open.ppm", Format::Ppm),
+            ("test_open.pam", Format::Pam),
+            ("test_open.pfm", Format::Pfm),
+            ("test_open.png", Format::Png),
+            ("test_open.bmp", Format::Bmp),
+            ("test_open.qoi", Format::Qoi),
+            ("test_open.ff", Format::Farbfeld),
+            ("test_open.tif", Format::Tiff),
+            ("test_open.xbm", Format::Xbm),
+            ("test_open.xpm", Format::Xpm),
+        ];
+        for &(filename, format) in files.iter(){
+            save(&AnyImage::Rgb(image.clone()), Path::new(filename), None)?;
+            // the extension is not used to open the file
+            fs::rename(Path::new(filename), Path::new("test_open.out"))?;
+            let image_load = open(Path::new("test_open.out"))?;
+            assert_eq!(format, image_load.format());
+            fs::remove_file(Path::new("test_open.out"))?;
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_save_conversions() -> Result<(), PpmError>{
+        let image = AnyImage::Rgb(rgb());
+
+        save(&image, Path::new("test_save_any.out"), Some(Format::Ppm))?;
+        assert_eq!(image, open(Path::new("test_save_any.out"))?);
+
+        save(&image, Path::new("test_save_any.out"), Some(Format::Pgm))?;
+        match open(Path::new("test_save_any.out"))? {
+            AnyImage::Gray(gray) => assert_eq!(vec![76, 150, 29, 200], gray.pixels),
+            _ => panic!("the file is a PGM image"),
+        }
+
+        save(&image, Path::new("test_save_any.out"), Some(Format::Pbm))?;
+        match open(Path::new("test_save_any.out"))? {
+            AnyImage::Bit(bits) => assert_eq!(vec![true, false, true, false], vec![bits.get(0, 0), bits.get(1, 0), bits.get(0, 1), bits.get(1, 1)]),
+            _ => panic!("the file is a PBM image"),
+        }
+
+        save(&image, Path::new("test_save_any.out"), Some(Format::Pfm))?;
+        assert_eq!(rgb().pixels, open(Path::new("test_save_any.out"))?.to_image().pixels);
+
+        save(&image, Path::new("test_save_any.out"), Some(Format::Png))?;
+        assert_eq!(AnyImage::Png(PngImage::Rgb(rgb())), open(Path::new("test_save_any.out"))?);
+
+        save(&image, Path::new("test_save_any.tga"), None)?;
+        assert_eq!(AnyImage::Tga(TgaImage::Rgb(rgb())), open(Path::new("test_save_any.tga"))?);
+        fs::remove_file(Path::new("test_save_any.tga"))?;
+
+        // a JPEG file is written but can not be opened
+        save(&image, Path::new("test_save_any.jpg"), None)?;
+        assert_eq!(Some(Format::Jpeg), Format::from_magic(&fs::read(Path::new("test_save_any.jpg"))?));
+        match open(Path::new("test_save_any.jpg")) {
+            Err(PpmError::UnsupportedFormat(_)) => {},
+            _ => panic!("there is no JPEG decoder"),
+        }
+        fs::remove_file(Path::new("test_save_any.jpg"))?;
+
+        save(&image, Path::new("test_save_any.gif"), None)?;
+        assert_eq!(Some(Format::Gif), Format::from_magic(&fs::read(Path::new("test_save_any.gif"))?));
+        match open(Path::new("test_save_any.gif")) {
+            Err(PpmError::UnsupportedFormat(_)) => {},
+            _ => panic!("there is no GIF decoder"),
+        }
+        fs::remove_file(Path::new("test_save_any.gif"))?;
+
+        fs::remove_file(Path::new("test_save_any.out"))?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_unsupported_format() -> Result<(), PpmError>{
+        match save(&AnyImage::Rgb(rgb()), Path::new("test_unsupported.txt"), None) {
+            Err(PpmError::UnsupportedFormat(name)) => assert_eq!("test_unsupported.txt", name),
+            _ => panic!("txt is not an image format"),
+        }
+
+        fs::write(Path::new("test_unsupported.txt"), b"not an image")?;
+        match open(Path::new("test_unsupported.txt")) {
+            Err(PpmError::UnsupportedFormat(_)) => {},
+            _ => panic!("the file is not an image"),
+        }
+        fs::remove_file(Path::new("test_unsupported.txt"))?;
+
+        Ok(())
+    }
+}
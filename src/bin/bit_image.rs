@@ -0,0 +1,431 @@
+use pixels::Pixels;
+use images::{self, Image};
+use image_view::ImageView;
+use header::PnmReader;
+use error::PpmError;
+
+use std::fs::File;
+use std::io::BufReader;
+use std::io::prelude::*;
+use std::path::Path;
+
+/// a bilevel image, each row is packed in bytes with the first pixel in the most significant bit,
+/// a bit set to 1 is black, as in the PBM format
+// the field `fileType` mirror the field of `Image`
+#[allow(non_snake_case)]
+#[derive(Clone, Debug)]
+pub struct BitImage{
+    pub bits : Vec<u8>,
+    pub heigth : usize,
+    pub width : usize,
+    pub fileType : String,
+    /// the commentaires of the header, the text after the `#` of each line
+    pub comments : Vec<String>,
+}
+
+impl BitImage {
+
+    /// create a new white bilevel image
+    ///
+    /// # Arguments
+    ///
+    /// * `heigth` - heigth of the image
+    /// * `width` - width of the image
+    /// * `file_type` - the type of the image, "P1" (ASCII) or "P4" (binary)
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// let image = BitImage::new(2, 10, "P4".to_string());
+    /// assert_eq!(4, image.bits.len());
+    /// ```
+    pub fn new(heigth : usize, width : usize, file_type : String) -> BitImage{
+        let bits = vec![0; heigth * row_bytes(width)];
+        BitImage{bits, heigth, width, fileType : file_type, comments : Vec::new()}
+    }
+
+    /// get the pixel of the column `x` and the row `y`, true if it is black
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// let image = BitImage::new(2, 10, "P4".to_string());
+    /// assert!(!image.get(9, 1));
+    /// ```
+    pub fn get(&self, x : usize, y : usize) -> bool{
+        let byte = self.bits[y * row_bytes(self.width) + x / 8];
+        byte & (0x80 >> (x % 8)) != 0
+    }
+
+    /// set the pixel of the column `x` and the row `y`, true for black
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// let mut image = BitImage::new(2, 10, "P4".to_string());
+    /// image.set(9, 1, true);
+    /// assert!(image.get(9, 1));
+    /// ```
+    pub fn set(&mut self, x : usize, y : usize, black : bool){
+        let index = y * row_bytes(self.width) + x / 8;
+        if black {
+            self.bits[index] |= 0x80 >> (x % 8);
+        } else {
+            self.bits[index] &= !(0x80 >> (x % 8));
+        }
+    }
+
+    ///load the image from the file pbm, ASCII (P1) or binary (P4)
+    ///
+    /// # Arguments
+    ///
+    /// * `filename` - the name of the file
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// let mut image = BitImage::new(2, 10, "P1".to_string());
+    /// image.set(3, 0, true);
+    /// image.save(Path::new("test_image.pbm"))?;
+    /// let image_load = BitImage::new_with_file(Path::new("test_image.pbm"));
+    /// assert_eq!(image, image_load?);
+    ///
+    /// fs::remove_file(Path::new("test_image.pbm"))?;
+    /// ```
+    pub fn new_with_file(filename: &Path) -> Result<BitImage, PpmError>{
+        let f = File::open(filename)?;
+        let mut reader = PnmReader::new(BufReader::new(f));
+
+        let position = reader.position();
+        let header = reader.read_header()?;
+        let heigth = header.heigth;
+        let width = header.width;
+        let comments = reader.take_comments();
+
+        if header.magic == "P4" {
+            let bits = reader.read_raw(heigth * row_bytes(width), 1)?;
+            let mut image = BitImage{bits, heigth, width, fileType : header.magic, comments};
+            image.clear_padding();
+            return Ok(image);
+        } else if header.magic != "P1" {
+            return Err(PpmError::BadMagic{ magic : header.magic, position });
+        }
+
+        // the digits of a P1 image may be written without any whitespace between them
+        let mut pixels = Vec::new();
+        let mut commentaire = false;
+        while pixels.len() < heigth * width {
+            let position = reader.position();
+            let c = match reader.read_byte()? {
+                Some(c) => c,
+                None => return Err(PpmError::Truncated{ expected : heigth * width, found : pixels.len(), position }),
+            };
+            match c {
+                b'\n' | b'\r' => commentaire = false,
+                _ if commentaire => {},
+                b'#' => commentaire = true,
+                b'0' | b'1' => pixels.push(c == b'1'),
+                b'2' ..= b'9' => return Err(PpmError::SampleOverMaxval{ sample : (c - b'0') as usize, max_value : 1, position }),
+                b' ' | b'\t' | 0x0b | 0x0c => {},
+                _ => return Err(PpmError::bad_header(format!("{:?} is not a pixel of a PBM image", c as char), position)),
+            }
+        }
+
+        let mut image = BitImage::new(heigth, width, header.magic);
+        for (index, &black) in pixels.iter().enumerate(){
+            image.set(index % width, index / width, black);
+        }
+        image.comments = comments;
+        Ok(image)
+    }
+
+    ///save the image in a file of pbm, in binary if `fileType` is "P4" else in ASCII
+    ///
+    /// the commentaires are written after the magic number
+    ///
+    /// # Arguments
+    ///
+    /// * `filename` - the name of the file
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// let image = BitImage::new(2, 10, "P4".to_string());
+    /// image.save(Path::new("test_image.pbm"))?;
+    /// ```
+    pub fn save(&self, filename : &Path) -> Result<(), PpmError>{
+        let mut f = File::create(filename)?;
+        let mut buf = String::new();
+        buf = buf + &self.fileType + "\n";
+        images::write_comments(&mut buf, &self.comments, "\n");
+        buf = buf + &format!("{} {}", self.width, self.heigth) + "\n";
+        f.write_all(buf.as_bytes())?;
+
+        if self.fileType == "P4" {
+            f.write_all(&self.bits)?;
+            return Ok(());
+        }
+
+        let mut pixel_string = String::new();
+        for y in 0 .. self.heigth{
+            let row : Vec<&str> = (0 .. self.width).map(|x| if self.get(x, y) { "1" } else { "0" }).collect();
+            for line in row.chunks(34){
+                pixel_string = pixel_string + &line.join(" ") + "\n";
+            }
+        }
+        f.write_all(pixel_string.as_bytes())?;
+        Ok(())
+    }
+
+    ///transform the RGB image to the bilevel image, a pixel with a luma under `threshold` become black
+    ///
+    /// a P3 image become a P1 image and a P6 image become a P4 image
+    ///
+    /// # Arguments
+    ///
+    /// * `image` - the RGB image
+    /// * `threshold` - the luma under which a pixel is black, on the scale of the `maxValue` of the image
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// let image_bit = BitImage::from_image(&image, (image.maxValue / 2) as u16);
+    /// ```
+    pub fn from_image<I : ImageView>(image : &I, threshold : u16) -> BitImage{
+        let file_type = if image.file_type() == "P6" { "P4" } else { "P1" };
+        let mut image_bit = BitImage::new(image.heigth(), image.width(), file_type.to_string());
+
+        for (index, pixel) in image.iter_pixels().enumerate(){
+            if pixel.luma() < threshold {
+                image_bit.set(index % image.width(), index / image.width(), true);
+            }
+        }
+        image_bit
+    }
+
+    ///transform the bilevel image to a RGB image, black pixels become 0 and white pixels `max_value`
+    ///
+    /// a P1 image become a P3 image and a P4 image become a P6 image
+    ///
+    /// # Arguments
+    ///
+    /// * `max_value` - the max value of the RGB image
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// let image = image_bit.to_image(255);
+    /// ```
+    pub fn to_image(&self, max_value : u16) -> Image{
+        let mut pixels = Vec::with_capacity(self.heigth * self.width);
+        for y in 0 .. self.heigth{
+            for x in 0 .. self.width{
+                let level = if self.get(x, y) { 0 } else { max_value };
+                pixels.push(Pixels::new(level, level, level));
+            }
+        }
+        let file_type = if self.fileType == "P4" { "P6" } else { "P3" };
+
+        Image::new(pixels, self.heigth, self.width, file_type.to_string(), max_value as usize)
+    }
+
+    /// set to 0 the bits after the last pixel of each row
+    fn clear_padding(&mut self){
+        let row_len = row_bytes(self.width);
+        if self.width.is_multiple_of(8) || row_len == 0 {
+            return;
+        }
+        let mask = 0xffu8 << (8 - self.width % 8);
+        for row in self.bits.chunks_mut(row_len){
+            row[row_len - 1] &= mask;
+        }
+    }
+}
+
+/// the number of bytes of a packed row
+///
+/// # Arguments
+///
+/// `width` - the width of the image
+///
+/// # Example
+///
+/// ```
+/// assert_eq!(2, row_bytes(10));
+/// ```
+fn row_bytes(width : usize) -> usize{
+    width.div_ceil(8)
+}
+
+impl PartialEq for BitImage {
+    fn eq(&self, other: &Self) -> bool {
+        // the commentaires are metadata, they are not compared
+        self.bits == other.bits &&
+        self.fileType == other.fileType &&
+        self.heigth == other.heigth &&
+        self.width == other.width
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use error::Position;
+    use std::fs;
+
+    fn test_image(file_type : &str) -> BitImage{
+        let mut image = BitImage::new(3, 10, file_type.to_string());
+        image.set(0, 0, true);
+        image.set(9, 0, true);
+        image.set(4, 1, true);
+        image.set(8, 2, true);
+        image
+    }
+
+    #[test]
+    fn test_row_bytes(){
+        assert_eq!(0, row_bytes(0));
+        assert_eq!(1, row_bytes(8));
+        assert_eq!(2, row_bytes(10));
+    }
+
+    #[test]
+    fn test_get_set(){
+        let mut image = test_image("P4");
+        assert_eq!(vec![0x80, 0x40, 0x08, 0x00, 0x00, 0x80], image.bits);
+        assert!(image.get(4, 1));
+        image.set(4, 1, false);
+        assert!(!image.get(4, 1));
+    }
+
+    #[test]
+    fn test_save_comments() -> Result<(), PpmError>{
+        for file_type in &["P1", "P4"] {
+            let mut image = test_image(file_type);
+            image.comments = vec![String::new(), " exposure: 1/250".to_string()];
+            image.save(Path::new("test_save_bit_comments.pbm"))?;
+
+            let image_load = BitImage::new_with_file(Path::new("test_save_bit_comments.pbm"))?;
+            assert_eq!(image.comments, image_load.comments);
+            assert_eq!(image, image_load);
+
+            fs::remove_file(Path::new("test_save_bit_comments.pbm"))?;
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_save_p1() -> std::io::Result<()>{
+        let image = test_image("P1");
+        image.save(Path::new("test_save_bit_p1.pbm"))?;
+        let image_load = BitImage::new_with_file(Path::new("test_save_bit_p1.pbm"));
+        assert_eq!(image, image_load?);
+
+        fs::remove_file(Path::new("test_save_bit_p1.pbm"))?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_new_with_file_p1_no_whitespace() -> std::io::Result<()>{
+        let mut f_w = File::create(Path::new("test_bit_p1_digits.pbm"))?;
+        f_w.write_all(b"P1 # no whitespace\n10 3 1000000001\n00001000\n00# a comment\n0000000010\n")?;
+
+        let image_load = BitImage::new_with_file(Path::new("test_bit_p1_digits.pbm"))?;
+        assert_eq!(test_image("P1"), image_load);
+
+        fs::remove_file(Path::new("test_bit_p1_digits.pbm"))?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_new_with_file_errors() -> std::io::Result<()>{
+        let mut f_w = File::create(Path::new("test_bit_errors.pbm"))?;
+        f_w.write_all(b"P1 2 2 1 0\n1 2")?;
+        match BitImage::new_with_file(Path::new("test_bit_errors.pbm")) {
+            Err(PpmError::SampleOverMaxval{ sample, position, .. }) => {
+                assert_eq!(2, sample);
+                assert_eq!(Position{line : 2, byte : 13}, position);
+            },
+            _ => panic!("2 is not a pixel of a PBM image"),
+        }
+
+        let mut f_w = File::create(Path::new("test_bit_errors.pbm"))?;
+        f_w.write_all(b"P1 2 2 1 0\n1")?;
+        match BitImage::new_with_file(Path::new("test_bit_errors.pbm")) {
+            Err(PpmError::Truncated{ expected, found, .. }) => assert_eq!((4, 3), (expected, found)),
+            _ => panic!("one pixel is missing"),
+        }
+
+        let mut f_w = File::create(Path::new("test_bit_errors.pbm"))?;
+        f_w.write_all(b"P4 9 2\n\x00")?;
+        match BitImage::new_with_file(Path::new("test_bit_errors.pbm")) {
+            Err(PpmError::Truncated{ expected, found, .. }) => assert_eq!((4, 1), (expected, found)),
+            _ => panic!("three rows are missing"),
+        }
+
+        fs::remove_file(Path::new("test_bit_errors.pbm"))?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_save_p4() -> std::io::Result<()>{
+        let image = test_image("P4");
+        image.save(Path::new("test_save_bit_p4.pbm"))?;
+        let image_load = BitImage::new_with_file(Path::new("test_save_bit_p4.pbm"));
+        assert_eq!(image, image_load?);
+
+        let data = fs::read(Path::new("test_save_bit_p4.pbm"))?;
+        assert_eq!(&[0x80, 0x40, 0x08, 0x00, 0x00, 0x80], &data[data.len() - 6 ..]);
+
+        fs::remove_file(Path::new("test_save_bit_p4.pbm"))?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_new_with_file_p4_padding() -> std::io::Result<()>{
+        let mut f_w = File::create(Path::new("test_bit_p4_padding.pbm"))?;
+        f_w.write_all(b"P4\n10 3\n")?;
+        f_w.write_all(&[0x80, 0x7f, 0x08, 0x3f, 0x00, 0xbf])?;
+
+        let image_load = BitImage::new_with_file(Path::new("test_bit_p4_padding.pbm"))?;
+        assert_eq!(test_image("P4"), image_load);
+
+        fs::remove_file(Path::new("test_bit_p4_padding.pbm"))?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_from_image(){
+        let mut pixels = Vec::new();
+        pixels.push(Pixels::new(0, 0, 0));
+        pixels.push(Pixels::new(100, 100, 100));
+        pixels.push(Pixels::new(200, 200, 200));
+        pixels.push(Pixels::new(255, 255, 255));
+        let image = Image::new(pixels, 1, 4, "P6".to_string(), 255);
+
+        let image_bit = BitImage::from_image(&image, 128);
+        assert_eq!("P4", image_bit.fileType);
+        assert_eq!(vec![true, true, false, false], (0 .. 4).map(|x| image_bit.get(x, 0)).collect::<Vec<bool>>());
+
+        let image_bit = BitImage::from_image(&image, 50);
+        assert_eq!(vec![true, false, false, false], (0 .. 4).map(|x| image_bit.get(x, 0)).collect::<Vec<bool>>());
+    }
+
+    #[test]
+    fn test_to_image(){
+        let image_bit = test_image("P1");
+
+        let image = image_bit.to_image(255);
+
+        assert_eq!("P3", image.fileType);
+        assert_eq!(Pixels::new(0, 0, 0), image.pixels[0]);
+        assert_eq!(Pixels::new(255, 255, 255), image.pixels[1]);
+        assert_eq!(BitImage::from_image(&image, 128), image_bit);
+    }
+}
@@ -0,0 +1,406 @@
+use pixels::Pixels;
+use images::{self, Image};
+use error::{PpmError, Position};
+use rgba_image::{RgbaPixels, RgbaImage};
+
+use std::fs::File;
+use std::io::BufWriter;
+use std::io::prelude::*;
+use std::path::Path;
+
+/// the size of the file header, before the DIB header
+const FILE_HEADER_SIZE : usize = 14;
+/// the size of the BITMAPINFOHEADER, written for the RGB images
+const INFO_HEADER_SIZE : usize = 40;
+/// the size of the BITMAPV4HEADER, written for the images with alpha
+const V4_HEADER_SIZE : usize = 108;
+/// the resolution written in the header, 72 dpi in pixels by meter
+const PIXELS_PER_METER : u32 = 2835;
+/// the compression of the uncompressed images
+const BI_RGB : u32 = 0;
+/// the compression of the uncompressed images with the masks of the channels
+const BI_BITFIELDS : u32 = 3;
+/// the compression of the uncompressed images with the masks of the channels and the alpha
+const BI_ALPHABITFIELDS : u32 = 6;
+
+/// the order of the rows in a BMP or a TGA file
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum RowOrder{
+    /// the first row of the file is the bottom of the image
+    BottomUp,
+    /// the first row of the file is the top of the image
+    TopDown,
+}
+
+/// an image of the BMP format, 24 bits for the RGB images and 32 bits for the images with alpha
+///
+/// the samples of an image with another max value than 255 are scaled to 0 .. 255
+#[derive(Clone, Debug, PartialEq)]
+pub enum BmpImage{
+    Rgb(Image),
+    Rgba(RgbaImage),
+}
+
+impl BmpImage {
+
+    ///load the image from the file bmp
+    ///
+    /// the 24 bits and 32 bits images are supported, bottom-up or top-down; a 32 bits image
+    /// is a RGBA image only if its header has a mask for the alpha
+    ///
+    /// # Arguments
+    ///
+    /// * `filename` - the name of the file
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// let image = BmpImage::new_with_file(Path::new("image.bmp"))?.to_image();
+    /// ```
+    pub fn new_with_file(filename: &Path) -> Result<BmpImage, PpmError>{
+        BmpImage::read_from(File::open(filename)?)
+    }
+
+    ///load the image from a reader of BMP data
+    ///
+    /// # Arguments
+    ///
+    /// * `reader` - the reader of the BMP data
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// let image = BmpImage::read_from(&bytes[..])?;
+    /// ```
+    pub fn read_from<R : Read>(mut reader : R) -> Result<BmpImage, PpmError>{
+        let mut data = Vec::new();
+        reader.read_to_end(&mut data)?;
+        decode(&data)
+    }
+
+    ///save the image in a file of bmp, bottom-up like most of the BMP files
+    ///
+    /// # Arguments
+    ///
+    /// * `filename` - the name of the file
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// BmpImage::Rgb(image).save(Path::new("image.bmp"))?;
+    /// ```
+    pub fn save(&self, filename : &Path) -> Result<(), PpmError>{
+        self.save_with_order(filename, RowOrder::BottomUp)
+    }
+
+    ///save the image in a file of bmp with the given order of the rows
+    ///
+    /// # Arguments
+    ///
+    /// * `filename` - the name of the file
+    /// * `order` - the order of the rows in the file
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// image.save_with_order(Path::new("image.bmp"), RowOrder::TopDown)?;
+    /// ```
+    pub fn save_with_order(&self, filename : &Path, order : RowOrder) -> Result<(), PpmError>{
+        let mut writer = BufWriter::new(File::create(filename)?);
+        self.write_to(&mut writer, order)?;
+        writer.flush()?;
+        Ok(())
+    }
+
+    ///write the image as BMP data to a writer
+    ///
+    /// # Arguments
+    ///
+    /// * `writer` - the writer of the BMP data
+    /// * `order` - the order of the rows in the file
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// let mut bytes = Vec::new();
+    /// image.write_to(&mut bytes, RowOrder::BottomUp)?;
+    /// ```
+    pub fn write_to<W : Write>(&self, mut writer : W, order : RowOrder) -> Result<(), PpmError>{
+        let (heigth, width, max_value) = self.size();
+        let bytes_per_pixel = match self { BmpImage::Rgb(_) => 3, BmpImage::Rgba(_) => 4 };
+        let stride = (width * bytes_per_pixel).next_multiple_of(4);
+        let header_size = match self { BmpImage::Rgb(_) => INFO_HEADER_SIZE, BmpImage::Rgba(_) => V4_HEADER_SIZE };
+        let offset = FILE_HEADER_SIZE + header_size;
+        let file_size = stride.checked_mul(heigth).and_then(|size| size.checked_add(offset));
+        if width > i32::MAX as usize || heigth > i32::MAX as usize || file_size.is_none_or(|size| size > u32::MAX as usize) {
+            return Err(PpmError::UnsupportedFormat(format!("BMP image of {}x{} pixels", width, heigth)));
+        }
+
+        let mut header = Vec::with_capacity(offset);
+        header.extend_from_slice(b"BM");
+        header.extend_from_slice(&(file_size.unwrap() as u32).to_le_bytes());
+        header.extend_from_slice(&[0; 4]);
+        header.extend_from_slice(&(offset as u32).to_le_bytes());
+        header.extend_from_slice(&(header_size as u32).to_le_bytes());
+        header.extend_from_slice(&(width as i32).to_le_bytes());
+        // a negative heigth is a top-down image
+        let signed_heigth = if order == RowOrder::TopDown { -(heigth as i32) } else { heigth as i32 };
+        header.extend_from_slice(&signed_heigth.to_le_bytes());
+        header.extend_from_slice(&1u16.to_le_bytes());
+        header.extend_from_slice(&(bytes_per_pixel as u16 * 8).to_le_bytes());
+        let compression = match self { BmpImage::Rgb(_) => BI_RGB, BmpImage::Rgba(_) => BI_BITFIELDS };
+        header.extend_from_slice(&compression.to_le_bytes());
+        header.extend_from_slice(&((stride * heigth) as u32).to_le_bytes());
+        header.extend_from_slice(&PIXELS_PER_METER.to_le_bytes());
+        header.extend_from_slice(&PIXELS_PER_METER.to_le_bytes());
+        header.extend_from_slice(&[0; 8]);
+        if let BmpImage::Rgba(_) = self {
+            for mask in &[0x00ff_0000u32, 0x0000_ff00, 0x0000_00ff, 0xff00_0000]{
+                header.extend_from_slice(&mask.to_le_bytes());
+            }
+            // the color space is sRGB, the end points and the gamma are not used
+            header.extend_from_slice(b"BGRs");
+            header.extend_from_slice(&[0; 48]);
+        }
+        writer.write_all(&header)?;
+
+        let mut row = vec![0u8; stride];
+        for y in 0 .. heigth{
+            let y = if order == RowOrder::TopDown { y } else { heigth - 1 - y };
+            for x in 0 .. width{
+                let (red, green, blue, alpha) = match self {
+                    BmpImage::Rgb(image) => {
+                        let pixel = image.pixels[y * width + x];
+                        (pixel.red, pixel.green, pixel.blue, 0)
+                    },
+                    BmpImage::Rgba(image) => {
+                        let pixel = image.pixels[y * width + x];
+                        (pixel.red, pixel.green, pixel.blue, pixel.alpha)
+                    },
+                };
+                let start = x * bytes_per_pixel;
+                for (index, &sample) in [blue, green, red, alpha].iter().take(bytes_per_pixel).enumerate(){
+                    row[start + index] = images::scale_sample(sample, max_value, 255) as u8;
+                }
+            }
+            writer.write_all(&row)?;
+        }
+        Ok(())
+    }
+
+    ///transform the image to a RGB image, the alpha channel is dropped
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// let image = BmpImage::new_with_file(Path::new("image.bmp"))?.to_image();
+    /// ```
+    pub fn to_image(&self) -> Image{
+        match self {
+            BmpImage::Rgb(image) => image.clone(),
+            BmpImage::Rgba(image) => image.to_image(),
+        }
+    }
+
+    /// the heigth, the width and the max value of the image
+    fn size(&self) -> (usize, usize, usize){
+        match self {
+            BmpImage::Rgb(image) => (image.heigth, image.width, image.maxValue),
+            BmpImage::Rgba(image) => (image.heigth, image.width, image.maxValue),
+        }
+    }
+}
+
+/// an error of the BMP data
+fn corrupt<S : Into<String>>(message : S) -> PpmError{
+    PpmError::Corrupt{ format : "BMP", message : message.into() }
+}
+
+/// the little-endian number of 2 bytes at the offset
+fn read_u16(data : &[u8], offset : usize) -> u16{
+    u16::from_le_bytes([data[offset], data[offset + 1]])
+}
+
+/// the little-endian number of 4 bytes at the offset
+fn read_u32(data : &[u8], offset : usize) -> u32{
+    u32::from_le_bytes([data[offset], data[offset + 1], data[offset + 2], data[offset + 3]])
+}
+
+/// a channel of a 32 bits pixel, found by its mask and scaled to 0 .. 255
+fn read_channel(value : u32, mask : u32) -> u16{
+    if mask == 0 {
+        return 0;
+    }
+    let bits = mask >> mask.trailing_zeros();
+    let channel = (value & mask) >> mask.trailing_zeros();
+    images::scale_sample(channel as u16, bits as usize, 255)
+}
+
+/// decode the BMP data of a file
+fn decode(data : &[u8]) -> Result<BmpImage, PpmError>{
+    if data.len() < 2 || &data[.. 2] != b"BM" {
+        let magic = String::from_utf8_lossy(&data[.. data.len().min(2)]).into_owned();
+        return Err(PpmError::BadMagic{ magic, position : Position{line : 1, byte : 0} });
+    }
+    if data.len() < FILE_HEADER_SIZE + 16 {
+        return Err(corrupt("the header is truncated"));
+    }
+    let offset = read_u32(data, 10) as usize;
+    let header_size = read_u32(data, 14) as usize;
+    if header_size < INFO_HEADER_SIZE || data.len() < FILE_HEADER_SIZE + header_size {
+        return Err(PpmError::UnsupportedFormat(format!("BMP header of {} bytes", header_size)));
+    }
+    let header = &data[FILE_HEADER_SIZE ..];
+    let width = read_u32(header, 4) as i32;
+    let signed_heigth = read_u32(header, 8) as i32;
+    let bits = read_u16(header, 14);
+    let compression = read_u32(header, 16);
+    if width <= 0 || signed_heigth == 0 || signed_heigth == i32::MIN {
+        return Err(corrupt(format!("bad size {}x{}", width, signed_heigth)));
+    }
+    if bits != 24 && bits != 32 {
+        return Err(PpmError::UnsupportedFormat(format!("BMP of {} bits", bits)));
+    }
+    let (width, heigth) = (width as usize, signed_heigth.unsigned_abs() as usize);
+    let order = if signed_heigth < 0 { RowOrder::TopDown } else { RowOrder::BottomUp };
+
+    // the masks are in the header since the V2 header, after the BITMAPINFOHEADER else
+    let masks = match compression {
+        BI_RGB => None,
+        BI_BITFIELDS | BI_ALPHABITFIELDS if bits == 32 => {
+            let count = if compression == BI_ALPHABITFIELDS || header_size >= 56 { 4 } else { 3 };
+            let start = FILE_HEADER_SIZE + INFO_HEADER_SIZE;
+            if data.len() < start + count * 4 {
+                return Err(corrupt("the masks are truncated"));
+            }
+            let mut masks = [0u32; 4];
+            for (index, mask) in masks.iter_mut().take(count).enumerate(){
+                *mask = read_u32(data, start + index * 4);
+            }
+            Some(masks)
+        },
+        _ => return Err(PpmError::UnsupportedFormat(format!("BMP compression {}", compression))),
+    };
+
+    let bytes_per_pixel = bits as usize / 8;
+    let stride = (width * bytes_per_pixel).next_multiple_of(4);
+    let size = stride.checked_mul(heigth).ok_or_else(|| corrupt("the image is too large"))?;
+    if offset.checked_add(size).is_none_or(|end| end > data.len()) {
+        return Err(PpmError::Truncated{ expected : size, found : data.len().saturating_sub(offset), position : Position{line : 1, byte : offset} });
+    }
+
+    let mut pixels = Vec::with_capacity(width * heigth);
+    for y in 0 .. heigth{
+        let row = if order == RowOrder::TopDown { y } else { heigth - 1 - y };
+        let row = &data[offset + row * stride ..];
+        for x in 0 .. width{
+            let pixel = &row[x * bytes_per_pixel ..];
+            pixels.push(match masks {
+                Some(masks) => {
+                    let value = read_u32(pixel, 0);
+                    let alpha = if masks[3] == 0 { 255 } else { read_channel(value, masks[3]) };
+                    RgbaPixels::new(read_channel(value, masks[0]), read_channel(value, masks[1]), read_channel(value, masks[2]), alpha)
+                },
+                // the fourth byte of a 32 bits pixel without masks is not used
+                None => RgbaPixels::new(pixel[2] as u16, pixel[1] as u16, pixel[0] as u16, 255),
+            });
+        }
+    }
+
+    match masks {
+        Some(masks) if masks[3] != 0 => Ok(BmpImage::Rgba(RgbaImage::new(pixels, heigth, width, 255))),
+        _ => {
+            let pixels = pixels.iter().map(|pixel| Pixels::new(pixel.red, pixel.green, pixel.blue)).collect();
+            Ok(BmpImage::Rgb(Image::new(pixels, heigth, width, "P6".to_string(), 255)))
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn test_read_bottom_up(){
+        // a 2x2 image of 24 bits, each row is padded to 8 bytes
+        let mut data = Vec::new();
+        data.extend_from_slice(b"BM");
+        data.extend_from_slice(&70u32.to_le_bytes());
+        data.extend_from_slice(&[0, 0, 0, 0, 54, 0, 0, 0, 40, 0, 0, 0, 2, 0, 0, 0, 2, 0, 0, 0, 1, 0, 24, 0]);
+        data.extend_from_slice(&[0; 24]);
+        // the bottom row, blue then white
+        data.extend_from_slice(&[255, 0, 0, 255, 255, 255, 0, 0]);
+        // the top row, red then green
+        data.extend_from_slice(&[0, 0, 255, 0, 255, 0, 0, 0]);
+
+        let pixels = vec![Pixels::new(255, 0, 0), Pixels::new(0, 255, 0), Pixels::new(0, 0, 255), Pixels::new(255, 255, 255)];
+        assert_eq!(BmpImage::Rgb(Image::new(pixels, 2, 2, "P6".to_string(), 255)), BmpImage::read_from(&data[..]).unwrap());
+    }
+
+    #[test]
+    fn test_round_trip_ppm() -> Result<(), PpmError>{
+        let ppm = Image::new_with_file(Path::new("resource/ppmImage.ppm"))?;
+        let image = images::ppm_scaled_to(255)?;
+        let image_rgba = RgbaImage::new(image.pixels.iter().enumerate().map(|(index, &pixel)| RgbaPixels::from_pixels(pixel, (index % 256) as u16)).collect(),
+            image.heigth, image.width, 255);
+
+        for &order in [RowOrder::BottomUp, RowOrder::TopDown].iter(){
+            BmpImage::Rgb(image.clone()).save_with_order(Path::new("test_save.bmp"), order)?;
+            assert_eq!(BmpImage::Rgb(image.clone()), BmpImage::new_with_file(Path::new("test_save.bmp"))?);
+
+            BmpImage::Rgba(image_rgba.clone()).save_with_order(Path::new("test_save.bmp"), order)?;
+            assert_eq!(BmpImage::Rgba(image_rgba.clone()), BmpImage::new_with_file(Path::new("test_save.bmp"))?);
+        }
+
+        // the same pixels after the BMP and after the PPM
+        BmpImage::Rgb(ppm.clone()).save(Path::new("test_save.bmp"))?;
+        BmpImage::new_with_file(Path::new("test_save.bmp"))?.to_image().save(Path::new("test_save_bmp.ppm"))?;
+        assert_eq!(image, Image::new_with_file(Path::new("test_save_bmp.ppm"))?);
+        fs::remove_file(Path::new("test_save.bmp"))?;
+        fs::remove_file(Path::new("test_save_bmp.ppm"))?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_write_layout() -> Result<(), PpmError>{
+        let image = Image::new(vec![Pixels::new(1, 2, 3), Pixels::new(4, 5, 6), Pixels::new(7, 8, 9)], 3, 1, "P6".to_string(), 255);
+        let mut bytes = Vec::new();
+        BmpImage::Rgb(image).write_to(&mut bytes, RowOrder::BottomUp)?;
+        assert_eq!(54 + 3 * 4, bytes.len());
+        assert_eq!(3, read_u32(&bytes, 22));
+        assert_eq!(&[9, 8, 7, 0, 6, 5, 4, 0, 3, 2, 1, 0], &bytes[54 ..]);
+
+        let image = Image::new(vec![Pixels::new(15, 0, 5)], 1, 1, "P3".to_string(), 15);
+        let mut bytes = Vec::new();
+        BmpImage::Rgb(image).write_to(&mut bytes, RowOrder::TopDown)?;
+        assert_eq!(-1i32, read_u32(&bytes, 22) as i32);
+        assert_eq!(&[85, 0, 255, 0], &bytes[54 ..]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_read_errors(){
+        match BmpImage::read_from(&b"P6\n1 1\n255\n"[..]) {
+            Err(PpmError::BadMagic{ .. }) => {},
+            _ => panic!("the file is not a BMP"),
+        }
+
+        let mut bytes = Vec::new();
+        let image = Image::new(vec![Pixels::new(1, 2, 3); 4], 2, 2, "P6".to_string(), 255);
+        BmpImage::Rgb(image).write_to(&mut bytes, RowOrder::BottomUp).unwrap();
+        match BmpImage::read_from(&bytes[.. 60]) {
+            Err(PpmError::Truncated{ expected, found, .. }) => assert_eq!((16, 6), (expected, found)),
+            _ => panic!("the file is truncated"),
+        }
+
+        // 8 bits with a palette
+        bytes[28] = 8;
+        match BmpImage::read_from(&bytes[..]) {
+            Err(PpmError::UnsupportedFormat(_)) => {},
+            _ => panic!("the palette is not supported"),
+        }
+    }
+}
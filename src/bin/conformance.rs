@@ -0,0 +1,358 @@
+use pixels::Pixels;
+use images::{self, Image};
+use header::{self, PnmReader};
+use error::PpmError;
+
+use std::fmt;
+use std::fs::File;
+use std::io::prelude::*;
+use std::path::Path;
+
+/// the longest line allowed by the spec in the plain formats (P1, P2, P3)
+pub const MAX_LINE_LEN : usize = 70;
+
+/// how the checker treat a violation of the spec
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Conformance{
+    /// a violation is an error
+    Strict,
+    /// a violation is repaired and reported as a warning
+    Lenient,
+}
+
+/// a violation of the Netpbm spec
+#[derive(Clone, Debug, PartialEq)]
+pub enum Violation{
+    /// the number of pixels is not width x heigth, the lenient mode drop the extra pixels and add black pixels
+    PixelCount{ expected : usize, found : usize },
+    /// some samples are larger than the max value, the lenient mode clamp them
+    SampleOverMaxval{ count : usize, largest : usize, max_value : usize },
+    /// the max value is not in 1..65535, the lenient mode use the largest sample, in the same range
+    MaxValueRange{ max_value : usize },
+    /// a line of a plain file is longer than 70 characters, the lenient mode accept it
+    LineTooLong{ line : usize, length : usize },
+}
+
+impl fmt::Display for Violation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Violation::PixelCount{ expected, found } => write!(f, "{} pixels, width x heigth is {}", found, expected),
+            Violation::SampleOverMaxval{ count, largest, max_value } =>
+                write!(f, "{} samples over the max value {}, the largest is {}", count, max_value, largest),
+            Violation::MaxValueRange{ max_value } => write!(f, "the max value {} is not in 1..65535", max_value),
+            Violation::LineTooLong{ line, length } =>
+                write!(f, "the line {} has {} characters, more than {}", line, length, MAX_LINE_LEN),
+        }
+    }
+}
+
+/// the violations found by the checker, as warnings in lenient mode
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Report{
+    pub warnings : Vec<Violation>,
+}
+
+impl Report {
+
+    /// true if the image follow the spec
+    pub fn is_conformant(&self) -> bool{
+        self.warnings.is_empty()
+    }
+}
+
+impl Image {
+
+    ///check the image against the spec, in lenient mode the violations are repaired
+    ///
+    /// # Arguments
+    ///
+    /// * `mode` - strict to get an error for a violation, lenient to repair it
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// let mut image = Image::new(vec![Pixels::new(7, 91, 43)], 1, 2, "P3".to_string(), 91);
+    /// let report = image.conform(Conformance::Lenient)?;
+    /// assert_eq!(2, image.pixels.len());
+    /// ```
+    pub fn conform(&mut self, mode : Conformance) -> Result<Report, PpmError>{
+        let mut warnings = Vec::new();
+
+        let max_value_valid = self.maxValue >= 1 && self.maxValue <= 65535;
+        if !max_value_valid {
+            warnings.push(Violation::MaxValueRange{ max_value : self.maxValue });
+        }
+        let expected = self.heigth.saturating_mul(self.width);
+        if self.pixels.len() != expected {
+            warnings.push(Violation::PixelCount{ expected, found : self.pixels.len() });
+        }
+        let max_value = self.maxValue.clamp(1, 65535);
+        let mut count = 0;
+        let mut largest = 0;
+        for pixel in &self.pixels{
+            for &sample in &[pixel.red, pixel.green, pixel.blue]{
+                if sample as usize > max_value {
+                    count += 1;
+                    largest = largest.max(sample as usize);
+                }
+            }
+        }
+        // with a max value out of the range, the samples are checked after the repair of the max value
+        if count > 0 && max_value_valid {
+            warnings.push(Violation::SampleOverMaxval{ count, largest, max_value });
+        }
+
+        if mode == Conformance::Strict {
+            if !warnings.is_empty() {
+                return Err(PpmError::Nonconformant{ violations : warnings });
+            }
+            return Ok(Report::default());
+        }
+
+        if !max_value_valid {
+            let largest = self.pixels.iter().map(|pixel| pixel.red.max(pixel.green).max(pixel.blue) as usize).max();
+            self.maxValue = largest.unwrap_or(1).max(1);
+        }
+        let max_value = self.maxValue as u16;
+        for pixel in self.pixels.iter_mut(){
+            pixel.red = pixel.red.min(max_value);
+            pixel.green = pixel.green.min(max_value);
+            pixel.blue = pixel.blue.min(max_value);
+        }
+        self.pixels.resize(expected, Pixels::new(0, 0, 0));
+
+        Ok(Report{warnings})
+    }
+
+    ///load the image ppm from a reader and check it against the spec
+    ///
+    /// in strict mode the violations are an error with all of them, in lenient mode the image is repaired
+    /// and the violations are in the report
+    ///
+    /// # Arguments
+    ///
+    /// * `reader` - the reader of the image
+    /// * `mode` - strict or lenient
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// let (image, report) = Image::read_checked("P3 2 1 255 7 91 43".as_bytes(), Conformance::Lenient)?;
+    /// assert!(!report.is_conformant());
+    /// ```
+    pub fn read_checked<R : Read>(mut reader : R, mode : Conformance) -> Result<(Image, Report), PpmError>{
+        let mut data = Vec::new();
+        reader.read_to_end(&mut data)?;
+        let lines = check_lines(&data);
+        let mut image = read_lenient(&data)?;
+
+        if mode == Conformance::Strict {
+            let mut violations = match image.conform(mode) {
+                Err(PpmError::Nonconformant{ violations }) => violations,
+                Err(error) => return Err(error),
+                Ok(_) => Vec::new(),
+            };
+            violations.extend(lines);
+            if !violations.is_empty() {
+                return Err(PpmError::Nonconformant{ violations });
+            }
+            return Ok((image, Report::default()));
+        }
+
+        let mut report = image.conform(mode)?;
+        report.warnings.extend(lines);
+        Ok((image, report))
+    }
+
+    ///load the image from the file ppm and check it against the spec
+    ///
+    /// # Arguments
+    ///
+    /// * `filename` - the name of the file
+    /// * `mode` - strict or lenient
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// let (image, report) = Image::new_with_file_checked(Path::new("image.ppm"), Conformance::Strict)?;
+    /// ```
+    pub fn new_with_file_checked(filename : &Path, mode : Conformance) -> Result<(Image, Report), PpmError>{
+        let f = File::open(filename)?;
+        Image::read_checked(f, mode)
+    }
+}
+
+/// find the lines longer than 70 characters of a file in a plain format, the end of line is not counted
+///
+/// # Arguments
+///
+/// `data` - the bytes of the file
+///
+/// # Example
+///
+/// ```
+/// assert!(check_lines(b"P3\n1 1\n255\n1 2 3\n").is_empty());
+/// ```
+pub fn check_lines(data : &[u8]) -> Vec<Violation>{
+    let magic = data.iter().skip_while(|byte| byte.is_ascii_whitespace()).take(2).cloned().collect::<Vec<u8>>();
+    if magic != b"P1" && magic != b"P2" && magic != b"P3" {
+        return Vec::new();
+    }
+    let mut violations = Vec::new();
+    for (index, line) in data.split(|&byte| byte == b'\n').enumerate(){
+        let length = if line.ends_with(b"\r") { line.len() - 1 } else { line.len() };
+        if length > MAX_LINE_LEN {
+            violations.push(Violation::LineTooLong{ line : index + 1, length });
+        }
+    }
+    violations
+}
+
+/// read a ppm image without the checks of the samples, the missing samples are not read
+/// and the samples larger than 65535 are clamped
+fn read_lenient(data : &[u8]) -> Result<Image, PpmError>{
+    let mut reader = PnmReader::new(data);
+    let position = reader.position();
+    let magic = reader.read_token()?.unwrap_or_default();
+    if magic != "P3" && magic != "P6" {
+        return Err(PpmError::BadMagic{ magic, position });
+    }
+    let position = reader.position();
+    let width = reader.read_number("width")?;
+    let heigth = reader.read_number("heigth")?;
+    // a sample take up to two bytes
+    let number = match width.checked_mul(heigth).and_then(|size| size.checked_mul(3)) {
+        Some(number) if number.checked_mul(2).is_some() => number,
+        _ => return Err(PpmError::Overflow{ value : format!("{}x{}", width, heigth), position }),
+    };
+    let max_value = reader.read_number("max value")?;
+
+    let mut samples = Vec::new();
+    if magic == "P6" {
+        let sample_size = if max_value < 256 { 1 } else { 2 };
+        let data = reader.read_available(number * sample_size)?;
+        for sample in data.chunks(sample_size).filter(|sample| sample.len() == sample_size){
+            samples.push(if sample_size == 1 { sample[0] as u16 } else { ((sample[0] as u16) << 8) | sample[1] as u16 });
+        }
+    } else {
+        while samples.len() < number {
+            let position = reader.position();
+            let token = match reader.read_token()? {
+                Some(token) => token,
+                None => break,
+            };
+            samples.push(header::parse_number(&token, position)?.min(65535) as u16);
+        }
+    }
+    let len = samples.len() / 3 * 3;
+    samples.truncate(len);
+
+    let mut image = Image::new(images::samples_to_pixels(&samples), heigth, width, magic, max_value);
+    image.comments = reader.take_comments();
+    Ok(image)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_conform_strict(){
+        let mut image = Image::new(vec![Pixels::new(7, 91, 43)], 1, 2, "P3".to_string(), 91);
+        match image.conform(Conformance::Strict) {
+            Err(PpmError::Nonconformant{ violations }) => assert_eq!(vec![Violation::PixelCount{ expected : 2, found : 1 }], violations),
+            _ => panic!("a pixel is missing"),
+        }
+
+        let mut image = Image::new(vec![Pixels::new(7, 91, 43), Pixels::new(300, 0, 92)], 1, 2, "P3".to_string(), 91);
+        match image.conform(Conformance::Strict) {
+            Err(PpmError::Nonconformant{ violations }) =>
+                assert_eq!(vec![Violation::SampleOverMaxval{ count : 2, largest : 300, max_value : 91 }], violations),
+            _ => panic!("two samples are over the max value"),
+        }
+
+        let mut image = Image::new(vec![Pixels::new(0, 0, 0)], 1, 1, "P6".to_string(), 0);
+        match image.conform(Conformance::Strict) {
+            Err(PpmError::Nonconformant{ violations }) => assert_eq!(vec![Violation::MaxValueRange{ max_value : 0 }], violations),
+            _ => panic!("the max value is 0"),
+        }
+
+        let mut image = Image::new(vec![Pixels::new(7, 91, 43)], 1, 1, "P6".to_string(), 91);
+        assert!(image.conform(Conformance::Strict).unwrap().is_conformant());
+    }
+
+    #[test]
+    fn test_conform_lenient() -> Result<(), PpmError>{
+        let mut image = Image::new(vec![Pixels::new(7, 91, 43), Pixels::new(300, 0, 92), Pixels::new(1, 2, 3)], 1, 2, "P3".to_string(), 91);
+        let report = image.conform(Conformance::Lenient)?;
+        assert_eq!(vec![Violation::PixelCount{ expected : 2, found : 3 },
+            Violation::SampleOverMaxval{ count : 2, largest : 300, max_value : 91 }], report.warnings);
+        assert_eq!(vec![Pixels::new(7, 91, 43), Pixels::new(91, 0, 91)], image.pixels);
+
+        let mut image = Image::new(vec![Pixels::new(7, 91, 43)], 2, 1, "P6".to_string(), 0);
+        let report = image.conform(Conformance::Lenient)?;
+        assert_eq!(vec![Violation::MaxValueRange{ max_value : 0 }, Violation::PixelCount{ expected : 2, found : 1 }], report.warnings);
+        assert_eq!(91, image.maxValue);
+        assert_eq!(vec![Pixels::new(7, 91, 43), Pixels::new(0, 0, 0)], image.pixels);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_check_lines(){
+        assert!(check_lines(b"P3\n1 1\n255\n1 2 3\n").is_empty());
+
+        let line = "1 2 3 ".repeat(11) + "1 2 ";
+        let long_line = line.clone() + "34";
+        let data = format!("P3\r\n4 3\r\n255\r\n{}\r\n{}\r\n", line, long_line);
+        assert_eq!(vec![Violation::LineTooLong{ line : 5, length : 72 }], check_lines(data.as_bytes()));
+
+        let data = format!("P6 4 3 255\n{}", long_line);
+        assert!(check_lines(data.as_bytes()).is_empty());
+    }
+
+    #[test]
+    fn test_read_checked() -> Result<(), PpmError>{
+        let (image, report) = Image::read_checked("P3\n2 1\n255\n7 91 43\n10 32 13\n".as_bytes(), Conformance::Strict)?;
+        assert!(report.is_conformant());
+        assert_eq!(2, image.width);
+
+        let data = "P3 2 2 15\n1 2 3 4 5 16 7 8 9\n";
+        match Image::read_checked(data.as_bytes(), Conformance::Strict) {
+            Err(PpmError::Nonconformant{ violations }) => assert_eq!(vec![Violation::PixelCount{ expected : 4, found : 3 },
+                Violation::SampleOverMaxval{ count : 1, largest : 16, max_value : 15 }], violations),
+            _ => panic!("16 is over the max value and a pixel is missing"),
+        }
+        let (image, report) = Image::read_checked(data.as_bytes(), Conformance::Lenient)?;
+        assert_eq!(vec![Violation::PixelCount{ expected : 4, found : 3 },
+            Violation::SampleOverMaxval{ count : 1, largest : 16, max_value : 15 }], report.warnings);
+        assert_eq!(vec![Pixels::new(1, 2, 3), Pixels::new(4, 5, 15), Pixels::new(7, 8, 9), Pixels::new(0, 0, 0)], image.pixels);
+
+        let data = b"P6 1 2 255\n\x01\x02\x03\x04";
+        match Image::read_checked(&data[..], Conformance::Strict) {
+            Err(PpmError::Nonconformant{ violations }) => assert_eq!(vec![Violation::PixelCount{ expected : 2, found : 1 }], violations),
+            _ => panic!("the data is truncated"),
+        }
+        let (image, report) = Image::read_checked(&data[..], Conformance::Lenient)?;
+        assert_eq!(vec![Violation::PixelCount{ expected : 2, found : 1 }], report.warnings);
+        assert_eq!(vec![Pixels::new(1, 2, 3), Pixels::new(0, 0, 0)], image.pixels);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_read_checked_lines() -> Result<(), PpmError>{
+        match Image::new_with_file_checked(Path::new("resource/headers/header_and_pixels_one_line.ppm"), Conformance::Strict) {
+            Err(PpmError::Nonconformant{ violations }) => assert_eq!(1, violations.len()),
+            _ => panic!("the image is on one long line"),
+        }
+        let (image, report) = Image::new_with_file_checked(Path::new("resource/headers/header_and_pixels_one_line.ppm"), Conformance::Lenient)?;
+        match report.warnings[..] {
+            [Violation::LineTooLong{ line : 1, .. }] => {},
+            _ => panic!("only the line is too long"),
+        }
+        assert_eq!(Image::new_with_file(Path::new("resource/ppmImage.ppm"))?.pixels, image.pixels);
+
+        Ok(())
+    }
+}
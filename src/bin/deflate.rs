@@ -0,0 +1,703 @@
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+
+/// the base length of the length codes 257 .. 285
+const LENGTH_BASE : [u16; 29] = [3, 4, 5, 6, 7, 8, 9, 10, 11, 13, 15, 17, 19, 23, 27, 31,
+    35, 43, 51, 59, 67, 83, 99, 115, 131, 163, 195, 227, 258];
+/// the extra bits of the length codes 257 .. 285
+const LENGTH_EXTRA : [u8; 29] = [0, 0, 0, 0, 0, 0, 0, 0, 1, 1, 1, 1, 2, 2, 2, 2,
+    3, 3, 3, 3, 4, 4, 4, 4, 5, 5, 5, 5, 0];
+/// the base distance of the distance codes 0 .. 29
+const DIST_BASE : [u16; 30] = [1, 2, 3, 4, 5, 7, 9, 13, 17, 25, 33, 49, 65, 97, 129, 193,
+    257, 385, 513, 769, 1025, 1537, 2049, 3073, 4097, 6145, 8193, 12289, 16385, 24577];
+/// the extra bits of the distance codes 0 .. 29
+const DIST_EXTRA : [u8; 30] = [0, 0, 0, 0, 1, 1, 2, 2, 3, 3, 4, 4, 5, 5, 6, 6,
+    7, 7, 8, 8, 9, 9, 10, 10, 11, 11, 12, 12, 13, 13];
+/// the order of the lengths of the code length code in a dynamic block
+const CODE_LENGTH_ORDER : [usize; 19] = [16, 17, 18, 0, 8, 7, 9, 6, 10, 5, 11, 4, 12, 3, 13, 2, 14, 1, 15];
+
+const WINDOW_SIZE : usize = 32768;
+const MIN_MATCH : usize = 3;
+const MAX_MATCH : usize = 258;
+/// the number of previous positions tried for a match
+const MAX_CHAIN : usize = 64;
+const HASH_BITS : usize = 15;
+/// the number of symbols of a block before a new Huffman code is built
+const BLOCK_SYMBOLS : usize = 1 << 15;
+
+/// the Adler-32 checksum of the zlib format
+///
+/// # Example
+///
+/// ```
+/// assert_eq!(0x11e60398, adler32(b"Wikipedia"));
+/// ```
+pub(crate) fn adler32(data : &[u8]) -> u32{
+    let mut a = 1u32;
+    let mut b = 0u32;
+    // 5552 bytes is the most that can be summed before the modulo without overflow
+    for chunk in data.chunks(5552){
+        for &byte in chunk{
+            a += byte as u32;
+            b += a;
+        }
+        a %= 65521;
+        b %= 65521;
+    }
+    (b << 16) | a
+}
+
+/// compress the data in the zlib format (RFC 1950) with deflate blocks using dynamic Huffman codes
+///
+/// # Example
+///
+/// ```
+/// let data = zlib_compress(b"aaaaaaaaaaaaaaaa");
+/// assert_eq!(b"aaaaaaaaaaaaaaaa".to_vec(), zlib_decompress(&data, 16)?);
+/// ```
+pub(crate) fn zlib_compress(data : &[u8]) -> Vec<u8>{
+    // deflate with a window of 32K, no dictionary and the default level
+    let mut out = vec![0x78, 0x9c];
+    out.extend(deflate(data));
+    out.extend_from_slice(&adler32(data).to_be_bytes());
+    out
+}
+
+/// decompress the data of the zlib format, with at most `max_len` bytes after decompression
+///
+/// # Example
+///
+/// ```
+/// assert_eq!(b"a".to_vec(), zlib_decompress(&[0x78, 0x9c, 0x4b, 0x04, 0x00, 0x00, 0x62, 0x00, 0x62], 1)?);
+/// ```
+pub(crate) fn zlib_decompress(data : &[u8], max_len : usize) -> Result<Vec<u8>, &'static str>{
+    if data.len() < 6 {
+        return Err("the zlib stream is too short");
+    }
+    if data[0] & 0x0f != 8 || !u16::from_be_bytes([data[0], data[1]]).is_multiple_of(31) {
+        return Err("bad zlib header");
+    }
+    if data[1] & 0x20 != 0 {
+        return Err("zlib dictionaries are not supported");
+    }
+    let (out, used) = inflate(&data[2 ..], max_len)?;
+    let check = &data[2 + used ..];
+    if check.len() < 4 {
+        return Err("missing zlib checksum");
+    }
+    if adler32(&out) != u32::from_be_bytes([check[0], check[1], check[2], check[3]]) {
+        return Err("bad zlib checksum");
+    }
+    Ok(out)
+}
+
+/// a canonical Huffman code for the decoder
+struct Huffman{
+    /// the number of codes of each length
+    counts : [u16; 16],
+    /// the symbols sorted by code
+    symbols : Vec<u16>,
+}
+
+impl Huffman {
+
+    /// build the code from the length of the code of each symbol, 0 for the unused symbols
+    fn new(lengths : &[u8]) -> Result<Huffman, &'static str>{
+        let mut counts = [0u16; 16];
+        for &len in lengths{
+            counts[len as usize] += 1;
+        }
+        let mut left = 1i32;
+        for &count in &counts[1 ..]{
+            left <<= 1;
+            left -= count as i32;
+            if left < 0 {
+                return Err("over-subscribed Huffman code");
+            }
+        }
+        let mut offsets = [0u16; 16];
+        for len in 1 .. 15{
+            offsets[len + 1] = offsets[len] + counts[len];
+        }
+        let mut symbols = vec![0; lengths.len()];
+        for (symbol, &len) in lengths.iter().enumerate(){
+            if len != 0 {
+                symbols[offsets[len as usize] as usize] = symbol as u16;
+                offsets[len as usize] += 1;
+            }
+        }
+        counts[0] = 0;
+        Ok(Huffman{counts, symbols})
+    }
+}
+
+/// a reader of the bits of a deflate stream, the first bit is the lowest of a byte
+struct BitReader<'a>{
+    data : &'a [u8],
+    position : usize,
+    buffer : u32,
+    count : u32,
+}
+
+impl<'a> BitReader<'a> {
+
+    fn bits(&mut self, number : u32) -> Result<u32, &'static str>{
+        while self.count < number {
+            if self.position >= self.data.len() {
+                return Err("the deflate stream is truncated");
+            }
+            self.buffer |= (self.data[self.position] as u32) << self.count;
+            self.position += 1;
+            self.count += 8;
+        }
+        let value = self.buffer & ((1u64 << number) - 1) as u32;
+        self.buffer = if number == 32 { 0 } else { self.buffer >> number };
+        self.count -= number;
+        Ok(value)
+    }
+
+    fn decode(&mut self, huffman : &Huffman) -> Result<u16, &'static str>{
+        let mut code = 0i32;
+        let mut first = 0i32;
+        let mut index = 0i32;
+        for len in 1 .. 16{
+            code |= self.bits(1)? as i32;
+            let count = huffman.counts[len] as i32;
+            if code - count < first {
+                return Ok(huffman.symbols[(index + code - first) as usize]);
+            }
+            index += count;
+            first = (first + count) << 1;
+            code <<= 1;
+        }
+        Err("bad Huffman code")
+    }
+
+    /// drop the bits until the next byte
+    fn align(&mut self){
+        self.buffer = 0;
+        self.count = 0;
+    }
+}
+
+/// decompress a raw deflate stream (RFC 1951), the number of bytes of the stream is returned with the data
+fn inflate(data : &[u8], max_len : usize) -> Result<(Vec<u8>, usize), &'static str>{
+    let mut reader = BitReader{data, position : 0, buffer : 0, count : 0};
+    let mut out = Vec::new();
+    loop {
+        let last = reader.bits(1)? == 1;
+        match reader.bits(2)? {
+            0 => {
+                reader.align();
+                if reader.position + 4 > data.len() {
+                    return Err("the deflate stream is truncated");
+                }
+                let header = &data[reader.position .. reader.position + 4];
+                let len = u16::from_le_bytes([header[0], header[1]]);
+                if len != !u16::from_le_bytes([header[2], header[3]]) {
+                    return Err("bad length of a stored block");
+                }
+                reader.position += 4;
+                let len = len as usize;
+                if reader.position + len > data.len() {
+                    return Err("the deflate stream is truncated");
+                }
+                if out.len() + len > max_len {
+                    return Err("the data is larger than expected");
+                }
+                out.extend_from_slice(&data[reader.position .. reader.position + len]);
+                reader.position += len;
+            },
+            1 => {
+                let (lit_len, dist) = fixed_codes();
+                inflate_block(&mut reader, &mut out, &lit_len, &dist, max_len)?;
+            },
+            2 => {
+                let (lit_len, dist) = dynamic_codes(&mut reader)?;
+                inflate_block(&mut reader, &mut out, &lit_len, &dist, max_len)?;
+            },
+            _ => return Err("bad deflate block type"),
+        }
+        if last {
+            break;
+        }
+    }
+    Ok((out, reader.position))
+}
+
+/// the codes of the blocks with fixed Huffman codes
+fn fixed_codes() -> (Huffman, Huffman){
+    let mut lengths = [0u8; 288];
+    for (symbol, len) in lengths.iter_mut().enumerate(){
+        *len = match symbol {
+            0 ..= 143 => 8,
+            144 ..= 255 => 9,
+            256 ..= 279 => 7,
+            _ => 8,
+        };
+    }
+    // the fixed codes are complete, they can not fail
+    (Huffman::new(&lengths).unwrap(), Huffman::new(&[5; 30]).unwrap())
+}
+
+/// read the codes of a block with dynamic Huffman codes
+fn dynamic_codes(reader : &mut BitReader) -> Result<(Huffman, Huffman), &'static str>{
+    let lit_len_count = reader.bits(5)? as usize + 257;
+    let dist_count = reader.bits(5)? as usize + 1;
+    let code_len_count = reader.bits(4)? as usize + 4;
+    if lit_len_count > 286 || dist_count > 30 {
+        return Err("bad counts of a dynamic block");
+    }
+    let mut code_lengths = [0u8; 19];
+    for &symbol in CODE_LENGTH_ORDER.iter().take(code_len_count){
+        code_lengths[symbol] = reader.bits(3)? as u8;
+    }
+    let code_length = Huffman::new(&code_lengths)?;
+
+    let mut lengths = vec![0u8; lit_len_count + dist_count];
+    let mut index = 0;
+    while index < lengths.len() {
+        let symbol = reader.decode(&code_length)?;
+        let (len, repeat) = match symbol {
+            0 ..= 15 => (symbol as u8, 1),
+            16 => {
+                if index == 0 {
+                    return Err("repeat of no length");
+                }
+                (lengths[index - 1], 3 + reader.bits(2)? as usize)
+            },
+            17 => (0, 3 + reader.bits(3)? as usize),
+            _ => (0, 11 + reader.bits(7)? as usize),
+        };
+        if index + repeat > lengths.len() {
+            return Err("too many lengths in a dynamic block");
+        }
+        for length in &mut lengths[index .. index + repeat]{
+            *length = len;
+        }
+        index += repeat;
+    }
+    if lengths[256] == 0 {
+        return Err("no end of block code");
+    }
+    Ok((Huffman::new(&lengths[.. lit_len_count])?, Huffman::new(&lengths[lit_len_count ..])?))
+}
+
+/// decode the symbols of a block until the end of block
+fn inflate_block(reader : &mut BitReader, out : &mut Vec<u8>, lit_len : &Huffman, dist : &Huffman, max_len : usize)
+    -> Result<(), &'static str>{
+    loop {
+        let symbol = reader.decode(lit_len)? as usize;
+        if symbol < 256 {
+            if out.len() >= max_len {
+                return Err("the data is larger than expected");
+            }
+            out.push(symbol as u8);
+        } else if symbol == 256 {
+            return Ok(());
+        } else {
+            let symbol = symbol - 257;
+            if symbol >= 29 {
+                return Err("bad length code");
+            }
+            let len = LENGTH_BASE[symbol] as usize + reader.bits(LENGTH_EXTRA[symbol] as u32)? as usize;
+            let symbol = reader.decode(dist)? as usize;
+            if symbol >= 30 {
+                return Err("bad distance code");
+            }
+            let distance = DIST_BASE[symbol] as usize + reader.bits(DIST_EXTRA[symbol] as u32)? as usize;
+            if distance > out.len() {
+                return Err("distance before the start of the data");
+            }
+            if out.len() + len > max_len {
+                return Err("the data is larger than expected");
+            }
+            let start = out.len() - distance;
+            // the copy may overlap the bytes it write
+            for index in start .. start + len{
+                let byte = out[index];
+                out.push(byte);
+            }
+        }
+    }
+}
+
+/// a symbol of the LZ77 compression
+#[derive(Clone, Copy)]
+enum Token{
+    Literal(u8),
+    Match{ len : u16, distance : u16 },
+}
+
+/// a writer of the bits of a deflate stream, the first bit is the lowest of a byte
+struct BitWriter{
+    out : Vec<u8>,
+    buffer : u64,
+    count : u32,
+}
+
+impl BitWriter {
+
+    fn bits(&mut self, value : u32, number : u32){
+        self.buffer |= (value as u64) << self.count;
+        self.count += number;
+        while self.count >= 8 {
+            self.out.push(self.buffer as u8);
+            self.buffer >>= 8;
+            self.count -= 8;
+        }
+    }
+
+    fn finish(mut self) -> Vec<u8>{
+        if self.count > 0 {
+            self.out.push(self.buffer as u8);
+        }
+        self.out
+    }
+}
+
+/// compress the data in a raw deflate stream
+fn deflate(data : &[u8]) -> Vec<u8>{
+    let tokens = lz77(data);
+    let mut writer = BitWriter{out : Vec::new(), buffer : 0, count : 0};
+    let mut blocks = tokens.chunks(BLOCK_SYMBOLS).peekable();
+    if blocks.peek().is_none() {
+        // an empty data is a single block with the end of block only
+        write_block(&mut writer, &[], true);
+    }
+    while let Some(block) = blocks.next() {
+        write_block(&mut writer, block, blocks.peek().is_none());
+    }
+    writer.finish()
+}
+
+/// find the repeated strings of the data, with hash chains on 3 bytes
+fn lz77(data : &[u8]) -> Vec<Token>{
+    let hash = |position : usize| {
+        let value = (data[position] as usize) << 16 | (data[position + 1] as usize) << 8 | data[position + 2] as usize;
+        (value.wrapping_mul(2654435761) >> 8) & ((1 << HASH_BITS) - 1)
+    };
+    let mut head = vec![usize::MAX; 1 << HASH_BITS];
+    let mut previous = vec![usize::MAX; WINDOW_SIZE];
+    let insert = |position : usize, head : &mut Vec<usize>, previous : &mut Vec<usize>| {
+        if position + MIN_MATCH <= data.len() {
+            let key = hash(position);
+            previous[position % WINDOW_SIZE] = head[key];
+            head[key] = position;
+        }
+    };
+
+    let mut tokens = Vec::new();
+    let mut position = 0;
+    while position < data.len() {
+        let mut best_len = 0;
+        let mut best_distance = 0;
+        if position + MIN_MATCH <= data.len() {
+            let max_len = MAX_MATCH.min(data.len() - position);
+            let mut candidate = head[hash(position)];
+            let mut chain = 0;
+            while candidate != usize::MAX && position - candidate <= WINDOW_SIZE && chain < MAX_CHAIN {
+                let len = data[candidate ..].iter().zip(&data[position .. position + max_len]).take_while(|&(a, b)| a == b).count();
+                if len > best_len {
+                    best_len = len;
+                    best_distance = position - candidate;
+                    if len == max_len {
+                        break;
+                    }
+                }
+                let next = previous[candidate % WINDOW_SIZE];
+                // the chain is stale when it go back to a position that was overwritten
+                if next == usize::MAX || next >= candidate {
+                    break;
+                }
+                candidate = next;
+                chain += 1;
+            }
+        }
+        if best_len >= MIN_MATCH {
+            tokens.push(Token::Match{ len : best_len as u16, distance : best_distance as u16 });
+            for index in position .. position + best_len{
+                insert(index, &mut head, &mut previous);
+            }
+            position += best_len;
+        } else {
+            tokens.push(Token::Literal(data[position]));
+            insert(position, &mut head, &mut previous);
+            position += 1;
+        }
+    }
+    tokens
+}
+
+/// the code of a length, with its extra bits and their value
+fn length_code(len : u16) -> (usize, u32, u32){
+    let symbol = LENGTH_BASE.iter().rposition(|&base| base <= len).unwrap();
+    (257 + symbol, LENGTH_EXTRA[symbol] as u32, (len - LENGTH_BASE[symbol]) as u32)
+}
+
+/// the code of a distance, with its extra bits and their value
+fn distance_code(distance : u16) -> (usize, u32, u32){
+    let symbol = DIST_BASE.iter().rposition(|&base| base <= distance).unwrap();
+    (symbol, DIST_EXTRA[symbol] as u32, (distance - DIST_BASE[symbol]) as u32)
+}
+
+/// write a block with dynamic Huffman codes
+fn write_block(writer : &mut BitWriter, tokens : &[Token], last : bool){
+    let mut lit_len_freqs = [0usize; 286];
+    let mut dist_freqs = [0usize; 30];
+    for token in tokens{
+        match *token {
+            Token::Literal(byte) => lit_len_freqs[byte as usize] += 1,
+            Token::Match{ len, distance } => {
+                lit_len_freqs[length_code(len).0] += 1;
+                dist_freqs[distance_code(distance).0] += 1;
+            },
+        }
+    }
+    lit_len_freqs[256] = 1;
+    let lit_len_lengths = huffman_lengths(&lit_len_freqs, 15);
+    let mut dist_lengths = huffman_lengths(&dist_freqs, 15);
+    if dist_lengths.iter().all(|&len| len == 0) {
+        // a block without distance still need one distance code
+        dist_lengths[0] = 1;
+    }
+    let lit_len_count = 257.max(lit_len_lengths.iter().rposition(|&len| len != 0).unwrap_or(0) + 1);
+    let dist_count = 1.max(dist_lengths.iter().rposition(|&len| len != 0).unwrap_or(0) + 1);
+
+    // the lengths of the two codes are written with a run-length encoding
+    let mut lengths = lit_len_lengths[.. lit_len_count].to_vec();
+    lengths.extend_from_slice(&dist_lengths[.. dist_count]);
+    let runs = run_lengths(&lengths);
+    let mut code_freqs = [0usize; 19];
+    for &(symbol, _) in &runs{
+        code_freqs[symbol as usize] += 1;
+    }
+    let code_lengths = huffman_lengths(&code_freqs, 7);
+    let code_len_count = 4.max(CODE_LENGTH_ORDER.iter().rposition(|&symbol| code_lengths[symbol] != 0).unwrap_or(0) + 1);
+
+    writer.bits(last as u32, 1);
+    writer.bits(2, 2);
+    writer.bits((lit_len_count - 257) as u32, 5);
+    writer.bits((dist_count - 1) as u32, 5);
+    writer.bits((code_len_count - 4) as u32, 4);
+    for &symbol in CODE_LENGTH_ORDER.iter().take(code_len_count){
+        writer.bits(code_lengths[symbol] as u32, 3);
+    }
+    let length_codes = canonical_codes(&code_lengths);
+    for &(symbol, extra) in &runs{
+        let symbol = symbol as usize;
+        writer.bits(length_codes[symbol], code_lengths[symbol] as u32);
+        match symbol {
+            16 => writer.bits(extra as u32, 2),
+            17 => writer.bits(extra as u32, 3),
+            18 => writer.bits(extra as u32, 7),
+            _ => {},
+        }
+    }
+
+    let lit_len_codes = canonical_codes(&lit_len_lengths);
+    let dist_codes = canonical_codes(&dist_lengths);
+    for token in tokens{
+        match *token {
+            Token::Literal(byte) => writer.bits(lit_len_codes[byte as usize], lit_len_lengths[byte as usize] as u32),
+            Token::Match{ len, distance } => {
+                let (symbol, extra_bits, extra) = length_code(len);
+                writer.bits(lit_len_codes[symbol], lit_len_lengths[symbol] as u32);
+                writer.bits(extra, extra_bits);
+                let (symbol, extra_bits, extra) = distance_code(distance);
+                writer.bits(dist_codes[symbol], dist_lengths[symbol] as u32);
+                writer.bits(extra, extra_bits);
+            },
+        }
+    }
+    writer.bits(lit_len_codes[256], lit_len_lengths[256] as u32);
+}
+
+/// the run-length encoding of the code lengths, as the symbols 0 .. 18 with the value of their extra bits
+fn run_lengths(lengths : &[u8]) -> Vec<(u8, u8)>{
+    let mut runs = Vec::new();
+    let mut index = 0;
+    while index < lengths.len() {
+        let len = lengths[index];
+        let repeat = lengths[index ..].iter().take_while(|&&other| other == len).count();
+        if len == 0 && repeat >= 11 {
+            let repeat = repeat.min(138);
+            runs.push((18, (repeat - 11) as u8));
+            index += repeat;
+        } else if len == 0 && repeat >= 3 {
+            runs.push((17, (repeat - 3) as u8));
+            index += repeat;
+        } else if len != 0 && repeat >= 4 {
+            // the first length is written, the next ones are repeated 3 to 6 at a time
+            runs.push((len, 0));
+            let mut left = repeat - 1;
+            while left >= 3 {
+                let count = left.min(6);
+                runs.push((16, (count - 3) as u8));
+                left -= count;
+            }
+            for _ in 0 .. left{
+                runs.push((len, 0));
+            }
+            index += repeat;
+        } else {
+            runs.push((len, 0));
+            index += 1;
+        }
+    }
+    runs
+}
+
+/// the lengths of a Huffman code for the frequencies, no longer than `max_len` bits
+fn huffman_lengths(freqs : &[usize], max_len : u8) -> Vec<u8>{
+    let mut freqs = freqs.to_vec();
+    loop {
+        let lengths = tree_lengths(&freqs);
+        if lengths.iter().all(|&len| len <= max_len) {
+            return lengths;
+        }
+        // flatten the frequencies until the tree is short enough
+        for freq in freqs.iter_mut().filter(|freq| **freq > 0){
+            *freq = freq.div_ceil(2);
+        }
+    }
+}
+
+/// the depth of each symbol in a Huffman tree of the frequencies
+fn tree_lengths(freqs : &[usize]) -> Vec<u8>{
+    let mut lengths = vec![0u8; freqs.len()];
+    let used : Vec<usize> = (0 .. freqs.len()).filter(|&symbol| freqs[symbol] > 0).collect();
+    if used.len() == 1 {
+        lengths[used[0]] = 1;
+        return lengths;
+    }
+    // the nodes after the symbols are the internal nodes of the tree
+    let mut parents = vec![0usize; freqs.len()];
+    let mut heap : BinaryHeap<Reverse<(usize, usize)>> = used.iter().map(|&symbol| Reverse((freqs[symbol], symbol))).collect();
+    while heap.len() > 1 {
+        let Reverse((freq_a, node_a)) = heap.pop().unwrap();
+        let Reverse((freq_b, node_b)) = heap.pop().unwrap();
+        let node = parents.len();
+        parents.push(0);
+        parents[node_a] = node;
+        parents[node_b] = node;
+        heap.push(Reverse((freq_a + freq_b, node)));
+    }
+    let root = parents.len() - 1;
+    for &symbol in &used{
+        let mut node = symbol;
+        let mut depth = 0;
+        while node != root {
+            node = parents[node];
+            depth += 1;
+        }
+        lengths[symbol] = depth.min(255) as u8;
+    }
+    lengths
+}
+
+/// the canonical codes of the lengths, with the bits reversed to be written from the lowest bit
+fn canonical_codes(lengths : &[u8]) -> Vec<u32>{
+    let mut counts = [0u32; 16];
+    for &len in lengths{
+        counts[len as usize] += 1;
+    }
+    counts[0] = 0;
+    let mut next = [0u32; 16];
+    let mut code = 0;
+    for len in 1 .. 16{
+        code = (code + counts[len - 1]) << 1;
+        next[len] = code;
+    }
+    lengths.iter().map(|&len| {
+        if len == 0 {
+            return 0;
+        }
+        let code = next[len as usize];
+        next[len as usize] += 1;
+        code.reverse_bits() >> (32 - len as u32)
+    }).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_adler32(){
+        assert_eq!(0x11e60398, adler32(b"Wikipedia"));
+        assert_eq!(1, adler32(b""));
+        let data = vec![0xff; 100000];
+        // computed with the definition, without the modulo by chunk
+        let (a, b) = data.iter().fold((1u64, 0u64), |(a, b), &byte| ((a + byte as u64) % 65521, (b + a + byte as u64) % 65521));
+        assert_eq!(((b << 16) | a) as u32, adler32(&data));
+    }
+
+    #[test]
+    fn test_inflate_stored_and_fixed() -> Result<(), &'static str>{
+        // "abc" in a stored block, then "abc" again in a block with fixed codes from zlib
+        let stored = [0x78, 0x01, 0x01, 0x03, 0x00, 0xfc, 0xff, b'a', b'b', b'c', 0x02, 0x4d, 0x01, 0x27];
+        assert_eq!(b"abc".to_vec(), zlib_decompress(&stored, 3)?);
+
+        let fixed = [0x78, 0x9c, 0x4b, 0x4c, 0x4a, 0x06, 0x00, 0x02, 0x4d, 0x01, 0x27];
+        assert_eq!(b"abc".to_vec(), zlib_decompress(&fixed, 3)?);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_compress() -> Result<(), &'static str>{
+        let mut samples : Vec<&[u8]> = vec![b"", b"a", b"abcabcabcabcabcabcabcabc"];
+        let text = "the quick brown fox jumps over the lazy dog ".repeat(200);
+        samples.push(text.as_bytes());
+        let noise : Vec<u8> = (0 .. 70000u32).map(|index| (index.wrapping_mul(2654435761) >> 13) as u8).collect();
+        samples.push(&noise);
+        let zeros = vec![0; 100000];
+        samples.push(&zeros);
+
+        for data in samples{
+            let compressed = zlib_compress(data);
+            assert_eq!(data.to_vec(), zlib_decompress(&compressed, data.len())?);
+        }
+        assert!(zlib_compress(text.as_bytes()).len() < text.len() / 20);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_decompress_errors(){
+        let data = zlib_compress(b"abcabcabcabc");
+        assert_eq!(Err("the data is larger than expected"), zlib_decompress(&data, 5));
+        assert_eq!(Err("bad zlib header"), zlib_decompress(&[0x78, 0x00, 0, 0, 0, 0], 5));
+
+        let mut corrupt = data.clone();
+        let len = corrupt.len();
+        corrupt[len - 1] ^= 1;
+        assert_eq!(Err("bad zlib checksum"), zlib_decompress(&corrupt, 12));
+
+        assert!(zlib_decompress(&data[.. 5], 12).is_err());
+    }
+
+    #[test]
+    fn test_huffman_lengths(){
+        let lengths = huffman_lengths(&[1, 1, 2, 4], 15);
+        assert_eq!(vec![3, 3, 2, 1], lengths);
+
+        // a Fibonacci distribution give a tree deeper than the limit
+        let mut freqs = vec![1, 1];
+        for index in 2 .. 30{
+            let freq = freqs[index - 1] + freqs[index - 2];
+            freqs.push(freq);
+        }
+        let lengths = huffman_lengths(&freqs, 15);
+        assert!(lengths.iter().all(|len| (1 ..= 15).contains(len)));
+        let kraft : f64 = lengths.iter().map(|&len| 0.5f64.powi(len as i32)).sum();
+        assert!(kraft <= 1.0);
+    }
+
+    #[test]
+    fn test_run_lengths(){
+        let lengths : Vec<u8> = [0; 14].iter().chain(&[5; 6]).chain(&[0; 3]).chain(&[7; 2]).cloned().collect();
+        assert_eq!(vec![(18, 3), (5, 0), (16, 2), (17, 0), (7, 0), (7, 0)], run_lengths(&lengths));
+    }
+}
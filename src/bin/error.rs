@@ -0,0 +1,180 @@
+use conformance::Violation;
+
+use std::error;
+use std::fmt;
+use std::io;
+
+/// the position in the file of an error, the first line is 1 and the first byte is 0
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Position{
+    pub line : usize,
+    pub byte : usize,
+}
+
+/// the errors of the loading and the saving of the images
+#[derive(Debug)]
+pub enum PpmError{
+    /// the file can not be read or written
+    Io(io::Error),
+    /// the magic number is not one of the expected formats
+    BadMagic{ magic : String, position : Position },
+    /// a value of the header is missing or is not valid
+    BadHeader{ message : String, position : Position },
+    /// the file end before all the samples of the image
+    Truncated{ expected : usize, found : usize, position : Position },
+    /// a sample is larger than the max value of the header
+    SampleOverMaxval{ sample : usize, max_value : usize, position : Position },
+    /// a number does not fit in the type that hold it
+    Overflow{ value : String, position : Position },
+    /// a row given to a writer has not the width of the header
+    RowLength{ expected : usize, found : usize },
+    /// the number of rows written is not the heigth of the header
+    RowCount{ expected : usize, found : usize },
+    /// the image does not follow the spec, found by the strict checker
+    Nonconformant{ violations : Vec<Violation> },
+    /// the format of the file is not known, from its magic number or its extension
+    UnsupportedFormat(String),
+    /// the data of a compressed or binary format is not valid, like a bad checksum
+    Corrupt{ format : &'static str, message : String },
+}
+
+impl PpmError {
+
+    /// create an error for a bad header
+    ///
+    /// # Arguments
+    ///
+    /// * `message` - what is wrong in the header
+    /// * `position` - where the error is found
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// let error = PpmError::bad_header("missing WIDTH", Position{line : 2, byte : 3});
+    /// ```
+    pub fn bad_header<S : Into<String>>(message : S, position : Position) -> PpmError{
+        PpmError::BadHeader{ message : message.into(), position }
+    }
+
+    /// the position of the error in the file, None for the errors of input/output and of the writers
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// let error = PpmError::bad_header("missing WIDTH", Position{line : 2, byte : 3});
+    /// assert_eq!(Some(Position{line : 2, byte : 3}), error.position());
+    /// ```
+    pub fn position(&self) -> Option<Position>{
+        match self {
+            PpmError::Io(_) |
+            PpmError::RowLength{ .. } |
+            PpmError::RowCount{ .. } |
+            PpmError::Nonconformant{ .. } |
+            PpmError::UnsupportedFormat(_) |
+            PpmError::Corrupt{ .. } => None,
+            PpmError::BadMagic{ position, .. } |
+            PpmError::BadHeader{ position, .. } |
+            PpmError::Truncated{ position, .. } |
+            PpmError::SampleOverMaxval{ position, .. } |
+            PpmError::Overflow{ position, .. } => Some(*position),
+        }
+    }
+}
+
+impl fmt::Display for Position {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "line {}, byte {}", self.line, self.byte)
+    }
+}
+
+impl fmt::Display for PpmError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            PpmError::Io(error) => write!(f, "{}", error),
+            PpmError::BadMagic{ magic, position } => write!(f, "bad magic number {:?} at {}", magic, position),
+            PpmError::BadHeader{ message, position } => write!(f, "bad header at {}: {}", position, message),
+            PpmError::Truncated{ expected, found, position } =>
+                write!(f, "truncated data at {}: {} samples expected, {} found", position, expected, found),
+            PpmError::SampleOverMaxval{ sample, max_value, position } =>
+                write!(f, "sample {} over the max value {} at {}", sample, max_value, position),
+            PpmError::Overflow{ value, position } => write!(f, "{} is too large at {}", value, position),
+            PpmError::RowLength{ expected, found } => write!(f, "row of {} pixels, {} expected", found, expected),
+            PpmError::RowCount{ expected, found } => write!(f, "{} rows written, {} expected", found, expected),
+            PpmError::UnsupportedFormat(name) => write!(f, "the format of {:?} is not supported", name),
+            PpmError::Corrupt{ format, message } => write!(f, "corrupt {} data: {}", format, message),
+            PpmError::Nonconformant{ violations } => {
+                write!(f, "the image does not follow the spec")?;
+                for violation in violations{
+                    write!(f, "; {}", violation)?;
+                }
+                Ok(())
+            },
+        }
+    }
+}
+
+impl error::Error for PpmError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            PpmError::Io(error) => Some(error),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for PpmError {
+    fn from(error: io::Error) -> Self {
+        PpmError::Io(error)
+    }
+}
+
+impl From<PpmError> for io::Error {
+    fn from(error: PpmError) -> Self {
+        match error {
+            PpmError::Io(error) => error,
+            PpmError::Truncated{ .. } => io::Error::new(io::ErrorKind::UnexpectedEof, error),
+            error => io::Error::new(io::ErrorKind::InvalidData, error),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_display(){
+        let error = PpmError::SampleOverMaxval{ sample : 300, max_value : 255, position : Position{line : 4, byte : 20} };
+        assert_eq!("sample 300 over the max value 255 at line 4, byte 20", error.to_string());
+
+        let error = PpmError::bad_header("missing WIDTH", Position{line : 2, byte : 3});
+        assert_eq!("bad header at line 2, byte 3: missing WIDTH", error.to_string());
+
+        let error = PpmError::RowCount{ expected : 40, found : 39 };
+        assert_eq!("39 rows written, 40 expected", error.to_string());
+
+        let error = PpmError::Corrupt{ format : "PNG", message : "bad zlib checksum".to_string() };
+        assert_eq!("corrupt PNG data: bad zlib checksum", error.to_string());
+    }
+
+    #[test]
+    fn test_position(){
+        let error = PpmError::Truncated{ expected : 12, found : 3, position : Position{line : 1, byte : 17} };
+        assert_eq!(Some(Position{line : 1, byte : 17}), error.position());
+
+        let error = PpmError::from(io::Error::new(io::ErrorKind::NotFound, "no file"));
+        assert_eq!(None, error.position());
+
+        let error = PpmError::RowLength{ expected : 4, found : 3 };
+        assert_eq!(None, error.position());
+    }
+
+    #[test]
+    fn test_into_io_error(){
+        let error = PpmError::Truncated{ expected : 12, found : 3, position : Position{line : 1, byte : 17} };
+        assert_eq!(io::ErrorKind::UnexpectedEof, io::Error::from(error).kind());
+
+        let error = PpmError::BadMagic{ magic : "Q3".to_string(), position : Position{line : 1, byte : 0} };
+        assert_eq!(io::ErrorKind::InvalidData, io::Error::from(error).kind());
+    }
+}
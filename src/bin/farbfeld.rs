@@ -0,0 +1,183 @@
+use rgba_image::{RgbaPixels, RgbaImage};
+use error::{PpmError, Position};
+
+use std::fs::File;
+use std::io::BufWriter;
+use std::io::prelude::*;
+use std::path::Path;
+
+/// the magic number at the start of the farbfeld files
+pub(crate) const MAGIC : &[u8] = b"farbfeld";
+/// the size of the header, magic number included
+const HEADER_SIZE : usize = 16;
+/// the max value of the samples of the farbfeld files
+const MAX_VALUE : usize = 65535;
+
+impl RgbaImage {
+
+    ///load the image from the file farbfeld, the max value of the image is 65535
+    ///
+    /// # Arguments
+    ///
+    /// * `filename` - the name of the file
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// let image = RgbaImage::new_with_farbfeld(Path::new("image.ff"))?;
+    /// ```
+    pub fn new_with_farbfeld(filename : &Path) -> Result<RgbaImage, PpmError>{
+        RgbaImage::read_farbfeld(File::open(filename)?)
+    }
+
+    ///load the image from a reader of farbfeld data
+    ///
+    /// # Arguments
+    ///
+    /// * `reader` - the reader of the farbfeld data
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// let image = RgbaImage::read_farbfeld(&bytes[..])?;
+    /// ```
+    pub fn read_farbfeld<R : Read>(mut reader : R) -> Result<RgbaImage, PpmError>{
+        let mut data = Vec::new();
+        reader.read_to_end(&mut data)?;
+        if data.len() < MAGIC.len() || &data[.. MAGIC.len()] != MAGIC {
+            let magic = String::from_utf8_lossy(&data[.. data.len().min(MAGIC.len())]).into_owned();
+            return Err(PpmError::BadMagic{ magic, position : Position{line : 1, byte : 0} });
+        }
+        if data.len() < HEADER_SIZE {
+            return Err(PpmError::Corrupt{ format : "farbfeld", message : "the header is truncated".to_string() });
+        }
+        let width = u32::from_be_bytes([data[8], data[9], data[10], data[11]]) as usize;
+        let heigth = u32::from_be_bytes([data[12], data[13], data[14], data[15]]) as usize;
+        let size = width.checked_mul(heigth)
+            .ok_or_else(|| PpmError::Corrupt{ format : "farbfeld", message : "the image is too large".to_string() })?;
+
+        // each pixel is 4 samples of 2 bytes in big endian
+        let found = (data.len() - HEADER_SIZE) / 8;
+        if found < size {
+            return Err(PpmError::Truncated{ expected : size, found, position : Position{line : 1, byte : HEADER_SIZE + found * 8} });
+        }
+        let pixels = data[HEADER_SIZE ..].chunks_exact(8).take(size).map(|pixel| RgbaPixels::new(
+            u16::from_be_bytes([pixel[0], pixel[1]]),
+            u16::from_be_bytes([pixel[2], pixel[3]]),
+            u16::from_be_bytes([pixel[4], pixel[5]]),
+            u16::from_be_bytes([pixel[6], pixel[7]]))).collect();
+
+        Ok(RgbaImage::new(pixels, heigth, width, MAX_VALUE))
+    }
+
+    ///save the image in a file of farbfeld
+    ///
+    /// the samples are scaled to 0 .. 65535 if the max value of the image is another value
+    ///
+    /// # Arguments
+    ///
+    /// * `filename` - the name of the file
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// RgbaImage::from_image(&image).save_farbfeld(Path::new("image.ff"))?;
+    /// ```
+    pub fn save_farbfeld(&self, filename : &Path) -> Result<(), PpmError>{
+        let mut writer = BufWriter::new(File::create(filename)?);
+        self.write_farbfeld(&mut writer)?;
+        writer.flush()?;
+        Ok(())
+    }
+
+    ///write the image as farbfeld data to a writer
+    ///
+    /// # Arguments
+    ///
+    /// * `writer` - the writer of the farbfeld data
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// let mut bytes = Vec::new();
+    /// image.write_farbfeld(&mut bytes)?;
+    /// ```
+    pub fn write_farbfeld<W : Write>(&self, mut writer : W) -> Result<(), PpmError>{
+        if self.width > u32::MAX as usize || self.heigth > u32::MAX as usize {
+            return Err(PpmError::UnsupportedFormat(format!("farbfeld image of {}x{} pixels", self.width, self.heigth)));
+        }
+        writer.write_all(MAGIC)?;
+        writer.write_all(&(self.width as u32).to_be_bytes())?;
+        writer.write_all(&(self.heigth as u32).to_be_bytes())?;
+
+        let mut row = Vec::with_capacity(self.width * 8);
+        for pixels in self.pixels.chunks(self.width.max(1)){
+            row.clear();
+            for pixel in pixels{
+                let pixel = pixel.rescale(self.maxValue, MAX_VALUE);
+                for sample in &[pixel.red, pixel.green, pixel.blue, pixel.alpha]{
+                    row.extend_from_slice(&sample.to_be_bytes());
+                }
+            }
+            writer.write_all(&row)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use images::Image;
+    use pixels::Pixels;
+    use std::fs;
+
+    #[test]
+    fn test_write_layout() -> Result<(), PpmError>{
+        let image = RgbaImage::new(vec![RgbaPixels::new(255, 128, 0, 255), RgbaPixels::new(1, 2, 3, 0)], 1, 2, 255);
+        let mut data = Vec::new();
+        image.write_farbfeld(&mut data)?;
+
+        assert_eq!(b"farbfeld\0\0\0\x02\0\0\0\x01", &data[.. HEADER_SIZE]);
+        assert_eq!(&[0xff, 0xff, 0x80, 0x80, 0, 0, 0xff, 0xff, 1, 1, 2, 2, 3, 3, 0, 0], &data[HEADER_SIZE ..]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_round_trip() -> Result<(), PpmError>{
+        let pixels = vec![RgbaPixels::new(0, 1000, 65535, 40000), RgbaPixels::new(12345, 54321, 7, 0),
+            RgbaPixels::new(1, 2, 3, 4), RgbaPixels::new(65535, 65535, 65535, 65535)];
+        let image = RgbaImage::new(pixels, 2, 2, 65535);
+        image.save_farbfeld(Path::new("test_save.ff"))?;
+        assert_eq!(image, RgbaImage::new_with_farbfeld(Path::new("test_save.ff"))?);
+        fs::remove_file(Path::new("test_save.ff"))?;
+
+        // the samples of the PPM are scaled from 0 .. 15 to 0 .. 65535, and back
+        let ppm = Image::new_with_file(Path::new("resource/ppmImage.ppm"))?;
+        let mut data = Vec::new();
+        RgbaImage::from_image(&ppm).write_farbfeld(&mut data)?;
+        let image = RgbaImage::read_farbfeld(&data[..])?;
+        assert_eq!(RgbaImage::from_image_scaled(&ppm, 65535), image);
+        assert_eq!(ppm.pixels, image.rescale(15).to_image().pixels);
+        assert_eq!(Pixels::new(65535, 0, 65535), image.pixels[3].to_pixels());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_read_errors(){
+        match RgbaImage::read_farbfeld(&b"farbfelt\0\0\0\x01\0\0\0\x01"[..]) {
+            Err(PpmError::BadMagic{ magic, .. }) => assert_eq!("farbfelt", magic),
+            _ => panic!("the magic number is wrong"),
+        }
+        match RgbaImage::read_farbfeld(&b"farbfeld\0\0\0\x01"[..]) {
+            Err(PpmError::Corrupt{ .. }) => {},
+            _ => panic!("the header is truncated"),
+        }
+        match RgbaImage::read_farbfeld(&b"farbfeld\0\0\0\x02\0\0\0\x01\0\0\0\0\0\0\0\0\0\0"[..]) {
+            Err(PpmError::Truncated{ expected, found, .. }) => assert_eq!((2, 1), (expected, found)),
+            _ => panic!("the pixels are truncated"),
+        }
+    }
+}
@@ -0,0 +1,398 @@
+use pixels::Pixels;
+use images::Image;
+use image_view::ImageView;
+use header::PnmReader;
+use error::PpmError;
+use gray_image::GrayImage;
+
+use std::fs::File;
+use std::io::BufReader;
+use std::io::prelude::*;
+use std::path::Path;
+
+/// a floating-point image of the PFM format, gray ("Pf") or RGB ("PF")
+///
+/// the samples are stored row after row from the top of the image,
+/// each pixel has `channels` samples
+#[derive(Clone, Debug, PartialEq)]
+pub struct FloatImage{
+    pub pixels : Vec<f32>,
+    pub heigth : usize,
+    pub width : usize,
+    pub channels : usize,
+    pub scale : f32,
+}
+
+impl FloatImage {
+
+    /// create a new floating-point image, a number of channels other than 1 or 3 is not supported
+    ///
+    /// # Arguments
+    ///
+    /// * `pixels` - a vec contain the samples of the image
+    /// * `heigth` - heigth of the image
+    /// * `width` - width of the image
+    /// * `channels` - 1 for a gray image, 3 for a RGB image
+    /// * `scale` - the scale factor of the file, negative for little-endian samples
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// let image = FloatImage::new(vec![0.5, 1.5, 2.0], 1, 3, 1, -1.0)?;
+    /// ```
+    pub fn new(pixels : Vec<f32>, heigth : usize, width : usize, channels : usize, scale : f32) -> Result<FloatImage, PpmError>{
+        if magic(channels).is_none() {
+            return Err(unsupported_channels(channels));
+        }
+        Ok(FloatImage{pixels, heigth, width, channels, scale})
+    }
+
+    ///load the image from the file pfm
+    ///
+    /// # Arguments
+    ///
+    /// * `filename` - the name of the file
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// let image = FloatImage::new(vec![0.5, 1.5, 2.0], 1, 3, 1, -1.0)?;
+    /// image.save(Path::new("test_image.pfm"))?;
+    /// let image_load = FloatImage::new_with_file(Path::new("test_image.pfm"));
+    /// assert_eq!(image, image_load?);
+    ///
+    /// fs::remove_file(Path::new("test_image.pfm"))?;
+    /// ```
+    pub fn new_with_file(filename: &Path) -> Result<FloatImage, PpmError>{
+        let f = File::open(filename)?;
+        let mut reader = PnmReader::new(BufReader::new(f));
+
+        let position = reader.position();
+        let header = reader.read_header()?;
+        let channels = match (1 ..= 3).find(|&channels| magic(channels) == Some(&*header.magic)) {
+            Some(channels) => channels,
+            None => return Err(PpmError::BadMagic{ magic : header.magic, position }),
+        };
+        let heigth = header.heigth;
+        let width = header.width;
+        let position = reader.position();
+        let scale = match reader.read_token()? {
+            Some(scale) => scale.parse::<f32>().map_err(|_| PpmError::bad_header(format!("bad scale {:?}", scale), position))?,
+            None => return Err(PpmError::bad_header("missing scale", position)),
+        };
+        if scale == 0.0 || !scale.is_finite() {
+            return Err(PpmError::bad_header("the scale of a PFM image must be a number other than 0", position));
+        }
+
+        let row_len = width * channels;
+        let data = reader.read_raw(heigth * row_len, 4)?;
+
+        let mut pixels = Vec::with_capacity(heigth * row_len);
+        // the rows of the file go from the bottom to the top of the image
+        for y in (0 .. heigth).rev(){
+            let row = &data[y * row_len * 4 .. (y + 1) * row_len * 4];
+            for sample in row.chunks(4){
+                let bytes = [sample[0], sample[1], sample[2], sample[3]];
+                let bits = if scale < 0.0 { u32::from_le_bytes(bytes) } else { u32::from_be_bytes(bytes) };
+                pixels.push(f32::from_bits(bits));
+            }
+        }
+
+        FloatImage::new(pixels, heigth, width, channels, scale)
+    }
+
+    ///save the image in a file of pfm, the sign of `scale` choose the byte order,
+    ///an image without 1 or 3 channels is not supported
+    ///
+    /// # Arguments
+    ///
+    /// * `filename` - the name of the file
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// let image = FloatImage::new(vec![0.5, 1.5, 2.0], 1, 3, 1, -1.0)?;
+    /// image.save(Path::new("test_image.pfm"))?;
+    /// ```
+    pub fn save(&self, filename : &Path) -> Result<(), PpmError>{
+        let magic = match magic(self.channels) {
+            Some(magic) => magic,
+            None => return Err(unsupported_channels(self.channels)),
+        };
+        let mut f = File::create(filename)?;
+        let mut buf = String::new();
+        buf = buf + magic + "\n";
+        buf = buf + &format!("{} {}", self.width, self.heigth) + "\n";
+        buf = buf + &format!("{:?}", self.scale) + "\n";
+        f.write_all(buf.as_bytes())?;
+
+        let row_len = self.width * self.channels;
+        let mut data = Vec::with_capacity(self.pixels.len() * 4);
+        for row in self.pixels.chunks(row_len.max(1)).rev(){
+            for sample in row{
+                let bits = sample.to_bits();
+                if self.scale < 0.0 {
+                    data.extend_from_slice(&bits.to_le_bytes());
+                } else {
+                    data.extend_from_slice(&bits.to_be_bytes());
+                }
+            }
+        }
+        f.write_all(&data)?;
+        Ok(())
+    }
+
+    ///transform the RGB image to a floating-point image, the samples are divided by the max value,
+    ///a max value of 0 is taken as 1
+    ///
+    /// # Arguments
+    ///
+    /// * `image` - the RGB image
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// let image_float = FloatImage::from_image(&image);
+    /// ```
+    pub fn from_image<I : ImageView>(image : &I) -> FloatImage{
+        let max_value = image.max_value().max(1) as f32;
+        let mut pixels = Vec::with_capacity(image.heigth() * image.width() * 3);
+        for pixel in image.iter_pixels(){
+            pixels.push(pixel.red as f32 / max_value);
+            pixels.push(pixel.green as f32 / max_value);
+            pixels.push(pixel.blue as f32 / max_value);
+        }
+
+        FloatImage{pixels, heigth : image.heigth(), width : image.width(), channels : 3, scale : -1.0}
+    }
+
+    ///transform the gray image to a floating-point image, the samples are divided by the max value,
+    ///a max value of 0 is taken as 1
+    ///
+    /// # Arguments
+    ///
+    /// * `image` - the gray image
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// let image_float = FloatImage::from_gray(&image_gray);
+    /// ```
+    pub fn from_gray(image : &GrayImage) -> FloatImage{
+        let max_value = image.maxValue.max(1) as f32;
+        let pixels = image.pixels.iter().map(|&gray| gray as f32 / max_value).collect();
+
+        FloatImage{pixels, heigth : image.heigth, width : image.width, channels : 1, scale : -1.0}
+    }
+
+    ///transform the image to a binary (P6) RGB image, the samples out of 0.0 .. 1.0 are clamped
+    ///
+    /// # Arguments
+    ///
+    /// * `max_value` - the max value of the RGB image, for the sample 1.0
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// let image = image_float.to_image(255);
+    /// ```
+    pub fn to_image(&self, max_value : u16) -> Image{
+        self.to_image_with(max_value, 1.0)
+    }
+
+    ///transform the image to a binary (P6) RGB image, the largest sample of the image become `max_value`
+    ///
+    /// # Arguments
+    ///
+    /// * `max_value` - the max value of the RGB image
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// let image = image_hdr.to_image_normalized(65535);
+    /// ```
+    pub fn to_image_normalized(&self, max_value : u16) -> Image{
+        let largest = self.pixels.iter().cloned().filter(|sample| sample.is_finite()).fold(0.0, f32::max);
+        self.to_image_with(max_value, if largest > 0.0 { largest } else { 1.0 })
+    }
+
+    /// transform the image to a RGB image, the sample `white` become `max_value`
+    fn to_image_with(&self, max_value : u16, white : f32) -> Image{
+        let to_sample = |sample : f32| {
+            let level = (sample / white).clamp(0.0, 1.0) * max_value as f32;
+            // clamp keep NaN, it become black
+            if level.is_nan() { 0 } else { level.round() as u16 }
+        };
+        // the fields are public, the number of channels is not trusted
+        let pixels = self.pixels.chunks(self.channels.max(1)).map(|samples| {
+            if samples.len() < 3 {
+                let gray = to_sample(samples[0]);
+                Pixels::new(gray, gray, gray)
+            } else {
+                Pixels::new(to_sample(samples[0]), to_sample(samples[1]), to_sample(samples[2]))
+            }
+        }).collect();
+
+        Image::new(pixels, self.heigth, self.width, "P6".to_string(), max_value as usize)
+    }
+}
+
+/// the magic number of a PFM image with `channels` samples by pixel
+fn magic(channels : usize) -> Option<&'static str>{
+    match channels {
+        1 => Some("Pf"),
+        3 => Some("PF"),
+        _ => None,
+    }
+}
+
+/// the error of a PFM image without 1 or 3 channels
+fn unsupported_channels(channels : usize) -> PpmError{
+    PpmError::UnsupportedFormat(format!("PFM image with {} channels", channels))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use error::Position;
+    use std::fs;
+
+    #[test]
+    fn test_save_little_endian() -> std::io::Result<()>{
+        let image = FloatImage::new(vec![0.5, 1.5, 2.0, -3.25, 100.0, 0.0], 2, 1, 3, -1.0)?;
+        image.save(Path::new("test_save_float_le.pfm"))?;
+        let image_load = FloatImage::new_with_file(Path::new("test_save_float_le.pfm"));
+        assert_eq!(image, image_load?);
+
+        let data = fs::read(Path::new("test_save_float_le.pfm"))?;
+        assert_eq!(b"PF\n1 2\n-1.0\n", &data[.. 12]);
+        // the bottom row is written first
+        assert_eq!(&(-3.25f32).to_bits().to_le_bytes(), &data[12 .. 16]);
+
+        fs::remove_file(Path::new("test_save_float_le.pfm"))?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_save_big_endian() -> std::io::Result<()>{
+        let image = FloatImage::new(vec![0.5, 1.5, 2.0, 0.25], 2, 2, 1, 4.0)?;
+        image.save(Path::new("test_save_float_be.pfm"))?;
+        let image_load = FloatImage::new_with_file(Path::new("test_save_float_be.pfm"));
+        assert_eq!(image, image_load?);
+
+        let data = fs::read(Path::new("test_save_float_be.pfm"))?;
+        assert_eq!(&2.0f32.to_bits().to_be_bytes(), &data[data.len() - 16 .. data.len() - 12]);
+
+        fs::remove_file(Path::new("test_save_float_be.pfm"))?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_new_with_file_errors() -> std::io::Result<()>{
+        let mut f_w = File::create(Path::new("test_float_errors.pfm"))?;
+        f_w.write_all(b"PF\n1 1\nscale\n")?;
+        match FloatImage::new_with_file(Path::new("test_float_errors.pfm")) {
+            Err(PpmError::BadHeader{ position, .. }) => assert_eq!(Position{line : 3, byte : 7}, position),
+            _ => panic!("the scale is not a number"),
+        }
+
+        let mut f_w = File::create(Path::new("test_float_errors.pfm"))?;
+        f_w.write_all(b"Pf\n2 1\n-1.0\n\x00\x00\x00\x00")?;
+        match FloatImage::new_with_file(Path::new("test_float_errors.pfm")) {
+            Err(PpmError::Truncated{ expected, found, .. }) => assert_eq!((2, 1), (expected, found)),
+            _ => panic!("one sample is missing"),
+        }
+
+        fs::remove_file(Path::new("test_float_errors.pfm"))?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_from_image(){
+        let pixels = vec![Pixels::new(0, 51, 255)];
+        let image = Image::new(pixels, 1, 1, "P6".to_string(), 255);
+
+        let image_float = FloatImage::from_image(&image);
+
+        assert_eq!(vec![0.0, 0.2, 1.0], image_float.pixels);
+        assert_eq!(image, image_float.to_image(255));
+    }
+
+    #[test]
+    fn test_from_gray(){
+        let image_gray = GrayImage::new(vec![0, 50, 100], 1, 3, "P5".to_string(), 100);
+
+        let image_float = FloatImage::from_gray(&image_gray);
+
+        assert_eq!(vec![0.0, 0.5, 1.0], image_float.pixels);
+        assert_eq!(image_gray.to_image(), image_float.to_image(100));
+    }
+
+    #[test]
+    fn test_to_image_clamp() -> Result<(), PpmError>{
+        let image_float = FloatImage::new(vec![-1.0, 0.5, 4.0], 1, 1, 3, -1.0)?;
+
+        let image = image_float.to_image(255);
+
+        assert_eq!(vec![Pixels::new(0, 128, 255)], image.pixels);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_channels() -> Result<(), PpmError>{
+        let mut image = FloatImage::new(vec![0.5, 1.0], 1, 1, 1, -1.0)?;
+        image.channels = 2;
+        match image.save(Path::new("test_float_channels.pfm")) {
+            Err(PpmError::UnsupportedFormat(_)) => {},
+            _ => panic!("a PFM image has 1 or 3 channels"),
+        }
+        assert!(!Path::new("test_float_channels.pfm").exists());
+
+        image.channels = 0;
+        assert_eq!(vec![Pixels::new(128, 128, 128), Pixels::new(255, 255, 255)], image.to_image(255).pixels);
+
+        for &channels in [0, 2, 4].iter(){
+            match FloatImage::new(vec![0.5; 4], 1, 1, channels, -1.0) {
+                Err(PpmError::UnsupportedFormat(_)) => {},
+                _ => panic!("a PFM image has 1 or 3 channels, not {}", channels),
+            }
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_from_max_value_zero(){
+        let image_gray = GrayImage::new(vec![0, 0], 1, 2, "P5".to_string(), 0);
+
+        let image_float = FloatImage::from_gray(&image_gray);
+
+        assert_eq!(vec![0.0, 0.0], image_float.pixels);
+    }
+
+    #[test]
+    fn test_to_image_nan() -> Result<(), PpmError>{
+        let image_float = FloatImage::new(vec![f32::NAN, 0.5, f32::INFINITY], 1, 1, 3, -1.0)?;
+
+        let image = image_float.to_image(255);
+
+        assert_eq!(vec![Pixels::new(0, 128, 255)], image.pixels);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_to_image_normalized() -> Result<(), PpmError>{
+        let image_float = FloatImage::new(vec![0.0, 2.0, 4.0], 1, 3, 1, -1.0)?;
+
+        let image = image_float.to_image_normalized(100);
+
+        assert_eq!(vec![Pixels::new(0, 0, 0), Pixels::new(50, 50, 50), Pixels::new(100, 100, 100)], image.pixels);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,626 @@
+use images::{self, Image};
+use error::PpmError;
+
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::BufWriter;
+use std::io::prelude::*;
+use std::path::Path;
+
+/// the signature and the version of the files written
+pub(crate) const SIGNATURE : &[u8] = b"GIF89a";
+/// the signature of the first version of GIF
+pub(crate) const SIGNATURE_87 : &[u8] = b"GIF87a";
+/// the largest number of colors of a palette
+const MAX_COLORS : usize = 256;
+/// the largest code of the LZW compression, the codes have 12 bits at most
+const MAX_CODES : u16 = 4096;
+/// the largest size of the sub-blocks of data
+const MAX_BLOCK : usize = 255;
+
+/// a frame of an animation, an image with the time it is shown
+#[derive(Clone, Debug, PartialEq)]
+pub struct GifFrame{
+    pub image : Image,
+    /// the time the frame is shown, in hundredths of a second
+    pub delay : u16,
+}
+
+/// the palettes of the frames of a GIF file
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum GifPalette{
+    /// one palette of 256 colors at most for all the frames, built from the colors of all the frames
+    Global,
+    /// a palette for each frame, built from the colors of the frame alone
+    PerFrame,
+}
+
+/// the mapping of the colors of the images to the colors of the palette
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Dithering{
+    /// each pixel takes the nearest color of the palette
+    None,
+    /// the error of each pixel is spread on the next pixels, the gradients have no bands
+    FloydSteinberg,
+}
+
+/// the number of times an animation is played
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Repeat{
+    /// the animation is played once, the file has no loop extension
+    Once,
+    /// the animation is played forever
+    Forever,
+    /// the animation is played once, then repeated this number of times
+    Times(u16),
+}
+
+impl GifFrame {
+
+    /// create a frame of an animation
+    ///
+    /// # Arguments
+    ///
+    /// * `image` - the image of the frame
+    /// * `delay` - the time the frame is shown, in hundredths of a second
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// let frame = GifFrame::new(image, 4);
+    /// ```
+    pub fn new(image : Image, delay : u16) -> GifFrame{
+        GifFrame{image, delay}
+    }
+}
+
+///save the frames in a file of gif, as an animation
+///
+/// # Arguments
+///
+/// * `filename` - the name of the file
+/// * `frames` - the frames of the animation, in the order they are shown
+/// * `repeat` - the number of times the animation is played
+/// * `palette` - a global palette or a palette for each frame
+/// * `dithering` - the mapping of the colors to the palette
+///
+/// # Example
+///
+/// ```
+/// let frames = ImageStream::new_with_file(Path::new("steps.ppm"))?
+///     .map(|image| image.map(|image| GifFrame::new(image, 5)))
+///     .collect::<Result<Vec<GifFrame>, PpmError>>()?;
+/// save_gif(Path::new("steps.gif"), &frames, Repeat::Forever, GifPalette::Global, Dithering::FloydSteinberg)?;
+/// ```
+pub fn save_gif(filename : &Path, frames : &[GifFrame], repeat : Repeat, palette : GifPalette, dithering : Dithering) -> Result<(), PpmError>{
+    let mut writer = BufWriter::new(File::create(filename)?);
+    write_gif(&mut writer, frames, repeat, palette, dithering)?;
+    writer.flush()?;
+    Ok(())
+}
+
+///write the frames as GIF89a data to a writer
+///
+/// the samples are scaled to 0 .. 255 and the colors are reduced to 256 by median cut if
+/// there are more; the screen has the size of the largest frame, the frames are at its top left
+///
+/// # Arguments
+///
+/// * `writer` - the writer of the GIF data
+/// * `frames` - the frames of the animation, in the order they are shown
+/// * `repeat` - the number of times the animation is played
+/// * `palette` - a global palette or a palette for each frame
+/// * `dithering` - the mapping of the colors to the palette
+///
+/// # Example
+///
+/// ```
+/// let mut bytes = Vec::new();
+/// write_gif(&mut bytes, &[GifFrame::new(image, 0)], Repeat::Once, GifPalette::PerFrame, Dithering::None)?;
+/// ```
+pub fn write_gif<W : Write>(mut writer : W, frames : &[GifFrame], repeat : Repeat, palette : GifPalette, dithering : Dithering) -> Result<(), PpmError>{
+    if frames.is_empty() {
+        return Err(PpmError::UnsupportedFormat("GIF without frames".to_string()));
+    }
+    let width = frames.iter().map(|frame| frame.image.width).max().unwrap_or(0);
+    let heigth = frames.iter().map(|frame| frame.image.heigth).max().unwrap_or(0);
+    if frames.iter().any(|frame| frame.image.width == 0 || frame.image.heigth == 0) || width > u16::MAX as usize || heigth > u16::MAX as usize {
+        return Err(PpmError::UnsupportedFormat(format!("GIF image of {}x{} pixels", width, heigth)));
+    }
+
+    // the colors of the frames with 8 bits samples
+    let frame_colors : Vec<Vec<[u8; 3]>> = frames.iter().map(|frame| {
+        let scale = |sample : u16| images::scale_sample(sample, frame.image.maxValue, 255) as u8;
+        frame.image.pixels.iter().map(|pixel| [scale(pixel.red), scale(pixel.green), scale(pixel.blue)]).collect()
+    }).collect();
+    let global = if palette == GifPalette::Global {
+        Some(quantize(&count_colors(frame_colors.iter().flatten()), MAX_COLORS))
+    } else {
+        None
+    };
+
+    let mut out = SIGNATURE.to_vec();
+    out.extend_from_slice(&(width as u16).to_le_bytes());
+    out.extend_from_slice(&(heigth as u16).to_le_bytes());
+    match &global {
+        // the global table, 8 bits by sample, then the index of the background and no aspect ratio
+        Some(colors) => out.extend_from_slice(&[0x80 | 0x70 | (table_bits(colors.len()) - 1), 0, 0]),
+        None => out.extend_from_slice(&[0x70, 0, 0]),
+    }
+    if let Some(colors) = &global {
+        write_color_table(&mut out, colors);
+    }
+    let loops = match repeat {
+        Repeat::Once => None,
+        Repeat::Forever => Some(0),
+        Repeat::Times(times) => Some(times),
+    };
+    if let Some(loops) = loops {
+        out.extend_from_slice(&[0x21, 0xff, 11]);
+        out.extend_from_slice(b"NETSCAPE2.0");
+        out.extend_from_slice(&[3, 1]);
+        out.extend_from_slice(&loops.to_le_bytes());
+        out.push(0);
+    }
+    writer.write_all(&out)?;
+
+    for (frame, colors) in frames.iter().zip(frame_colors.iter()){
+        let palette = match &global {
+            Some(palette) => palette.clone(),
+            None => quantize(&count_colors(colors.iter()), MAX_COLORS),
+        };
+        let indexes = map_colors(colors, frame.image.width, &palette, dithering);
+
+        // the graphic control extension, the frame is kept under the next frame
+        let mut out = vec![0x21, 0xf9, 4, 1 << 2];
+        out.extend_from_slice(&frame.delay.to_le_bytes());
+        out.extend_from_slice(&[0, 0]);
+        out.push(0x2c);
+        out.extend_from_slice(&[0, 0, 0, 0]);
+        out.extend_from_slice(&(frame.image.width as u16).to_le_bytes());
+        out.extend_from_slice(&(frame.image.heigth as u16).to_le_bytes());
+        let bits = table_bits(palette.len());
+        if global.is_none() {
+            out.push(0x80 | (bits - 1));
+            write_color_table(&mut out, &palette);
+        } else {
+            out.push(0);
+        }
+        // the codes of the indexes start at 2 bits
+        let min_code_size = bits.max(2);
+        out.push(min_code_size);
+        for block in lzw_encode(&indexes, min_code_size).chunks(MAX_BLOCK){
+            out.push(block.len() as u8);
+            out.extend_from_slice(block);
+        }
+        out.push(0);
+        writer.write_all(&out)?;
+    }
+    writer.write_all(&[0x3b])?;
+    Ok(())
+}
+
+/// the number of bits of the indexes of a palette, the tables have 2 colors at least
+fn table_bits(colors : usize) -> u8{
+    let mut bits = 1;
+    while (1 << bits) < colors {
+        bits += 1;
+    }
+    bits
+}
+
+/// write a table of colors, filled with black up to a power of 2
+fn write_color_table(out : &mut Vec<u8>, colors : &[[u8; 3]]){
+    for color in colors{
+        out.extend_from_slice(color);
+    }
+    for _ in colors.len() .. 1 << table_bits(colors.len()){
+        out.extend_from_slice(&[0, 0, 0]);
+    }
+}
+
+/// the number of pixels of each color
+fn count_colors<'a, I : Iterator<Item = &'a [u8; 3]>>(colors : I) -> HashMap<[u8; 3], usize>{
+    let mut counts = HashMap::new();
+    for &color in colors{
+        *counts.entry(color).or_insert(0) += 1;
+    }
+    counts
+}
+
+/// the palette of `max_colors` colors at most for the colors and their counts, by median cut
+///
+/// the colors are kept if they are not too many; else the box of colors with the widest range
+/// is cut in two at the median of the pixels, until there are `max_colors` boxes, and each box
+/// gives the mean of its pixels
+fn quantize(counts : &HashMap<[u8; 3], usize>, max_colors : usize) -> Vec<[u8; 3]>{
+    let mut colors : Vec<([u8; 3], usize)> = counts.iter().map(|(&color, &count)| (color, count)).collect();
+    // the order of a hash map changes from a run to the other, the palette must not
+    colors.sort_unstable();
+    if colors.len() <= max_colors {
+        return colors.iter().map(|&(color, _)| color).collect();
+    }
+
+    let range = |colors : &[([u8; 3], usize)], channel : usize| {
+        let (min, max) = colors.iter().fold((255, 0), |(min, max), (color, _)| (color[channel].min(min), color[channel].max(max)));
+        max - min
+    };
+    let mut boxes = vec![colors];
+    while boxes.len() < max_colors {
+        // the box with the widest channel, that has two colors at least
+        let widest = boxes.iter().enumerate().filter(|(_, colors)| colors.len() > 1)
+            .map(|(index, colors)| (index, (0 .. 3).map(|channel| (range(colors, channel), channel)).max().unwrap_or((0, 0))))
+            .max_by_key(|&(_, (range, _))| range);
+        let (index, channel) = match widest {
+            Some((index, (_, channel))) => (index, channel),
+            None => break,
+        };
+        let mut colors = boxes.swap_remove(index);
+        colors.sort_unstable_by_key(|(color, _)| color[channel]);
+        let total : usize = colors.iter().map(|&(_, count)| count).sum();
+        let mut half = 0;
+        let mut cut = 1;
+        for (position, &(_, count)) in colors.iter().enumerate(){
+            half += count;
+            if half * 2 >= total {
+                cut = position + 1;
+                break;
+            }
+        }
+        let cut = cut.clamp(1, colors.len() - 1);
+        let upper = colors.split_off(cut);
+        boxes.push(colors);
+        boxes.push(upper);
+    }
+
+    boxes.iter().map(|colors| {
+        let total : usize = colors.iter().map(|&(_, count)| count).sum();
+        let mut mean = [0u8; 3];
+        for (channel, sample) in mean.iter_mut().enumerate(){
+            let sum : usize = colors.iter().map(|(color, count)| color[channel] as usize * count).sum();
+            *sample = ((sum + total / 2) / total) as u8;
+        }
+        mean
+    }).collect()
+}
+
+/// the index of the color of the palette nearest to a color
+fn nearest(palette : &[[u8; 3]], color : [i32; 3]) -> usize{
+    let distance = |other : &[u8; 3]| (0 .. 3).map(|channel| (other[channel] as i32 - color[channel]).pow(2)).sum::<i32>();
+    (0 .. palette.len()).min_by_key(|&index| distance(&palette[index])).unwrap_or(0)
+}
+
+/// the indexes in the palette of the pixels of a frame
+///
+/// # Arguments
+///
+/// * `colors` - the colors of the pixels, row by row
+/// * `width` - the width of the frame
+/// * `palette` - the colors of the palette
+/// * `dithering` - the mapping of the colors to the palette
+fn map_colors(colors : &[[u8; 3]], width : usize, palette : &[[u8; 3]], dithering : Dithering) -> Vec<u8>{
+    let mut cache = HashMap::new();
+    let mut lookup = |color : [i32; 3]| *cache.entry(color).or_insert_with(|| nearest(palette, color));
+    if dithering == Dithering::None {
+        return colors.iter().map(|color| lookup([color[0] as i32, color[1] as i32, color[2] as i32]) as u8).collect();
+    }
+
+    // the errors spread on the current row and on the next row, with a column of margin on each side
+    let mut errors = vec![[0i32; 3]; width + 2];
+    let mut next_errors = vec![[0i32; 3]; width + 2];
+    let mut indexes = Vec::with_capacity(colors.len());
+    for row in colors.chunks(width){
+        for (x, color) in row.iter().enumerate(){
+            let mut wanted = [0i32; 3];
+            for channel in 0 .. 3{
+                // the errors are in sixteenths
+                wanted[channel] = (color[channel] as i32 + errors[x + 1][channel] / 16).clamp(0, 255);
+            }
+            let index = lookup(wanted);
+            indexes.push(index as u8);
+            for channel in 0 .. 3{
+                let error = wanted[channel] - palette[index][channel] as i32;
+                errors[x + 2][channel] += error * 7;
+                next_errors[x][channel] += error * 3;
+                next_errors[x + 1][channel] += error * 5;
+                next_errors[x + 2][channel] += error;
+            }
+        }
+        errors = std::mem::replace(&mut next_errors, vec![[0i32; 3]; width + 2]);
+    }
+    indexes
+}
+
+/// the LZW compression of the indexes of a frame, with the codes packed from the lowest bit
+///
+/// # Arguments
+///
+/// * `indexes` - the indexes of the pixels in the palette
+/// * `min_code_size` - the number of bits of the indexes, the codes start with one bit more
+fn lzw_encode(indexes : &[u8], min_code_size : u8) -> Vec<u8>{
+    let clear = 1u16 << min_code_size;
+    let end = clear + 1;
+    let mut out = Vec::with_capacity(indexes.len() / 2 + 16);
+    let mut buffer = 0u32;
+    let mut count = 0u32;
+    let mut write = |code : u16, size : u8, out : &mut Vec<u8>| {
+        buffer |= (code as u32) << count;
+        count += size as u32;
+        while count >= 8 {
+            out.push(buffer as u8);
+            buffer >>= 8;
+            count -= 8;
+        }
+    };
+
+    let mut table : HashMap<(u16, u8), u16> = HashMap::new();
+    let mut size = min_code_size + 1;
+    let mut next = end + 1;
+    write(clear, size, &mut out);
+    let mut prefix = match indexes.first() {
+        Some(&index) => index as u16,
+        None => {
+            write(end, size, &mut out);
+            write(0, 7, &mut out);
+            return out;
+        },
+    };
+    for &index in &indexes[1 ..]{
+        if let Some(&code) = table.get(&(prefix, index)) {
+            prefix = code;
+            continue;
+        }
+        write(prefix, size, &mut out);
+        if next < MAX_CODES {
+            table.insert((prefix, index), next);
+            next += 1;
+            // the decoder adds its codes one step later, the size grows after the code 2^size is used
+            if next > 1 << size && size < 12 {
+                size += 1;
+            }
+        } else {
+            write(clear, size, &mut out);
+            table.clear();
+            size = min_code_size + 1;
+            next = end + 1;
+        }
+        prefix = index as u16;
+    }
+    write(prefix, size, &mut out);
+    write(end, size, &mut out);
+    // the last bits
+    write(0, 7, &mut out);
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pixels::Pixels;
+    use std::fs;
+
+    /// the LZW decompression of the GIF decoders
+    fn lzw_decode(data : &[u8], min_code_size : u8) -> Vec<u8>{
+        let clear = 1u16 << min_code_size;
+        let end = clear + 1;
+        let mut position = 0;
+        let mut read = |size : u8| -> u16 {
+            let mut code = 0;
+            for bit in 0 .. size as usize{
+                code |= ((data[(position + bit) / 8] >> ((position + bit) % 8)) & 1) as u16 * (1 << bit);
+            }
+            position += size as usize;
+            code
+        };
+        let mut table : Vec<Vec<u8>> = Vec::new();
+        let mut size = min_code_size + 1;
+        let mut previous : Option<Vec<u8>> = None;
+        let mut out = Vec::new();
+        loop {
+            let code = read(size);
+            if code == clear {
+                table = (0 .. clear).map(|index| vec![index as u8]).collect();
+                table.push(Vec::new());
+                table.push(Vec::new());
+                size = min_code_size + 1;
+                previous = None;
+                continue;
+            }
+            if code == end {
+                return out;
+            }
+            let entry = match table.get(code as usize) {
+                Some(entry) => entry.clone(),
+                None => {
+                    let previous = previous.clone().unwrap();
+                    let mut entry = previous.clone();
+                    entry.push(previous[0]);
+                    entry
+                },
+            };
+            out.extend_from_slice(&entry);
+            if let Some(previous) = previous {
+                if table.len() < MAX_CODES as usize {
+                    let mut added = previous.clone();
+                    added.push(entry[0]);
+                    table.push(added);
+                    if table.len() == 1 << size && size < 12 {
+                        size += 1;
+                    }
+                }
+            }
+            previous = Some(entry);
+        }
+    }
+
+    /// the colors of the pixels of a frame and its delay
+    type Frame = (Vec<[u8; 3]>, u16);
+
+    /// the frames of a GIF file and the loop count
+    fn decode(data : &[u8]) -> (Vec<Frame>, Option<u16>){
+        assert_eq!(SIGNATURE, &data[.. 6]);
+        let mut position = 13;
+        let table = |packed : u8, position : &mut usize| -> Vec<[u8; 3]> {
+            let len = 2 << (packed & 0x07);
+            let colors = data[*position .. *position + len * 3].chunks(3).map(|color| [color[0], color[1], color[2]]).collect();
+            *position += len * 3;
+            colors
+        };
+        let global = if data[10] & 0x80 != 0 { Some(table(data[10], &mut position)) } else { None };
+        let mut frames = Vec::new();
+        let mut loops = None;
+        let mut delay = 0;
+        loop {
+            match data[position] {
+                0x21 => {
+                    let label = data[position + 1];
+                    position += 2;
+                    let mut blocks = Vec::new();
+                    while data[position] != 0 {
+                        blocks.push(&data[position + 1 .. position + 1 + data[position] as usize]);
+                        position += 1 + data[position] as usize;
+                    }
+                    position += 1;
+                    match label {
+                        0xf9 => delay = u16::from_le_bytes([blocks[0][1], blocks[0][2]]),
+                        0xff if blocks[0] == b"NETSCAPE2.0" => loops = Some(u16::from_le_bytes([blocks[1][1], blocks[1][2]])),
+                        _ => {},
+                    }
+                },
+                0x2c => {
+                    let packed = data[position + 9];
+                    position += 10;
+                    let local = if packed & 0x80 != 0 { Some(table(packed, &mut position)) } else { None };
+                    let min_code_size = data[position];
+                    position += 1;
+                    let mut compressed = Vec::new();
+                    while data[position] != 0 {
+                        compressed.extend_from_slice(&data[position + 1 .. position + 1 + data[position] as usize]);
+                        position += 1 + data[position] as usize;
+                    }
+                    position += 1;
+                    let palette = local.as_ref().or(global.as_ref()).unwrap();
+                    let pixels = lzw_decode(&compressed, min_code_size).iter().map(|&index| palette[index as usize]).collect();
+                    frames.push((pixels, delay));
+                },
+                0x3b => return (frames, loops),
+                byte => panic!("unknown block {:#x}", byte),
+            }
+        }
+    }
+
+    fn colors(image : &Image) -> Vec<[u8; 3]>{
+        image.pixels.iter().map(|pixel| [pixel.red as u8, pixel.green as u8, pixel.blue as u8]).collect()
+    }
+
+    #[test]
+    fn test_lzw(){
+        let indexes = [1, 1, 1, 1, 2, 2, 3, 1, 1, 1, 1, 1];
+        assert_eq!(indexes.to_vec(), lzw_decode(&lzw_encode(&indexes, 2), 2));
+        assert_eq!(Vec::<u8>::new(), lzw_decode(&lzw_encode(&[], 2), 2));
+
+        // a noise that fill the table of the codes several times
+        let mut state = 12345u32;
+        let indexes : Vec<u8> = (0 .. 100_000).map(|_| {
+            state = state.wrapping_mul(1_103_515_245).wrapping_add(12345);
+            (state >> 16) as u8
+        }).collect();
+        assert_eq!(indexes, lzw_decode(&lzw_encode(&indexes, 8), 8));
+        let indexes : Vec<u8> = indexes.iter().map(|index| index % 5).collect();
+        assert_eq!(indexes, lzw_decode(&lzw_encode(&indexes, 3), 3));
+    }
+
+    #[test]
+    fn test_quantize(){
+        let counts = count_colors([[1, 2, 3], [1, 2, 3], [9, 9, 9]].iter());
+        assert_eq!(vec![[1, 2, 3], [9, 9, 9]], quantize(&counts, 256));
+
+        // two clusters of colors, the mean of each
+        let colors = [[0, 0, 0], [2, 0, 0], [4, 0, 0], [200, 100, 0], [202, 100, 0]];
+        let mut palette = quantize(&count_colors(colors.iter()), 2);
+        palette.sort_unstable();
+        assert_eq!(vec![[2, 0, 0], [201, 100, 0]], palette);
+
+        // a gradient of 4096 colors
+        let colors : Vec<[u8; 3]> = (0 .. 4096).map(|index| [(index % 16 * 16) as u8, (index / 16 % 16 * 16) as u8, (index / 256 * 16) as u8]).collect();
+        let palette = quantize(&count_colors(colors.iter()), 256);
+        assert_eq!(256, palette.len());
+        let error = colors.iter().map(|color| {
+            let other = palette[nearest(&palette, [color[0] as i32, color[1] as i32, color[2] as i32])];
+            (0 .. 3).map(|channel| (color[channel] as i32 - other[channel] as i32).abs()).max().unwrap()
+        }).max().unwrap();
+        // each box keeps 16 colors, 4 steps of 16 at most on a channel
+        assert!(error <= 24, "error of {}", error);
+    }
+
+    #[test]
+    fn test_dithering(){
+        // a gray at the half between black and white
+        let colors = vec![[128, 128, 128]; 64];
+        let palette = [[0, 0, 0], [255, 255, 255]];
+        let plain = map_colors(&colors, 8, &palette, Dithering::None);
+        assert!(plain.iter().all(|&index| index == 1));
+        let dithered = map_colors(&colors, 8, &palette, Dithering::FloydSteinberg);
+        let white = dithered.iter().filter(|&&index| index == 1).count();
+        assert!((28 ..= 36).contains(&white), "{} white pixels", white);
+
+        // the colors of the palette are kept as they are
+        let colors = vec![[0, 0, 0], [255, 255, 255], [255, 255, 255], [0, 0, 0]];
+        assert_eq!(vec![0, 1, 1, 0], map_colors(&colors, 2, &palette, Dithering::FloydSteinberg));
+    }
+
+    #[test]
+    fn test_animation() -> Result<(), PpmError>{
+        let frames : Vec<GifFrame> = (0 .. 3).map(|step| {
+            let pixels = (0 .. 6 * 5).map(|index| Pixels::new(((index * 8 + step * 20) % 256) as u16, (step * 50) as u16, 255)).collect();
+            GifFrame::new(Image::new(pixels, 5, 6, "P6".to_string(), 255), step as u16 * 10 + 5)
+        }).collect();
+
+        for &palette in [GifPalette::Global, GifPalette::PerFrame].iter(){
+            for &dithering in [Dithering::None, Dithering::FloydSteinberg].iter(){
+                let mut data = Vec::new();
+                write_gif(&mut data, &frames, Repeat::Forever, palette, dithering)?;
+                let (decoded, loops) = decode(&data);
+                assert_eq!(Some(0), loops);
+                assert_eq!(3, decoded.len());
+                // less than 256 colors, the pixels are exact
+                for (frame, (pixels, delay)) in frames.iter().zip(decoded.iter()){
+                    assert_eq!(colors(&frame.image), *pixels);
+                    assert_eq!(frame.delay, *delay);
+                }
+            }
+        }
+
+        let mut data = Vec::new();
+        write_gif(&mut data, &frames[.. 1], Repeat::Once, GifPalette::PerFrame, Dithering::None)?;
+        assert_eq!((None, 0x3b), (decode(&data).1, data[data.len() - 1]));
+        // the screen of 6x5 pixels has no global table
+        assert_eq!(&[6, 0, 5, 0, 0x70], &data[6 .. 11]);
+        let mut data = Vec::new();
+        write_gif(&mut data, &frames, Repeat::Times(3), GifPalette::Global, Dithering::None)?;
+        assert_eq!(Some(3), decode(&data).1);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_save_gif() -> Result<(), PpmError>{
+        // a gradient of more than 256 colors and the PPM scaled from 0 .. 15
+        let pixels = (0 .. 64 * 64).map(|index| Pixels::new((index % 64 * 4) as u16, (index / 64 * 4) as u16, 128)).collect();
+        let gradient = Image::new(pixels, 64, 64, "P6".to_string(), 255);
+        let ppm = Image::new_with_file(Path::new("resource/ppmImage.ppm"))?;
+        let frames = vec![GifFrame::new(gradient.clone(), 10), GifFrame::new(ppm.clone(), 10)];
+        save_gif(Path::new("test_save.gif"), &frames, Repeat::Forever, GifPalette::PerFrame, Dithering::FloydSteinberg)?;
+        let (decoded, _) = decode(&fs::read(Path::new("test_save.gif"))?);
+        fs::remove_file(Path::new("test_save.gif"))?;
+
+        let error : f64 = colors(&gradient).iter().zip(decoded[0].0.iter())
+            .map(|(a, b)| (0 .. 3).map(|channel| (a[channel] as f64 - b[channel] as f64).powi(2)).sum::<f64>()).sum();
+        let psnr = 10.0 * (255.0 * 255.0 * 3.0 * 64.0 * 64.0 / error).log10();
+        assert!(psnr > 30.0, "PSNR of {:.1} dB", psnr);
+        let scaled : Vec<[u8; 3]> = ppm.pixels.iter().map(|pixel| [pixel.red as u8 * 17, pixel.green as u8 * 17, pixel.blue as u8 * 17]).collect();
+        assert_eq!(scaled, decoded[1].0[.. 16]);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,303 @@
+use pixels::{Pixels, GrayMethod};
+use images::{self, Image};
+use image_view::ImageView;
+use header::PnmReader;
+use error::PpmError;
+
+use std::fs::File;
+use std::io::BufReader;
+use std::io::prelude::*;
+use std::path::Path;
+
+// the fields `fileType` and `maxValue` mirror the fields of `Image`
+#[allow(non_snake_case)]
+#[derive(Clone, Debug)]
+pub struct GrayImage{
+    pub pixels : Vec<u16>,
+    pub heigth : usize,
+    pub width : usize,
+    pub fileType : String,
+    pub maxValue : usize,
+    /// the commentaires of the header, the text after the `#` of each line
+    pub comments : Vec<String>,
+}
+
+impl GrayImage {
+
+    /// create a new gray image
+    ///
+    /// # Arguments
+    ///
+    /// * `pixels` - a vec contain the gray level of each pixel in the image
+    /// * `heigth` - heigth of the image
+    /// * `width` - width of the image
+    /// * `file_type` - the type of the image, "P2" (ASCII) or "P5" (binary)
+    /// * `max_value` - the max value of the pixels in the image
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// let image = GrayImage::new(vec![7, 91, 43], 1, 3, "P2".to_string(), 91);
+    /// ```
+    pub fn new(pixels : Vec<u16>, heigth : usize, width : usize, file_type : String, max_value : usize) -> GrayImage{
+        GrayImage{pixels, heigth, width, fileType : file_type, maxValue : max_value, comments : Vec::new()}
+    }
+
+    ///load the image from the file pgm, ASCII (P2) or binary (P5)
+    ///
+    /// # Arguments
+    ///
+    /// * `filename` - the name of the file
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// let image = GrayImage::new(vec![7, 91, 43], 1, 3, "P2".to_string(), 91);
+    /// image.save(Path::new("test_image.pgm"))?;
+    /// let image_load = GrayImage::new_with_file(Path::new("test_image.pgm"));
+    /// assert_eq!(image, image_load?);
+    ///
+    /// fs::remove_file(Path::new("test_image.pgm"))?;
+    /// ```
+    pub fn new_with_file(filename: &Path) -> Result<GrayImage, PpmError>{
+        let f = File::open(filename)?;
+        let mut reader = PnmReader::new(BufReader::new(f));
+
+        let position = reader.position();
+        let header = reader.read_header()?;
+        let number = header.heigth * header.width;
+
+        let pixels = match &*header.magic {
+            "P5" => reader.read_binary_samples(number, header.max_value)?,
+            "P2" => reader.read_plain_samples(number, header.max_value)?,
+            _ => return Err(PpmError::BadMagic{ magic : header.magic, position }),
+        };
+
+        Ok(GrayImage{ pixels , heigth : header.heigth, width : header.width, fileType : header.magic, maxValue : header.max_value,
+            comments : reader.take_comments()})
+    }
+
+    ///save the image in a file of pgm, in binary if `fileType` is "P5" else in ASCII
+    ///
+    /// the commentaires are written after the magic number
+    ///
+    /// # Arguments
+    ///
+    /// * `filename` - the name of the file
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// let image = GrayImage::new(vec![7, 91, 43], 1, 3, "P5".to_string(), 91);
+    /// image.save(Path::new("test_image.pgm"))?;
+    /// ```
+    pub fn save(&self, filename : &Path) -> Result<(), PpmError>{
+        let mut f = File::create(filename)?;
+        let mut buf = String::new();
+        buf = buf + &self.fileType + "\n";
+        images::write_comments(&mut buf, &self.comments, "\n");
+        buf = buf + &format!("{} {}", self.width, self.heigth) + "\n";
+        buf = buf + &self.maxValue.to_string() + "\n";
+        f.write_all(buf.as_bytes())?;
+
+        if self.fileType == "P5" {
+            f.write_all(&images::write_binary_samples(&self.pixels, self.maxValue))?;
+            return Ok(());
+        }
+
+        let mut pixel_string = String::new();
+        for line in self.pixels.chunks(10){
+            let line : Vec<String> = line.iter().map(|pixel| pixel.to_string()).collect();
+            pixel_string = pixel_string + &line.join(" ") + "\n";
+        }
+        f.write_all(pixel_string.as_bytes())?;
+        Ok(())
+    }
+
+    ///transform the RGB image to the gray image, with the luma of each pixel
+    ///
+    /// a P3 image become a P2 image and a P6 image become a P5 image
+    ///
+    /// # Arguments
+    ///
+    /// * `image` - the RGB image
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// let image_gray = GrayImage::from_image(&image);
+    /// ```
+    pub fn from_image<I : ImageView>(image : &I) -> GrayImage{
+        GrayImage::from_image_with(image, GrayMethod::Rec601)
+    }
+
+    ///transform the RGB image to the gray image, with the gray level of the method for each pixel
+    ///
+    /// # Arguments
+    ///
+    /// * `image` - the RGB image
+    /// * `method` - the weights of the channels
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// let image_gray = GrayImage::from_image_with(&image, GrayMethod::Rec709);
+    /// ```
+    pub fn from_image_with<I : ImageView>(image : &I, method : GrayMethod) -> GrayImage{
+        let pixels = image.iter_pixels().map(|pixel| pixel.gray(method)).collect();
+        let file_type = if image.file_type() == "P6" { "P5" } else { "P2" };
+
+        GrayImage::new(pixels, image.heigth(), image.width(), file_type.to_string(), image.max_value())
+    }
+
+    ///transform the gray image to a RGB image with three equal channels
+    ///
+    /// a P2 image become a P3 image and a P5 image become a P6 image
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// let image = image_gray.to_image();
+    /// ```
+    pub fn to_image(&self) -> Image{
+        let pixels = self.pixels.iter().map(|&gray| Pixels::new(gray, gray, gray)).collect();
+        let file_type = if self.fileType == "P5" { "P6" } else { "P3" };
+
+        Image::new(pixels, self.heigth, self.width, file_type.to_string(), self.maxValue)
+    }
+}
+
+impl PartialEq for GrayImage {
+    fn eq(&self, other: &Self) -> bool {
+        // the commentaires are metadata, they are not compared
+        self.pixels == other.pixels &&
+        self.fileType == other.fileType &&
+        self.maxValue == other.maxValue &&
+        self.heigth == other.heigth &&
+        self.width == other.width
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn test_save_p2() -> std::io::Result<()>{
+        let pixels = (0 .. 24).map(|i| i * 3).collect();
+        let image = GrayImage::new(pixels, 4, 6, "P2".to_string(), 69);
+        image.save(Path::new("test_save_gray_p2.pgm"))?;
+        let image_load = GrayImage::new_with_file(Path::new("test_save_gray_p2.pgm"));
+        assert_eq!(image, image_load?);
+
+        fs::remove_file(Path::new("test_save_gray_p2.pgm"))?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_save_comments() -> Result<(), PpmError>{
+        for file_type in &["P2", "P5"] {
+            let mut image = GrayImage::new(vec![7, 91, 43], 1, 3, file_type.to_string(), 91);
+            image.comments = vec![" camera: 12".to_string(), String::new(), "no space".to_string()];
+            image.save(Path::new("test_save_gray_comments.pgm"))?;
+
+            let image_load = GrayImage::new_with_file(Path::new("test_save_gray_comments.pgm"))?;
+            assert_eq!(image.comments, image_load.comments);
+            assert_eq!(image, image_load);
+
+            fs::remove_file(Path::new("test_save_gray_comments.pgm"))?;
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_new_with_file_p2_one_line() -> std::io::Result<()>{
+        let mut f_w = File::create(Path::new("test_gray_p2_one_line.pgm"))?;
+        f_w.write_all(b"P2 3 1 # comment\n91 7 91 43")?;
+
+        let image_load = GrayImage::new_with_file(Path::new("test_gray_p2_one_line.pgm"))?;
+        assert_eq!(GrayImage::new(vec![7, 91, 43], 1, 3, "P2".to_string(), 91), image_load);
+
+        fs::remove_file(Path::new("test_gray_p2_one_line.pgm"))?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_new_with_file_errors() -> std::io::Result<()>{
+        let mut f_w = File::create(Path::new("test_gray_errors.pgm"))?;
+        f_w.write_all(b"P3 1 1 15 1 1 1")?;
+        match GrayImage::new_with_file(Path::new("test_gray_errors.pgm")) {
+            Err(PpmError::BadMagic{ magic, .. }) => assert_eq!("P3", magic),
+            _ => panic!("P3 is not a PGM image"),
+        }
+
+        let mut f_w = File::create(Path::new("test_gray_errors.pgm"))?;
+        f_w.write_all(b"P5 2 2 255\n\x01\x02")?;
+        match GrayImage::new_with_file(Path::new("test_gray_errors.pgm")) {
+            Err(PpmError::Truncated{ expected, found, .. }) => assert_eq!((4, 2), (expected, found)),
+            _ => panic!("two samples are missing"),
+        }
+
+        fs::remove_file(Path::new("test_gray_errors.pgm"))?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_save_p5() -> std::io::Result<()>{
+        let image = GrayImage::new(vec![7, 91, 43, 255], 2, 2, "P5".to_string(), 255);
+        image.save(Path::new("test_save_gray_p5.pgm"))?;
+        let image_load = GrayImage::new_with_file(Path::new("test_save_gray_p5.pgm"));
+        assert_eq!(image, image_load?);
+
+        let data = fs::read(Path::new("test_save_gray_p5.pgm"))?;
+        assert_eq!(&[7, 91, 43, 255], &data[data.len() - 4 ..]);
+
+        fs::remove_file(Path::new("test_save_gray_p5.pgm"))?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_save_p5_16_bits() -> std::io::Result<()>{
+        let image = GrayImage::new(vec![7, 910, 65535, 256], 2, 2, "P5".to_string(), 65535);
+        image.save(Path::new("test_save_gray_p5_16.pgm"))?;
+        let image_load = GrayImage::new_with_file(Path::new("test_save_gray_p5_16.pgm"));
+        assert_eq!(image, image_load?);
+
+        fs::remove_file(Path::new("test_save_gray_p5_16.pgm"))?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_from_image(){
+        let mut pixels = Vec::new();
+        pixels.push(Pixels::new(255, 0, 0));
+        pixels.push(Pixels::new(0, 255, 0));
+        pixels.push(Pixels::new(0, 0, 255));
+        pixels.push(Pixels::new(100, 100, 100));
+        let image = Image::new(pixels, 2, 2, "P6".to_string(), 255);
+
+        let image_gray = GrayImage::from_image(&image);
+
+        assert_eq!(vec![76, 150, 29, 100], image_gray.pixels);
+        assert_eq!("P5", image_gray.fileType);
+        assert_eq!(255, image_gray.maxValue);
+    }
+
+    #[test]
+    fn test_to_image(){
+        let image_gray = GrayImage::new(vec![7, 91], 1, 2, "P2".to_string(), 91);
+
+        let image = image_gray.to_image();
+
+        assert_eq!(vec![Pixels::new(7, 7, 7), Pixels::new(91, 91, 91)], image.pixels);
+        assert_eq!("P3", image.fileType);
+        assert_eq!(GrayImage::from_image(&image), image_gray);
+    }
+}
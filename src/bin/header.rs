@@ -0,0 +1,590 @@
+use error::{PpmError, Position};
+
+use std::io::prelude::*;
+use std::mem;
+
+/// the header of a Netpbm image (PBM, PGM, PPM and PFM)
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) struct Header{
+    pub magic : String,
+    pub heigth : usize,
+    pub width : usize,
+    pub max_value : usize,
+}
+
+/// a reader of Netpbm images, it keep the line and the byte reached in the file for the errors
+/// and the commentaires found between the tokens
+pub(crate) struct PnmReader<R>{
+    inner : R,
+    line : usize,
+    byte : usize,
+    comments : Vec<String>,
+}
+
+impl<R : BufRead> PnmReader<R> {
+
+    /// create a reader at the start of an image
+    ///
+    /// # Arguments
+    ///
+    /// `inner` - the reader of the file
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// let mut reader = PnmReader::new(BufReader::new(File::open(Path::new("image.ppm"))?));
+    /// ```
+    pub fn new(inner : R) -> PnmReader<R>{
+        PnmReader{inner, line : 1, byte : 0, comments : Vec::new()}
+    }
+
+    /// the position of the next byte to read
+    pub fn position(&self) -> Position{
+        Position{line : self.line, byte : self.byte}
+    }
+
+    /// the commentaires read since the last call, the text after the `#` without the end of line
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// let mut reader = PnmReader::new("P3 # comment\n4 4 15\n".as_bytes());
+    /// reader.read_header()?;
+    /// assert_eq!(vec![" comment".to_string()], reader.take_comments());
+    /// ```
+    pub fn take_comments(&mut self) -> Vec<String>{
+        mem::take(&mut self.comments)
+    }
+
+    /// read the header of a Netpbm image, as a stream of tokens
+    ///
+    /// the tokens are separated by any whitespace and a `#` start a commentaire until the end of the line,
+    /// anywhere in the header. The reader is left just after the single whitespace that end the header.
+    ///
+    /// the max value is 1 for the formats without max value in the header (P1, P4), the PFM formats
+    /// (Pf, PF) stop after the size and let the caller read the scale
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// let mut reader = PnmReader::new("P3 # comment\n4 4 15\n".as_bytes());
+    /// let header = reader.read_header()?;
+    /// assert_eq!(15, header.max_value);
+    /// ```
+    pub fn read_header(&mut self) -> Result<Header, PpmError>{
+        let position = self.position();
+        let magic = match self.read_token()? {
+            Some(magic) => magic,
+            None => return Err(PpmError::BadMagic{ magic : String::new(), position }),
+        };
+        if magic.len() != 2 || !magic.starts_with('P') {
+            return Err(PpmError::BadMagic{ magic, position });
+        }
+        let position = self.position();
+        // the width come before the heigth, as in the spec and in the C `ppma_write_header`
+        let width = self.read_number("width")?;
+        let heigth = self.read_number("heigth")?;
+        // 4 samples of 2 bytes is the largest pixel of the formats
+        if heigth.checked_mul(width).and_then(|size| size.checked_mul(8)).is_none() {
+            return Err(PpmError::Overflow{ value : format!("{}x{}", heigth, width), position });
+        }
+        let max_value = match &*magic {
+            "P1" | "P4" | "Pf" | "PF" => 1,
+            _ => {
+                let position = self.position();
+                let max_value = self.read_number("max value")?;
+                if max_value > 65535 {
+                    return Err(PpmError::Overflow{ value : max_value.to_string(), position });
+                }
+                max_value
+            },
+        };
+
+        Ok(Header{magic, heigth, width, max_value})
+    }
+
+    /// read the next token, skipping the whitespace and the commentaires before it, None at the end of the file
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// let mut reader = PnmReader::new("  P3#comment\n 4".as_bytes());
+    /// assert_eq!(Some("P3".to_string()), reader.read_token()?);
+    /// assert_eq!(Some("4".to_string()), reader.read_token()?);
+    /// assert_eq!(None, reader.read_token()?);
+    /// ```
+    pub fn read_token(&mut self) -> Result<Option<String>, PpmError>{
+        let mut token = Vec::new();
+        let mut position = self.position();
+        let mut commentaire : Option<Vec<u8>> = None;
+        while let Some(byte) = self.read_byte()? {
+            if let Some(mut comment) = commentaire.take() {
+                if byte == b'\n' || byte == b'\r' {
+                    self.comments.push(String::from_utf8_lossy(&comment).into_owned());
+                    if !token.is_empty() {
+                        break;
+                    }
+                } else {
+                    comment.push(byte);
+                    commentaire = Some(comment);
+                }
+                continue;
+            }
+            match byte {
+                b'#' => commentaire = Some(Vec::new()),
+                b' ' | b'\t' | b'\n' | b'\r' | 0x0b | 0x0c => {
+                    if !token.is_empty() {
+                        break;
+                    }
+                },
+                _ => {
+                    if token.is_empty() {
+                        position = Position{line : self.line, byte : self.byte - 1};
+                    }
+                    token.push(byte)
+                },
+            }
+        }
+        // a commentaire at the end of the file
+        if let Some(comment) = commentaire {
+            self.comments.push(String::from_utf8_lossy(&comment).into_owned());
+        }
+        if token.is_empty() {
+            return Ok(None);
+        }
+        match String::from_utf8(token) {
+            Ok(token) => Ok(Some(token)),
+            Err(_) => Err(PpmError::bad_header("the token is not a text", position)),
+        }
+    }
+
+    /// read the next token of the header as a number
+    ///
+    /// # Arguments
+    ///
+    /// `name` - the name of the value, for the errors
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// let mut reader = PnmReader::new("3 41".as_bytes());
+    /// assert_eq!(3, reader.read_number("heigth")?);
+    /// assert_eq!(41, reader.read_number("width")?);
+    /// ```
+    pub fn read_number(&mut self, name : &str) -> Result<usize, PpmError>{
+        let position = self.position();
+        let token = match self.read_token()? {
+            Some(token) => token,
+            None => return Err(PpmError::bad_header(format!("missing {}", name), position)),
+        };
+        parse_number(&token, position).map_err(|error| match error {
+            PpmError::BadHeader{ position, .. } => PpmError::bad_header(format!("{} {:?} is not a number", name, token), position),
+            error => error,
+        })
+    }
+
+    /// read the samples of an ASCII image (P2, P3), written as numbers separated by whitespace
+    ///
+    /// # Arguments
+    ///
+    /// `number` - the number of samples to read
+    /// `max_value` - the max value of the samples in the image
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// let mut reader = PnmReader::new("4 11\n 4".as_bytes());
+    /// assert_eq!(vec![4, 11, 4], reader.read_plain_samples(3, 15)?);
+    /// ```
+    pub fn read_plain_samples(&mut self, number : usize, max_value : usize) -> Result<Vec<u16>, PpmError>{
+        let mut samples = Vec::with_capacity(number.min(1 << 16));
+        for _ in 0 .. number{
+            let position = self.position();
+            let token = match self.read_token()? {
+                Some(token) => token,
+                None => return Err(PpmError::Truncated{ expected : number, found : samples.len(), position }),
+            };
+            let sample = parse_number(&token, position)?;
+            if sample > max_value {
+                return Err(PpmError::SampleOverMaxval{ sample, max_value, position });
+            }
+            samples.push(sample as u16);
+        }
+        Ok(samples)
+    }
+
+    /// read the samples of a binary image (P5, P6, P7)
+    ///
+    /// each sample take one byte if `max_value` is less than 256, else two bytes in big-endian
+    ///
+    /// # Arguments
+    ///
+    /// `number` - the number of samples to read
+    /// `max_value` - the max value of the samples in the image
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// let mut reader = PnmReader::new(&[4, 11, 0xff][..]);
+    /// assert_eq!(vec![4, 11, 255], reader.read_binary_samples(3, 255)?);
+    /// ```
+    pub fn read_binary_samples(&mut self, number : usize, max_value : usize) -> Result<Vec<u16>, PpmError>{
+        let sample_size = if max_value < 256 { 1 } else { 2 };
+        let position = self.position();
+        let data = self.read_raw(number, sample_size)?;
+
+        let mut samples = Vec::with_capacity(number);
+        for (index, sample) in data.chunks(sample_size).enumerate(){
+            let sample = if sample_size == 1 {
+                sample[0] as u16
+            } else {
+                ((sample[0] as u16) << 8) | sample[1] as u16
+            };
+            if sample as usize > max_value {
+                let position = Position{line : position.line, byte : position.byte + index * sample_size};
+                return Err(PpmError::SampleOverMaxval{ sample : sample as usize, max_value, position });
+            }
+            samples.push(sample);
+        }
+        Ok(samples)
+    }
+
+    /// read the bytes of `number` samples of `sample_size` bytes
+    ///
+    /// # Arguments
+    ///
+    /// `number` - the number of samples to read
+    /// `sample_size` - the number of bytes of a sample
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// let mut reader = PnmReader::new(&[4, 11, 0xff, 0][..]);
+    /// assert_eq!(vec![4, 11, 0xff, 0], reader.read_raw(2, 2)?);
+    /// ```
+    pub fn read_raw(&mut self, number : usize, sample_size : usize) -> Result<Vec<u8>, PpmError>{
+        let position = self.position();
+        let len = match number.checked_mul(sample_size) {
+            Some(len) => len,
+            None => return Err(PpmError::Overflow{ value : format!("{} samples", number), position }),
+        };
+        let data = self.read_available(len)?;
+        if data.len() < len {
+            return Err(PpmError::Truncated{ expected : number, found : data.len() / sample_size, position : self.position() });
+        }
+        Ok(data)
+    }
+
+    /// read up to `len` bytes, less at the end of the file
+    ///
+    /// # Arguments
+    ///
+    /// `len` - the number of bytes to read
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// let mut reader = PnmReader::new(&[4, 11, 0xff][..]);
+    /// assert_eq!(vec![4, 11, 0xff], reader.read_available(6)?);
+    /// ```
+    pub fn read_available(&mut self, len : usize) -> Result<Vec<u8>, PpmError>{
+        // the data is not allocated at once, a bad header must not allocate a large buffer for nothing
+        let mut data = Vec::new();
+        (&mut self.inner).take(len as u64).read_to_end(&mut data)?;
+        self.byte += data.len();
+        Ok(data)
+    }
+
+    /// read a line, without the end of line, None at the end of the file
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// let mut reader = PnmReader::new("P7\nWIDTH 4\n".as_bytes());
+    /// assert_eq!(Some("P7".to_string()), reader.read_line()?);
+    /// ```
+    pub fn read_line(&mut self) -> Result<Option<String>, PpmError>{
+        let position = self.position();
+        let mut line = Vec::new();
+        let len = self.inner.read_until(b'\n', &mut line)?;
+        if len == 0 {
+            return Ok(None);
+        }
+        self.byte += len;
+        if line.ends_with(b"\n") {
+            self.line += 1;
+            line.pop();
+        }
+        if line.ends_with(b"\r") {
+            line.pop();
+        }
+        match String::from_utf8(line) {
+            Ok(line) => Ok(Some(line)),
+            Err(_) => Err(PpmError::bad_header("the line is not a text", position)),
+        }
+    }
+
+    /// skip the whitespace after an image, true if nothing else follow it in the file
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// let mut reader = PnmReader::new(" \nP3".as_bytes());
+    /// assert!(!reader.at_end()?);
+    /// assert_eq!(Some("P3".to_string()), reader.read_token()?);
+    /// assert!(reader.at_end()?);
+    /// ```
+    pub fn at_end(&mut self) -> Result<bool, PpmError>{
+        loop {
+            let byte = {
+                let buf = self.inner.fill_buf()?;
+                if buf.is_empty() {
+                    return Ok(true);
+                }
+                buf[0]
+            };
+            match byte {
+                b' ' | b'\t' | b'\n' | b'\r' | 0x0b | 0x0c => self.read_byte()?,
+                _ => return Ok(false),
+            };
+        }
+    }
+
+    /// read one byte, None at the end of the file
+    pub fn read_byte(&mut self) -> Result<Option<u8>, PpmError>{
+        let byte = {
+            let buf = self.inner.fill_buf()?;
+            if buf.is_empty() {
+                return Ok(None);
+            }
+            buf[0]
+        };
+        self.inner.consume(1);
+        self.byte += 1;
+        if byte == b'\n' {
+            self.line += 1;
+        }
+        Ok(Some(byte))
+    }
+}
+
+/// parse a number of the file
+///
+/// # Arguments
+///
+/// `token` - the text of the number
+/// `position` - the position of the token, for the errors
+///
+/// # Example
+///
+/// ```
+/// assert_eq!(41, parse_number("41", Position{line : 1, byte : 0})?);
+/// ```
+pub(crate) fn parse_number(token : &str, position : Position) -> Result<usize, PpmError>{
+    if token.is_empty() || !token.bytes().all(|c| c.is_ascii_digit()) {
+        return Err(PpmError::bad_header(format!("{:?} is not a number", token), position));
+    }
+    token.parse::<usize>().map_err(|_| PpmError::Overflow{ value : token.to_string(), position })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_at_end() -> Result<(), PpmError>{
+        let mut reader = PnmReader::new("\r\n\t P3\n".as_bytes());
+        assert!(!reader.at_end()?);
+        assert_eq!(Position{line : 2, byte : 4}, reader.position());
+        assert_eq!(Some("P3".to_string()), reader.read_token()?);
+        assert!(reader.at_end()?);
+
+        let mut reader = PnmReader::new("".as_bytes());
+        assert!(reader.at_end()?);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_read_token() -> Result<(), PpmError>{
+        let mut reader = PnmReader::new("  P3#comment\n 4\t\r\n# other comment\n15".as_bytes());
+        assert_eq!(Some("P3".to_string()), reader.read_token()?);
+        assert_eq!(Some("4".to_string()), reader.read_token()?);
+        assert_eq!(Some("15".to_string()), reader.read_token()?);
+        assert_eq!(None, reader.read_token()?);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_take_comments() -> Result<(), PpmError>{
+        let mut reader = PnmReader::new("P3#first\r\n# camera: 12 \n4 4 # size\n15\n# last".as_bytes());
+        reader.read_header()?;
+        assert_eq!(vec!["first", " camera: 12 ", " size"], reader.take_comments());
+        assert!(reader.take_comments().is_empty());
+        assert_eq!(None, reader.read_token()?);
+        assert_eq!(vec![" last"], reader.take_comments());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_read_number() -> Result<(), PpmError>{
+        let mut reader = PnmReader::new("3 41 x 99999999999999999999999".as_bytes());
+        assert_eq!(3, reader.read_number("heigth")?);
+        assert_eq!(41, reader.read_number("width")?);
+        match reader.read_number("max value") {
+            Err(PpmError::BadHeader{ position, .. }) => assert_eq!(Position{line : 1, byte : 5}, position),
+            _ => panic!("x is not a number"),
+        }
+        match reader.read_number("max value") {
+            Err(PpmError::Overflow{ .. }) => {},
+            _ => panic!("the number does not fit in usize"),
+        }
+        match reader.read_number("max value") {
+            Err(PpmError::BadHeader{ message, .. }) => assert_eq!("missing max value", message),
+            _ => panic!("no more number"),
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_position() -> Result<(), PpmError>{
+        let mut reader = PnmReader::new("P3\n# comment\n  4 4".as_bytes());
+        reader.read_token()?;
+        reader.read_token()?;
+        assert_eq!(Position{line : 3, byte : 17}, reader.position());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_read_header_single_whitespace() -> Result<(), PpmError>{
+        let mut reader = PnmReader::new("P6 1 2 255\n\n\n".as_bytes());
+        let header = reader.read_header()?;
+        assert_eq!(Header{magic : "P6".to_string(), heigth : 2, width : 1, max_value : 255}, header);
+        assert_eq!(vec![b'\n', b'\n'], reader.read_raw(2, 1)?);
+
+        let mut reader = PnmReader::new("P5 2 1 255#comment\n\n".as_bytes());
+        reader.read_header()?;
+        assert_eq!(vec![b'\n'], reader.read_raw(1, 1)?);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_read_header_corpus() -> Result<(), PpmError>{
+        let corpus = [
+            "P3 4 4 15\n",
+            "P3\n4 4\n15\n",
+            "P3\n# comment line\n4 4\n15\n",
+            "P3 # comment after the magic number\n4 4 15\n",
+            "P3\n4 # comment between the dimensions\n4\n15\n",
+            "P3#comment without space\n4#\n4#\n15#\n",
+            "P3\r\n4\t4\r\n15\r\n",
+            "P3\x0b4\x0c4 15 ",
+            "\n  P3   4    4\n\n   15\n",
+        ];
+        for header in corpus.iter(){
+            let mut reader = PnmReader::new(header.as_bytes());
+            assert_eq!(Header{magic : "P3".to_string(), heigth : 4, width : 4, max_value : 15}, reader.read_header()?);
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_read_header_without_max_value() -> Result<(), PpmError>{
+        let mut reader = PnmReader::new("P4 9 2\n\x00".as_bytes());
+        assert_eq!(1, reader.read_header()?.max_value);
+        assert_eq!(vec![0], reader.read_raw(1, 1)?);
+
+        let mut reader = PnmReader::new("PF\n9 2\n-1.0\n".as_bytes());
+        assert_eq!(1, reader.read_header()?.max_value);
+        assert_eq!(Some("-1.0".to_string()), reader.read_token()?);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_read_header_errors(){
+        match PnmReader::new("Q3 4 4 15".as_bytes()).read_header() {
+            Err(PpmError::BadMagic{ magic, .. }) => assert_eq!("Q3", magic),
+            _ => panic!("Q3 is not a magic number"),
+        }
+        match PnmReader::new("".as_bytes()).read_header() {
+            Err(PpmError::BadMagic{ .. }) => {},
+            _ => panic!("an empty file has no magic number"),
+        }
+        match PnmReader::new("P3 4".as_bytes()).read_header() {
+            Err(PpmError::BadHeader{ message, .. }) => assert_eq!("missing heigth", message),
+            _ => panic!("the heigth is missing"),
+        }
+        match PnmReader::new("P3 4 4 65536".as_bytes()).read_header() {
+            Err(PpmError::Overflow{ position, .. }) => assert_eq!(Position{line : 1, byte : 7}, position),
+            _ => panic!("the max value is larger than 16 bits"),
+        }
+        match PnmReader::new(format!("P3 {} 2 255", usize::MAX).as_bytes()).read_header() {
+            Err(PpmError::Overflow{ .. }) => {},
+            _ => panic!("the size overflow"),
+        }
+    }
+
+    #[test]
+    fn test_read_plain_samples() -> Result<(), PpmError>{
+        let mut reader = PnmReader::new("4 11\n 4\n# comment\n12 9 11".as_bytes());
+        assert_eq!(vec![4, 11, 4, 12, 9], reader.read_plain_samples(5, 15)?);
+        match reader.read_plain_samples(2, 15) {
+            Err(PpmError::Truncated{ expected, found, .. }) => assert_eq!((2, 1), (expected, found)),
+            _ => panic!("only one sample left"),
+        }
+
+        let mut reader = PnmReader::new("4 11\n16".as_bytes());
+        match reader.read_plain_samples(3, 15) {
+            Err(PpmError::SampleOverMaxval{ sample, position, .. }) => {
+                assert_eq!(16, sample);
+                assert_eq!(Position{line : 2, byte : 5}, position);
+            },
+            _ => panic!("16 is over the max value"),
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_read_binary_samples() -> Result<(), PpmError>{
+        let mut reader = PnmReader::new(&[4, 11, 4, 0, 4, 0x12, 0x34][..]);
+        assert_eq!(vec![4, 11, 4], reader.read_binary_samples(3, 255)?);
+        assert_eq!(vec![4, 0x1234], reader.read_binary_samples(2, 65535)?);
+
+        let mut reader = PnmReader::new(&[4, 11, 4, 12][..]);
+        match reader.read_binary_samples(6, 255) {
+            Err(PpmError::Truncated{ expected, found, .. }) => assert_eq!((6, 4), (expected, found)),
+            _ => panic!("only four samples"),
+        }
+
+        let mut reader = PnmReader::new(&[4, 11, 4, 12][..]);
+        match reader.read_binary_samples(4, 11) {
+            Err(PpmError::SampleOverMaxval{ sample, position, .. }) => {
+                assert_eq!(12, sample);
+                assert_eq!(3, position.byte);
+            },
+            _ => panic!("12 is over the max value"),
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_read_line() -> Result<(), PpmError>{
+        let mut reader = PnmReader::new("P7\r\nWIDTH 4\nEND".as_bytes());
+        assert_eq!(Some("P7".to_string()), reader.read_line()?);
+        assert_eq!(Some("WIDTH 4".to_string()), reader.read_line()?);
+        assert_eq!(Position{line : 3, byte : 12}, reader.position());
+        assert_eq!(Some("END".to_string()), reader.read_line()?);
+        assert_eq!(None, reader.read_line()?);
+
+        Ok(())
+    }
+}
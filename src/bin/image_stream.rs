@@ -0,0 +1,223 @@
+use images::{self, Image};
+use header::PnmReader;
+use error::PpmError;
+
+use std::fs::File;
+use std::io::{BufReader, BufWriter};
+use std::io::prelude::*;
+use std::path::Path;
+
+/// an iterator on the ppm images written one after the other in a file or a stream
+///
+/// the iterator stop after the last image, or after the first error
+pub struct ImageStream<R>{
+    reader : PnmReader<BufReader<R>>,
+    done : bool,
+}
+
+impl<R : Read> ImageStream<R> {
+
+    /// create an iterator on the images of a reader
+    ///
+    /// # Arguments
+    ///
+    /// * `reader` - the reader of the images, a file, a pipe or a slice of bytes
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// for image in ImageStream::new(io::stdin()) {
+    ///     let image = image?;
+    /// }
+    /// ```
+    pub fn new(reader : R) -> ImageStream<R>{
+        ImageStream{reader : PnmReader::new(BufReader::new(reader)), done : false}
+    }
+}
+
+impl ImageStream<File> {
+
+    /// create an iterator on the images of a file ppm
+    ///
+    /// # Arguments
+    ///
+    /// * `filename` - the name of the file
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// let images = ImageStream::new_with_file(Path::new("frames.ppm"))?.collect::<Result<Vec<Image>, PpmError>>()?;
+    /// ```
+    pub fn new_with_file(filename : &Path) -> Result<ImageStream<File>, PpmError>{
+        let f = File::open(filename)?;
+        Ok(ImageStream::new(f))
+    }
+}
+
+impl<R : Read> Iterator for ImageStream<R> {
+    type Item = Result<Image, PpmError>;
+
+    fn next(&mut self) -> Option<Result<Image, PpmError>>{
+        if self.done {
+            return None;
+        }
+        let image = match self.reader.at_end() {
+            Ok(true) => {
+                self.done = true;
+                return None;
+            },
+            Ok(false) => images::read_image(&mut self.reader),
+            Err(error) => Err(error),
+        };
+        if image.is_err() {
+            self.done = true;
+        }
+        Some(image)
+    }
+}
+
+/// a writer that append the ppm images one after the other in a file or a stream
+pub struct FrameWriter<W>{
+    writer : W,
+    frames : usize,
+}
+
+impl<W : Write> FrameWriter<W> {
+
+    /// create a writer of images
+    ///
+    /// # Arguments
+    ///
+    /// * `writer` - the writer of the images, a file, a pipe or a vec of bytes
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// let mut frames = FrameWriter::new(io::stdout());
+    /// ```
+    pub fn new(writer : W) -> FrameWriter<W>{
+        FrameWriter{writer, frames : 0}
+    }
+
+    /// append an image after the images already written
+    ///
+    /// # Arguments
+    ///
+    /// * `image` - the image to write
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// let mut frames = FrameWriter::new(Vec::new());
+    /// frames.write_frame(&image)?;
+    /// frames.write_frame(&image)?;
+    /// assert_eq!(2, frames.frames());
+    /// ```
+    pub fn write_frame(&mut self, image : &Image) -> Result<(), PpmError>{
+        image.write_to(&mut self.writer)?;
+        self.frames += 1;
+        Ok(())
+    }
+
+    /// the number of images written
+    pub fn frames(&self) -> usize{
+        self.frames
+    }
+
+    /// give back the writer, after the last image
+    pub fn into_inner(self) -> W{
+        self.writer
+    }
+}
+
+impl FrameWriter<BufWriter<File>> {
+
+    /// create a writer of images in a new file ppm
+    ///
+    /// # Arguments
+    ///
+    /// * `filename` - the name of the file
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// let mut frames = FrameWriter::new_with_file(Path::new("frames.ppm"))?;
+    /// ```
+    pub fn new_with_file(filename : &Path) -> Result<FrameWriter<BufWriter<File>>, PpmError>{
+        let f = File::create(filename)?;
+        Ok(FrameWriter::new(BufWriter::new(f)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pixels::Pixels;
+    use error::Position;
+    use std::fs;
+
+    fn frame(level : u16, file_type : &str) -> Image{
+        let pixels = vec![Pixels::new(level, 0, 0), Pixels::new(0, level, 0), Pixels::new(0, 0, level)];
+        Image::new(pixels, 1, 3, file_type.to_string(), 255)
+    }
+
+    #[test]
+    fn test_stream() -> Result<(), PpmError>{
+        let mut frames = FrameWriter::new(Vec::new());
+        frames.write_frame(&frame(10, "P6"))?;
+        frames.write_frame(&frame(20, "P3"))?;
+        frames.write_frame(&frame(30, "P6"))?;
+        assert_eq!(3, frames.frames());
+        let data = frames.into_inner();
+
+        let images = ImageStream::new(&data[..]).collect::<Result<Vec<Image>, PpmError>>()?;
+        assert_eq!(vec![frame(10, "P6"), frame(20, "P3"), frame(30, "P6")], images);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_stream_whitespace() -> Result<(), PpmError>{
+        let data = "P3 1 1 255 1 2 3\n\nP3 1 1 255 4 5 6\n\n";
+        let images = ImageStream::new(data.as_bytes()).collect::<Result<Vec<Image>, PpmError>>()?;
+        assert_eq!(vec![Pixels::new(1, 2, 3)], images[0].pixels);
+        assert_eq!(vec![Pixels::new(4, 5, 6)], images[1].pixels);
+        assert_eq!(2, images.len());
+
+        assert_eq!(0, ImageStream::new("".as_bytes()).count());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_stream_error(){
+        let data = "P3 1 1 255 1 2 3\nP3 1 1 255 4 5\n";
+        let mut images = ImageStream::new(data.as_bytes());
+        assert!(images.next().unwrap().is_ok());
+        match images.next() {
+            Some(Err(PpmError::Truncated{ position, .. })) => assert_eq!(Position{line : 3, byte : 32}, position),
+            _ => panic!("the second image is truncated"),
+        }
+        assert!(images.next().is_none());
+    }
+
+    #[test]
+    fn test_stream_file() -> Result<(), PpmError>{
+        let mut frames = FrameWriter::new_with_file(Path::new("test_stream_frames.ppm"))?;
+        for level in 0 .. 4 {
+            frames.write_frame(&frame(level * 50, "P6"))?;
+        }
+        drop(frames);
+
+        let mut count = 0;
+        for (level, image) in ImageStream::new_with_file(Path::new("test_stream_frames.ppm"))?.enumerate() {
+            assert_eq!(frame(level as u16 * 50, "P6"), image?);
+            count += 1;
+        }
+        assert_eq!(4, count);
+
+        fs::remove_file(Path::new("test_stream_frames.ppm"))?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,103 @@
+use pixels::Pixels;
+use images::Image;
+
+/// the read-only access to a RGB image, for the operations that do not need to own the pixels
+///
+/// it is implemented by `Image` and by `MappedImage`, a view of a file mapped in memory
+pub trait ImageView{
+
+    /// the heigth of the image
+    fn heigth(&self) -> usize;
+
+    /// the width of the image
+    fn width(&self) -> usize;
+
+    /// the max value of the pixels in the image
+    fn max_value(&self) -> usize;
+
+    /// the type of the image, "P3" or "P6"
+    fn file_type(&self) -> &str;
+
+    /// the pixel at the column `x` and the row `y`
+    ///
+    /// # Arguments
+    ///
+    /// * `x` - the column of the pixel, from the left
+    /// * `y` - the row of the pixel, from the top
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// let pixel = image.pixel(0, 0);
+    /// ```
+    fn pixel(&self, x : usize, y : usize) -> Pixels;
+
+    /// an iterator on the pixels, row after row from the top of the image
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// let red = image.iter_pixels().map(|pixel| pixel.red as usize).sum::<usize>();
+    /// ```
+    fn iter_pixels(&self) -> ViewPixels<'_, Self> where Self : Sized{
+        ViewPixels{view : self, index : 0}
+    }
+}
+
+/// the iterator on the pixels of an `ImageView`
+pub struct ViewPixels<'a, V : 'a>{
+    view : &'a V,
+    index : usize,
+}
+
+impl<'a, V : ImageView> Iterator for ViewPixels<'a, V> {
+    type Item = Pixels;
+
+    fn next(&mut self) -> Option<Pixels>{
+        let width = self.view.width();
+        if width == 0 || self.index >= width * self.view.heigth() {
+            return None;
+        }
+        let pixel = self.view.pixel(self.index % width, self.index / width);
+        self.index += 1;
+        Some(pixel)
+    }
+}
+
+impl ImageView for Image {
+
+    fn heigth(&self) -> usize{
+        self.heigth
+    }
+
+    fn width(&self) -> usize{
+        self.width
+    }
+
+    fn max_value(&self) -> usize{
+        self.maxValue
+    }
+
+    fn file_type(&self) -> &str{
+        &self.fileType
+    }
+
+    fn pixel(&self, x : usize, y : usize) -> Pixels{
+        self.pixels[y * self.width + x]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_image_view(){
+        let pixels = vec![Pixels::new(1, 2, 3), Pixels::new(4, 5, 6), Pixels::new(7, 8, 9), Pixels::new(10, 11, 12)];
+        let image = Image::new(pixels.clone(), 2, 2, "P6".to_string(), 255);
+
+        assert_eq!(Pixels::new(7, 8, 9), image.pixel(0, 1));
+        assert_eq!(pixels, image.iter_pixels().collect::<Vec<Pixels>>());
+        assert_eq!(("P6", 255), (image.file_type(), image.max_value()));
+    }
+}
@@ -1,9 +1,10 @@
-use pixels::Pixels;
-#[path = "pixels.rs"]
-mod pixels;
+use pixels::{Pixels, GrayMethod};
+use gray_image::GrayImage;
+use header::PnmReader;
+use error::PpmError;
 
 use std::fs::File;
-use std::io::{self, BufReader, Error, Lines};
+use std::io::{BufReader, BufWriter};
 use std::io::prelude::*;
 use std::path::Path;
 use std::os::raw::{c_char,c_int};
@@ -15,6 +16,8 @@ pub struct Image{
     pub width : usize,
     pub fileType : String,
     pub maxValue : usize,
+    /// the commentaires of the header, the text after the `#` of each line
+    pub comments : Vec<String>,
 }
 
 impl  Image {
@@ -39,10 +42,10 @@ impl  Image {
     /// let image = Image::new(pixels, 1, 3, "P3".to_string(), 91);
     /// ```
     pub fn new(pixels : Vec<Pixels>, heigth : usize, width : usize, fileType : String, maxValue : usize) -> Image{
-        Image{pixels, heigth, width, fileType, maxValue}
+        Image{pixels, heigth, width, fileType, maxValue, comments : Vec::new()}
     }
 
-    ///load the image from the file ppm
+    ///load the image from the file ppm, ASCII (P3) or binary (P6)
     /// 
     /// # Arguments
     /// 
@@ -62,36 +65,46 @@ impl  Image {
     ///
     /// fs::remove_file(Path::new("test_image.ppm"))?;
     /// ```
-    pub fn new_with_file(filename: &Path) -> std::io::Result<Image>{
+    pub fn new_with_file(filename: &Path) -> Result<Image, PpmError>{
         let f = File::open(filename)?;
-        let f = BufReader::new(f);
-        //let mut contents = fs::read_to_string(filename).expect("files not found");
-        let mut pixels = Vec::new();
+        Image::read_from(f)
+    }
 
-        let mut lines = f.lines();
-        let lineType = find_line_not_commentaire(&mut lines);
-        let mut lineSize = find_line_not_commentaire(&mut lines);
-        let mut lineMaxNumber = find_line_not_commentaire(&mut lines);
-
-        let heigth = find_number(&mut lineSize).unwrap();
-        let width = find_number(&mut lineSize).unwrap();
-        let maxValue = find_number(&mut lineMaxNumber).unwrap();
-
-        for line in lines {
-            let mut line = line.unwrap();
-            while &line != ""{
-                if &(*line)[0..1] == "#"{
-                    break
-                }
-                let pixel = find_pixels(&mut line);
-                pixels.push(pixel);
-            }
-        }
+    ///load the image ppm, ASCII (P3) or binary (P6), from a reader
+    /// 
+    /// # Arguments
+    /// 
+    /// * `reader` - the reader of the image, a file, a socket or a slice of bytes
+    /// 
+    /// # Example
+    /// 
+    /// ```
+    /// let image = Image::read_from("P3\n1 1\n255\n7 91 43\n".as_bytes())?;
+    /// assert_eq!(vec![Pixels::new(7, 91, 43)], image.pixels);
+    /// ```
+    pub fn read_from<R : Read>(reader : R) -> Result<Image, PpmError>{
+        let mut reader = PnmReader::new(BufReader::new(reader));
+        read_image(&mut reader)
+    }
 
-        Ok(Image{ pixels , heigth, width, fileType : lineType, maxValue})
+    ///load the image ppm from the bytes of the file
+    /// 
+    /// # Arguments
+    /// 
+    /// * `data` - the bytes of the file
+    /// 
+    /// # Example
+    /// 
+    /// ```
+    /// let image = Image::from_bytes(b"P6\n1 1\n255\n\x07\x5b\x2b")?;
+    /// assert_eq!(vec![Pixels::new(7, 91, 43)], image.pixels);
+    /// ```
+    pub fn from_bytes(data : &[u8]) -> Result<Image, PpmError>{
+        let mut reader = PnmReader::new(data);
+        read_image(&mut reader)
     }
 
-    ///save the image in a file of ppm
+    ///save the image in a file of ppm, in binary if `fileType` is "P6" else in ASCII
     /// 
     /// # Arguments
     /// 
@@ -111,13 +124,40 @@ impl  Image {
     ///
     /// fs::remove_file(Path::new("test_image.ppm"))?;
     /// ```
-    pub fn save(&self, filename : &Path) -> std::io::Result<()>{
-        let mut f = File::create(filename)?;
+    pub fn save(&self, filename : &Path) -> Result<(), PpmError>{
+        let f = File::create(filename)?;
+        self.write_to(BufWriter::new(f))
+    }
+
+    ///write the image ppm to a writer, in binary if `fileType` is "P6" else in ASCII
+    /// 
+    /// the commentaires are written after the magic number
+    /// 
+    /// # Arguments
+    /// 
+    /// * `writer` - the writer of the image, a file, a socket or a vec of bytes
+    /// 
+    /// # Example
+    /// 
+    /// ```
+    /// let image = Image::new(vec![Pixels::new(7, 91, 43)], 1, 1, "P6".to_string(), 255);
+    /// let mut data = Vec::new();
+    /// image.write_to(&mut data)?;
+    /// assert_eq!(b"P6\n1 1\n255\n\x07\x5b\x2b".to_vec(), data);
+    /// ```
+    pub fn write_to<W : Write>(&self, mut writer : W) -> Result<(), PpmError>{
+        let end_line = if self.fileType == "P6" { "\n" } else { "\r\n" };
         let mut buf = String::new();
-        buf = buf + &self.fileType + "\r\n";
-        buf = buf + "#" + &filename.to_str().unwrap() + "\r\n";
-        buf = buf + &format!("{} {}",self.heigth, self.width) + "\r\n"; 
-        buf = buf + &self.maxValue.to_string() + "\r\n";
+        buf = buf + &self.fileType + end_line;
+        write_comments(&mut buf, &self.comments, end_line);
+        buf = buf + &format!("{} {}", self.width, self.heigth) + end_line;
+        buf = buf + &self.maxValue.to_string() + end_line;
+        if self.fileType == "P6" {
+            writer.write_all(buf.as_bytes())?;
+            writer.write_all(&write_binary_pixels(&self.pixels, self.maxValue))?;
+            writer.flush()?;
+            return Ok(());
+        }
         let mut index = 0;
         let mut pixel_string = String::new();
         for pixel in &self.pixels{
@@ -130,234 +170,439 @@ impl  Image {
         }
         buf += &pixel_string;
 
-        f.write_all(buf.as_bytes()).unwrap();
+        writer.write_all(buf.as_bytes())?;
+        writer.flush()?;
         Ok(())
     }
 
-    ///transform the RGB image to the gray image
+    ///write the image ppm in a vec of bytes
     /// 
     /// # Example
     /// 
     /// ```
-    /// let image_gray = image.grayscale();
+    /// let data = image.to_bytes()?;
+    /// assert_eq!(image, Image::from_bytes(&data)?);
+    /// ```
+    pub fn to_bytes(&self) -> Result<Vec<u8>, PpmError>{
+        let mut data = Vec::new();
+        self.write_to(&mut data)?;
+        Ok(data)
+    }
+
+    ///add a commentaire to the header
+    /// 
+    /// # Arguments
+    /// 
+    /// * `comment` - the text of the commentaire, without the `#`
+    /// 
+    /// # Example
+    /// 
+    /// ```
+    /// image.add_comment(" scanned in 2019");
     /// ```
-    pub fn grayscale(&self) -> Image{
-        let mut ret_pixels = Vec::new();
-        let pixels = self.pixels.clone();
+    pub fn add_comment(&mut self, comment : &str){
+        self.comments.push(comment.to_string());
+    }
 
-        for pixel in pixels{
-            ret_pixels.push(pixel.grayscale());
+    ///the value of a metadata, a commentaire written as "key: value"
+    /// 
+    /// # Arguments
+    /// 
+    /// * `key` - the name of the metadata
+    /// 
+    /// # Example
+    /// 
+    /// ```
+    /// image.set_metadata("camera", "12");
+    /// assert_eq!(Some("12"), image.metadata("camera"));
+    /// ```
+    pub fn metadata(&self, key : &str) -> Option<&str>{
+        self.comments.iter().filter_map(|comment| split_metadata(comment)).find(|&(name, _)| name == key).map(|(_, value)| value)
+    }
+
+    ///set the value of a metadata, the commentaire of the key is replaced or added
+    /// 
+    /// # Arguments
+    /// 
+    /// * `key` - the name of the metadata
+    /// * `value` - the value of the metadata
+    /// 
+    /// # Example
+    /// 
+    /// ```
+    /// image.set_metadata("exposure", "1/250");
+    /// ```
+    pub fn set_metadata(&mut self, key : &str, value : &str){
+        let comment = format!(" {}: {}", key, value);
+        match self.comments.iter().position(|comment| split_metadata(comment).map(|(name, _)| name) == Some(key)) {
+            Some(index) => self.comments[index] = comment,
+            None => self.comments.push(comment),
         }
+    }
+
+    ///remove a metadata, the value is returned if the key was found
+    /// 
+    /// # Arguments
+    /// 
+    /// * `key` - the name of the metadata
+    /// 
+    /// # Example
+    /// 
+    /// ```
+    /// image.remove_metadata("camera");
+    /// assert_eq!(None, image.metadata("camera"));
+    /// ```
+    pub fn remove_metadata(&mut self, key : &str) -> Option<String>{
+        let index = self.comments.iter().position(|comment| split_metadata(comment).map(|(name, _)| name) == Some(key))?;
+        let comment = self.comments.remove(index);
+        split_metadata(&comment).map(|(_, value)| value.to_string())
+    }
 
-        let fileType = self.fileType.to_string();
+    ///transform the RGB image to the gray image
+    /// 
+    /// # Example
+    /// 
+    /// ```
+    /// let image_gray = image.grayscale();
+    /// image_gray.save(Path::new("gray.pgm"))?;
+    /// ```
+    pub fn grayscale(&self) -> GrayImage{
+        GrayImage::from_image(self)
+    }
 
-        Image::new(ret_pixels, self.heigth, self.width, fileType, self.maxValue)
+    ///transform the RGB image to the gray image, with the gray level of the method
+    ///
+    /// # Arguments
+    ///
+    /// * `method` - the weights of the channels
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// let image_gray = image.grayscale_with(GrayMethod::Lightness);
+    /// ```
+    pub fn grayscale_with(&self, method : GrayMethod) -> GrayImage{
+        GrayImage::from_image_with(self, method)
     }
 }
 
-/// remove the espace in the start of the string
+/// read a ppm image, ASCII (P3) or binary (P6), the reader is left just after the last sample
 /// 
 /// # Arguments
 /// 
-/// `contents` - the string for remove
+/// `reader` - the reader at the start of the image
 /// 
 /// # Example
+/// 
 /// ```
-/// let mut test_str = "   a".to_string();
-/// filter_start_espace(&mut test_str);
-/// assert_eq!("a", test_str);
+/// let mut reader = PnmReader::new("P3\n1 1\n255\n7 91 43\n".as_bytes());
+/// let image = read_image(&mut reader)?;
 /// ```
-fn filter_start_espace(contents : &mut String){
-    let mut index = 0;
-    for c in contents.chars() {
-        if c != ' ' {
-            *contents = contents[index..].to_string();
-            return;
+pub(crate) fn read_image<R : BufRead>(reader : &mut PnmReader<R>) -> Result<Image, PpmError>{
+    let position = reader.position();
+    let header = reader.read_header()?;
+    let number = header.heigth * header.width * 3;
+
+    let samples = match &*header.magic {
+        "P6" => reader.read_binary_samples(number, header.max_value)?,
+        "P3" => reader.read_plain_samples(number, header.max_value)?,
+        _ => return Err(PpmError::BadMagic{ magic : header.magic, position }),
+    };
+
+    Ok(Image{ pixels : samples_to_pixels(&samples), heigth : header.heigth, width : header.width, fileType : header.magic,
+        maxValue : header.max_value, comments : reader.take_comments()})
+}
+
+/// write the commentaires of a header, each line of a commentaire after a `#`
+/// 
+/// an empty commentaire is written as a bare `#`, so that it is read back
+/// 
+/// # Arguments
+/// 
+/// * `buf` - the header being written
+/// * `comments` - the commentaires, without the `#`
+/// * `end_line` - the end of the lines of the header
+/// 
+/// # Example
+/// 
+/// ```
+/// let mut buf = String::new();
+/// write_comments(&mut buf, &[" camera: 12".to_string(), String::new()], "\n");
+/// assert_eq!("# camera: 12\n#\n", buf);
+/// ```
+pub(crate) fn write_comments(buf : &mut String, comments : &[String], end_line : &str){
+    for comment in comments{
+        if comment.is_empty() {
+            *buf += "#";
+            *buf += end_line;
+        }
+        // a commentaire stop at the end of the line
+        for line in comment.lines(){
+            *buf += "#";
+            *buf += line;
+            *buf += end_line;
         }
-        index += 1;
     }
-    *contents = "".to_string();
 }
 
-/// find next line from the file that not only contain the commentaire
+/// split a commentaire "key: value" in its key and its value, without the spaces around them
 /// 
 /// # Arguments
 /// 
-/// `line` - a line read by file
+/// `comment` - the text of the commentaire
 /// 
 /// # Example
+/// 
 /// ```
-/// let mut f_w = File::create(Path::new("test.txt"))?;
-/// f_w.write_all("first\n#sathey\nhello!".as_bytes())?;
-///
-/// let f_r = File::open(Path::new("test.txt"));
-/// let f_r = BufReader::new(f_r.unwrap());
-///
-/// let mut lines = f_r.lines();
-///
-/// assert_eq!("first".to_string(), find_line_not_commentaire(&mut lines));
-/// assert_eq!("hello!".to_string(), find_line_not_commentaire(&mut lines));
-///
-/// fs::remove_file(Path::new("test.txt"))?;
+/// assert_eq!(Some(("camera", "12")), split_metadata(" camera: 12"));
 /// ```
-fn find_line_not_commentaire(lines : &mut Lines<BufReader<File>>) -> String{
-    let mut line = lines.next();
-    while !line.is_none() {
-
-        let res_string = filter_commentaire(&line.unwrap().unwrap());
-        if res_string != ""{
-            return res_string;
-        }
-        line = lines.next();
+fn split_metadata(comment : &str) -> Option<(&str, &str)>{
+    let mut parts = comment.splitn(2, ':');
+    let key = parts.next()?.trim();
+    let value = parts.next()?.trim();
+    if key.is_empty() {
+        return None;
     }
-    "".to_string()
+    Some((key, value))
 }
 
-/// remove the commentaire of the string
+/// write the samples of a binary (P5, P6) image
+/// 
+/// each sample take one byte if `max_value` is less than 256, else two bytes in big-endian
 /// 
 /// # Arguments
 /// 
-/// `contents` - the string for remove
+/// `samples` - the samples to write
+/// `max_value` - the max value of the samples in the image
 /// 
 /// # Example
+/// 
 /// ```
-/// let mut test_str = "   a".to_string();
-/// filter_start_espace(&mut test_str);
-/// assert_eq!("a", test_str);
+/// assert_eq!(vec![0x03, 0xe8], write_binary_samples(&[1000], 65535));
 /// ```
-fn filter_commentaire(contents : &String) -> String{
-    let mut index = 0;
-    for c in contents.chars() {
-        if c == '#'{
-            return contents[..index].to_string();
+pub(crate) fn write_binary_samples(samples : &[u16], max_value : usize) -> Vec<u8>{
+    let sample_size = if max_value < 256 { 1 } else { 2 };
+    let mut data = Vec::with_capacity(samples.len() * sample_size);
+    for &sample in samples{
+        if sample_size == 2 {
+            data.push((sample >> 8) as u8);
         }
-        index += 1;
+        data.push(sample as u8);
     }
-    contents.clone()
+    data
 }
 
-/// find the first number from the string
+/// group the samples of a RGB image in pixels
 /// 
 /// # Arguments
 /// 
-/// `contents` - the string for find
+/// `samples` - the samples, three by pixel
 /// 
 /// # Example
 /// 
 /// ```
-/// let mut test_str = "3 41 5".to_string();
-/// assert_eq!(3, find_number(&mut test_str)?);
-/// assert_eq!(41, find_number(&mut test_str)?);
+/// let pixels = samples_to_pixels(&[4, 11, 4, 12, 9, 11]);
+/// assert_eq!(vec![Pixels::new(4, 11, 4), Pixels::new(12, 9, 11)], pixels);
 /// ```
-fn find_number(contents : &mut String) -> Result<usize, std::num::ParseIntError>{
-    let mut index = 0;
-    for c in contents.chars(){
-        if c == ' ' {
-            let n = &contents[..index].to_string().parse::<usize>()?;
-            *contents = contents[index..].to_string();
-            filter_start_espace(contents);
-            return Ok(*n);
-        }
-        index += 1;
+pub(crate) fn samples_to_pixels(samples : &[u16]) -> Vec<Pixels>{
+    samples.chunks(3).map(|rgb| Pixels::new(rgb[0], rgb[1], rgb[2])).collect()
+}
+
+/// write the pixels of a binary (P6) image
+/// 
+/// # Arguments
+/// 
+/// `pixels` - the pixels to write
+/// `max_value` - the max value of the pixels in the image
+/// 
+/// # Example
+/// 
+/// ```
+/// let pixels = vec![Pixels::new(4, 11, 4), Pixels::new(12, 9, 11)];
+/// assert_eq!(vec![4, 11, 4, 12, 9, 11], write_binary_pixels(&pixels, 255));
+/// ```
+pub(crate) fn write_binary_pixels(pixels : &[Pixels], max_value : usize) -> Vec<u8>{
+    let mut samples = Vec::with_capacity(pixels.len() * 3);
+    for pixel in pixels{
+        samples.push(pixel.red);
+        samples.push(pixel.green);
+        samples.push(pixel.blue);
     }
-    let ret = contents.parse::<usize>().unwrap();
-    *contents = "".to_string();
-    Ok(ret)
+    write_binary_samples(&samples, max_value)
 }
 
-/// find the first pixel from the string
+/// scale a sample from the range 0 .. `max_value` to the range 0 .. `target`, rounded to the nearest,
+/// a sample over the max value is clamped to it, so the scaled sample fit in `target`
 /// 
 /// # Arguments
 /// 
-/// `contents` - the string for find
+/// `sample` - the sample to scale
+/// `max_value` - the max value of the samples in the image
+/// `target` - the max value of the samples after the scaling
 /// 
 /// # Example
 /// 
 /// ```
-/// extern crate ppm;
-/// let mut test_pixel = "4 11 4 12 9 11".to_string();
-/// let pixel1 = ppm::Pixels::new(4, 11, 4);
-/// let pixel2 = ppm::Pixels::new(12, 9, 11);
-/// assert_eq!(pixel1, find_pixels(&mut test_pixel));
-/// assert_eq!(pixel2, find_pixels(&mut test_pixel));
+/// assert_eq!(170, scale_sample(10, 15, 255));
+/// assert_eq!(255, scale_sample(300, 255, 255));
 /// ```
-fn find_pixels(mut contents : &mut String) -> Pixels{
-    
-    let red = find_number(&mut contents).unwrap();
-    let green = find_number(&mut contents).unwrap();
-    let blue = find_number(&mut contents).unwrap();
+pub(crate) fn scale_sample(sample : u16, max_value : usize, target : usize) -> u16{
+    if max_value == 0 {
+        return (sample as usize).min(target) as u16;
+    }
+    let sample = (sample as usize).min(max_value) as u16;
+    if max_value == target {
+        return sample;
+    }
+    ((sample as usize * target + max_value / 2) / max_value) as u16
+}
 
-    Pixels::new(red as u8, green as u8, blue as u8)
+/// the capacity of a buffer for the `size` bytes declared by a header, at most `expansion` bytes for each
+/// of the `data_len` bytes of the data, so a forged header do not allocate more than the data can give
+/// 
+/// # Example
+/// 
+/// ```
+/// let out = Vec::<u8>::with_capacity(bounded_capacity(usize::MAX, 2, 128));
+/// ```
+pub(crate) fn bounded_capacity(size : usize, data_len : usize, expansion : usize) -> usize{
+    size.min(data_len.saturating_mul(expansion))
+}
+
+/// the image of `resource/ppmImage.ppm` with its samples scaled from 0 .. 15 to 0 .. `max_value`,
+/// the image of reference of the encoders
+#[cfg(test)]
+pub(crate) fn ppm_scaled_to(max_value : usize) -> Result<Image, PpmError>{
+    let ppm = Image::new_with_file(Path::new("resource/ppmImage.ppm"))?;
+    let scale = |sample| scale_sample(sample, ppm.maxValue, max_value);
+    let pixels = ppm.pixels.iter().map(|pixel| Pixels::new(scale(pixel.red), scale(pixel.green), scale(pixel.blue))).collect();
+    Ok(Image::new(pixels, ppm.heigth, ppm.width, "P6".to_string(), max_value))
 }
 
 impl PartialEq for Image {
     fn eq(&self, other: &Self) -> bool {
-        let mut pixels_self = self.pixels.clone();
-        let mut pixels_other = other.pixels.clone();
-        while !pixels_self.is_empty() {
-            if ! (pixels_self.pop() == pixels_other.pop()){
-                return false;
-            }
-        }
+        // the commentaires are metadata, they are not compared
+        self.pixels == other.pixels &&
         self.fileType == other.fileType &&
         self.maxValue == other.maxValue &&
-        self.heigth == self.heigth &&
-        self.width == self.width
+        self.heigth == other.heigth &&
+        self.width == other.width
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use error::Position;
     use std::fs;
 
     #[test]
-    fn test_filter_start_espace(){
-        let mut test_str = "   a".to_string();
-        filter_start_espace(&mut test_str);
-        assert_eq!("a", test_str);
+    fn test_samples_to_pixels(){
+        let pixels = samples_to_pixels(&[4, 11, 4, 12, 9, 65535]);
+        assert_eq!(vec![Pixels::new(4, 11, 4), Pixels::new(12, 9, 65535)], pixels);
+    }
+
+    #[test]
+    fn test_write_binary_pixels(){
+        let pixels = vec![Pixels::new(4, 11, 4), Pixels::new(12, 9, 11)];
+        assert_eq!(vec![4, 11, 4, 12, 9, 11], write_binary_pixels(&pixels, 255));
+        assert_eq!(vec![0, 4, 0, 11, 0, 4, 0, 12, 0, 9, 0, 11], write_binary_pixels(&pixels, 1000));
+
+        let pixels = vec![Pixels::new(1000, 256, 65535)];
+        assert_eq!(vec![0x03, 0xe8, 0x01, 0x00, 0xff, 0xff], write_binary_pixels(&pixels, 65535));
     }
 
     #[test]
-    fn test_find_number()-> Result<(), std::num::ParseIntError>{
-        let mut test_str = "3 41 5".to_string();
-        assert_eq!(3, find_number(&mut test_str)?);
-        assert_eq!(41, find_number(&mut test_str)?);
-        
-        assert_eq!(5, find_number(&mut test_str)?);
+    fn test_new_with_file_p6() -> std::io::Result<()>{
+        let mut f_w = File::create(Path::new("test_new_with_file_p6.ppm"))?;
+        f_w.write_all(b"P6\n# binary\n2 1\n255\n")?;
+        f_w.write_all(&[7, 91, 43, 10, 32, 13])?;
+
+        let image_load = Image::new_with_file(Path::new("test_new_with_file_p6.ppm"))?;
+        assert_eq!("P6", image_load.fileType);
+        assert_eq!(255, image_load.maxValue);
+        assert_eq!(vec![Pixels::new(7, 91, 43), Pixels::new(10, 32, 13)], image_load.pixels);
+
+        fs::remove_file(Path::new("test_new_with_file_p6.ppm"))?;
 
         Ok(())
     }
 
     #[test]
-    fn test_find_pixel(){
-        let mut test_pixel = "4 11 4 12 9 11".to_string();
-        let pixel1 = Pixels::new(4, 11, 4);
-        let pixel2 = Pixels::new(12, 9, 11);
-        assert_eq!(pixel1, find_pixels(&mut test_pixel));
-        assert_eq!(pixel2, find_pixels(&mut test_pixel));
+    fn test_new_with_file_one_line() -> std::io::Result<()>{
+        let mut f_w = File::create(Path::new("test_new_with_file_one_line.ppm"))?;
+        f_w.write_all(b"P3 1 2 15 1 2 3 # comment\n4 5 6")?;
+
+        let image_load = Image::new_with_file(Path::new("test_new_with_file_one_line.ppm"))?;
+        assert_eq!(vec![Pixels::new(1, 2, 3), Pixels::new(4, 5, 6)], image_load.pixels);
+
+        fs::remove_file(Path::new("test_new_with_file_one_line.ppm"))?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_new_with_file_corpus() -> std::io::Result<()>{
+        let image = Image::new_with_file(Path::new("resource/ppmImage.ppm"))?;
+        assert_eq!(16, image.pixels.len());
+        assert_eq!(Pixels::new(15, 0, 15), image.pixels[3]);
+
+        let mut count = 0;
+        for entry in fs::read_dir(Path::new("resource/headers"))?{
+            let image_load = Image::new_with_file(&entry?.path())?;
+            assert_eq!(image.pixels, image_load.pixels);
+            assert_eq!(15, image_load.maxValue);
+            count += 1;
+        }
+        assert!(count > 0);
+
+        Ok(())
     }
 
     #[test]
-    fn test_filter_commentaire(){
-        let test_str = "hello#sayhay".to_string();
-        assert_eq!("hello", filter_commentaire(&test_str));
+    fn test_new_with_file_not_ppm() -> std::io::Result<()>{
+        let mut f_w = File::create(Path::new("test_new_with_file_not_ppm.ppm"))?;
+        f_w.write_all(b"P2 1 1 15 1")?;
+
+        match Image::new_with_file(Path::new("test_new_with_file_not_ppm.ppm")) {
+            Err(PpmError::BadMagic{ magic, .. }) => assert_eq!("P2", magic),
+            _ => panic!("P2 is not a PPM image"),
+        }
+
+        fs::remove_file(Path::new("test_new_with_file_not_ppm.ppm"))?;
+
+        Ok(())
     }
 
     #[test]
-    fn test_find_line_not_commentaire() -> std::io::Result<()>{
-        let mut f_w = File::create(Path::new("test_find_line.txt"))?;
-        f_w.write_all("first\n#sathey\nhello!".as_bytes())?;
+    fn test_new_with_file_errors() -> std::io::Result<()>{
+        match Image::new_with_file(Path::new("test_no_such_image.ppm")) {
+            Err(PpmError::Io(_)) => {},
+            _ => panic!("the file does not exist"),
+        }
 
-        let f_r = File::open(Path::new("test_find_line.txt"));
-        let f_r = BufReader::new(f_r.unwrap());
+        let mut f_w = File::create(Path::new("test_new_with_file_errors.ppm"))?;
+        f_w.write_all(b"P3 2 1 15\n1 2 3\n4 x 6")?;
+        match Image::new_with_file(Path::new("test_new_with_file_errors.ppm")) {
+            Err(PpmError::BadHeader{ position, .. }) => assert_eq!(Position{line : 3, byte : 18}, position),
+            _ => panic!("x is not a sample"),
+        }
 
-        let mut lines = f_r.lines();
+        let mut f_w = File::create(Path::new("test_new_with_file_errors.ppm"))?;
+        f_w.write_all(b"P3 2 1 15\n1 2 3\n4 16 6")?;
+        match Image::new_with_file(Path::new("test_new_with_file_errors.ppm")) {
+            Err(PpmError::SampleOverMaxval{ sample, .. }) => assert_eq!(16, sample),
+            _ => panic!("16 is over the max value"),
+        }
 
-        assert_eq!("first".to_string(), find_line_not_commentaire(&mut lines));
-        assert_eq!("hello!".to_string(), find_line_not_commentaire(&mut lines));
+        let mut f_w = File::create(Path::new("test_new_with_file_errors.ppm"))?;
+        f_w.write_all(b"P6 2 1 255\n\x01\x02\x03\x04")?;
+        match Image::new_with_file(Path::new("test_new_with_file_errors.ppm")) {
+            Err(PpmError::Truncated{ expected, found, .. }) => assert_eq!((6, 4), (expected, found)),
+            _ => panic!("two samples are missing"),
+        }
 
-        fs::remove_file(Path::new("test_find_line.txt"))?;
+        fs::remove_file(Path::new("test_new_with_file_errors.ppm"))?;
 
         Ok(())
     }
@@ -378,6 +623,192 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_save_p6() -> std::io::Result<()>{
+        let mut pixels = Vec::new();
+        pixels.push(Pixels::new(7, 91, 43));
+        pixels.push(Pixels::new(14, 32, 56));
+        pixels.push(Pixels::new(23, 43, 32));
+        let image = Image::new(pixels, 1, 3, "P6".to_string(), 255);
+        image.save(Path::new("test_save_image_p6.ppm"))?;
+        let image_load = Image::new_with_file(Path::new("test_save_image_p6.ppm"));
+        assert_eq!(image, image_load?);
+
+        let data = fs::read(Path::new("test_save_image_p6.ppm"))?;
+        assert_eq!(&[7, 91, 43, 14, 32, 56, 23, 43, 32], &data[data.len() - 9 ..]);
+
+        fs::remove_file(Path::new("test_save_image_p6.ppm"))?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_save_p6_two_bytes() -> std::io::Result<()>{
+        let mut pixels = Vec::new();
+        pixels.push(Pixels::new(7, 910, 43));
+        pixels.push(Pixels::new(65535, 256, 0));
+        let image = Image::new(pixels, 1, 2, "P6".to_string(), 65535);
+        image.save(Path::new("test_save_image_p6_16.ppm"))?;
+        let image_load = Image::new_with_file(Path::new("test_save_image_p6_16.ppm"));
+        assert_eq!(image, image_load?);
+
+        fs::remove_file(Path::new("test_save_image_p6_16.ppm"))?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_save_16_bits() -> std::io::Result<()>{
+        let mut pixels = Vec::new();
+        pixels.push(Pixels::new(7, 910, 43));
+        pixels.push(Pixels::new(65535, 256, 0));
+        pixels.push(Pixels::new(300, 40000, 1023));
+        let image = Image::new(pixels, 1, 3, "P3".to_string(), 65535);
+        image.save(Path::new("test_save_image_16.ppm"))?;
+        let image_load = Image::new_with_file(Path::new("test_save_image_16.ppm"))?;
+        assert_eq!(image.pixels, image_load.pixels);
+        assert_eq!(65535, image_load.maxValue);
+
+        fs::remove_file(Path::new("test_save_image_16.ppm"))?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_save_dimension_order() -> std::io::Result<()>{
+        let pixels = vec![Pixels::new(7, 91, 43), Pixels::new(14, 32, 56), Pixels::new(23, 43, 32)];
+        let image = Image::new(pixels, 1, 3, "P3".to_string(), 91);
+        image.save(Path::new("test_save_dimension_order.ppm"))?;
+
+        // the width is written first, as the C `ppma_write_header` does
+        let text = fs::read_to_string(Path::new("test_save_dimension_order.ppm"))?;
+        assert_eq!(Some("3 1"), text.lines().nth(1));
+
+        let image_load = Image::new_with_file(Path::new("test_save_dimension_order.ppm"))?;
+        assert_eq!((1, 3), (image_load.heigth, image_load.width));
+
+        fs::remove_file(Path::new("test_save_dimension_order.ppm"))?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_save_comments() -> Result<(), PpmError>{
+        for file_type in &["P3", "P6"] {
+            let mut image = Image::new(vec![Pixels::new(7, 91, 43)], 1, 1, file_type.to_string(), 91);
+            image.add_comment(" source: a1b2c3");
+            image.add_comment("no space");
+            image.add_comment("");
+            image.save(Path::new("test_save_comments.ppm"))?;
+
+            let image_load = Image::new_with_file(Path::new("test_save_comments.ppm"))?;
+            assert_eq!(image.comments, image_load.comments);
+
+            fs::remove_file(Path::new("test_save_comments.ppm"))?;
+        }
+
+        let image = Image::read_from("P3 # size\n1 1 # two\n91\n7 91 43\n".as_bytes())?;
+        assert_eq!(vec![" size", " two"], image.comments);
+        assert_eq!(b"P3\r\n# size\r\n# two\r\n1 1\r\n91\r\n7 91 43 ".to_vec(), image.to_bytes()?);
+
+        // a bare `#` is an empty commentaire, it is kept
+        let image = Image::read_from("P6\n#\n# camera: 12\n1 1\n255\n\x07\x5b\x2b".as_bytes())?;
+        assert_eq!(vec!["", " camera: 12"], image.comments);
+        assert_eq!(b"P6\n#\n# camera: 12\n1 1\n255\n\x07\x5b\x2b".to_vec(), image.to_bytes()?);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_metadata(){
+        let mut image = Image::new(vec![Pixels::new(7, 91, 43)], 1, 1, "P6".to_string(), 91);
+        image.add_comment(" made by hand");
+        image.set_metadata("camera", "12");
+        image.set_metadata("exposure", "1/250");
+        assert_eq!(Some("12"), image.metadata("camera"));
+        assert_eq!(None, image.metadata("made by hand"));
+
+        image.set_metadata("camera", "14");
+        assert_eq!(vec![" made by hand", " camera: 14", " exposure: 1/250"], image.comments);
+
+        assert_eq!(Some("14".to_string()), image.remove_metadata("camera"));
+        assert_eq!(None, image.remove_metadata("camera"));
+        assert_eq!(vec![" made by hand", " exposure: 1/250"], image.comments);
+    }
+
+    #[test]
+    fn test_read_from() -> Result<(), PpmError>{
+        let image = Image::read_from("P3\n# plain\n2 1\n255\n7 91 43\n10 32 13\n".as_bytes())?;
+        assert_eq!(vec![Pixels::new(7, 91, 43), Pixels::new(10, 32, 13)], image.pixels);
+
+        let image = Image::read_from(&b"P6\n1 1\n65535\n\x03\xe8\x01\x00\xff\xff"[..])?;
+        assert_eq!(vec![Pixels::new(1000, 256, 65535)], image.pixels);
+
+        match Image::read_from("P3\n2 1\n255\n7 91 43\n".as_bytes()) {
+            Err(PpmError::Truncated{ expected, found, .. }) => assert_eq!((6, 3), (expected, found)),
+            _ => panic!("the second pixel is missing"),
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_write_to() -> Result<(), PpmError>{
+        let image = Image::new(vec![Pixels::new(7, 91, 43), Pixels::new(14, 32, 56)], 1, 2, "P6".to_string(), 255);
+        let mut data = Vec::new();
+        image.write_to(&mut data)?;
+        assert_eq!(b"P6\n2 1\n255\n\x07\x5b\x2b\x0e\x20\x38".to_vec(), data);
+
+        let image = Image::new(vec![Pixels::new(7, 91, 43)], 1, 1, "P3".to_string(), 91);
+        let mut data = Vec::new();
+        image.write_to(&mut data)?;
+        assert_eq!(b"P3\r\n1 1\r\n91\r\n7 91 43 ".to_vec(), data);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_bytes() -> Result<(), PpmError>{
+        let mut pixels = Vec::new();
+        pixels.push(Pixels::new(7, 910, 43));
+        pixels.push(Pixels::new(65535, 256, 0));
+        pixels.push(Pixels::new(300, 40000, 1023));
+        for file_type in &["P3", "P6"] {
+            let image = Image::new(pixels.clone(), 1, 3, file_type.to_string(), 65535);
+            let data = image.to_bytes()?;
+            assert_eq!(image, Image::from_bytes(&data)?);
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_scale_sample(){
+        assert_eq!(170, scale_sample(10, 15, 255));
+        assert_eq!(257, scale_sample(1, 255, 65535));
+        // the samples over the max value are clamped before the scaling
+        assert_eq!(255, scale_sample(300, 255, 255));
+        assert_eq!(255, scale_sample(16, 15, 255));
+        assert_eq!(255, scale_sample(300, 0, 255));
+    }
+
+    #[test]
+    fn test_bounded_capacity(){
+        assert_eq!(10, bounded_capacity(10, 1, 128));
+        assert_eq!(256, bounded_capacity(usize::MAX, 2, 128));
+        assert_eq!(usize::MAX, bounded_capacity(usize::MAX, usize::MAX, 128));
+    }
+
+    #[test]
+    fn test_ppm_scaled_to() -> Result<(), PpmError>{
+        let ppm = Image::new_with_file(Path::new("resource/ppmImage.ppm"))?;
+        let image = ppm_scaled_to(255)?;
+        assert_eq!(15, ppm.maxValue);
+        assert_eq!(ppm.pixels.iter().map(|pixel| Pixels::new(pixel.red * 17, pixel.green * 17, pixel.blue * 17)).collect::<Vec<Pixels>>(), image.pixels);
+
+        Ok(())
+    }
+
     #[test]
     fn test_grayscale(){
         let mut pixels = Vec::new();
@@ -388,13 +819,15 @@ mod tests {
 
         let mut pixels_grayscale = Vec::new();
         for pixel in &image.pixels{
-            pixels_grayscale.push(pixel.grayscale());
+            pixels_grayscale.push(pixel.luma());
         }
-        let image_grayscale = Image::new(pixels_grayscale, 1, 3, "P3".to_string(), 91);
+        let image_grayscale = GrayImage::new(pixels_grayscale, 1, 3, "P2".to_string(), 91);
         let image_compare = image.grayscale();
 
         assert_eq!(image_grayscale, image_compare);
+        assert_eq!(vec![60, 29, 36], image_compare.pixels);
+        assert_eq!(vec![70, 30, 38], image.grayscale_with(GrayMethod::Rec709).pixels);
+        assert_eq!(vec![49, 35, 33], image.grayscale_with(GrayMethod::Lightness).pixels);
     }
     
-}
-
+}
\ No newline at end of file
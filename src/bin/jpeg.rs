@@ -0,0 +1,661 @@
+use images::{self, Image};
+use error::PpmError;
+
+use std::f32::consts::PI;
+use std::fs::File;
+use std::io::BufWriter;
+use std::io::prelude::*;
+use std::path::Path;
+
+/// the quality used when no quality is given, as by `save` with the JPEG format
+pub(crate) const DEFAULT_QUALITY : u8 = 85;
+
+/// the order of the coefficients of a block in the file, from the low to the high frequencies
+const ZIGZAG : [usize; 64] = [
+    0, 1, 8, 16, 9, 2, 3, 10, 17, 24, 32, 25, 18, 11, 4, 5,
+    12, 19, 26, 33, 40, 48, 41, 34, 27, 20, 13, 6, 7, 14, 21, 28,
+    35, 42, 49, 56, 57, 50, 43, 36, 29, 22, 15, 23, 30, 37, 44, 51,
+    58, 59, 52, 45, 38, 31, 39, 46, 53, 60, 61, 54, 47, 55, 62, 63];
+
+/// the quantization table of the luminance at the quality 50, in the order of the rows (Annex K)
+const LUMA_QUANTIZATION : [u16; 64] = [
+    16, 11, 10, 16, 24, 40, 51, 61, 12, 12, 14, 19, 26, 58, 60, 55,
+    14, 13, 16, 24, 40, 57, 69, 56, 14, 17, 22, 29, 51, 87, 80, 62,
+    18, 22, 37, 56, 68, 109, 103, 77, 24, 35, 55, 64, 81, 104, 113, 92,
+    49, 64, 78, 87, 103, 121, 120, 101, 72, 92, 95, 98, 112, 100, 103, 99];
+
+/// the quantization table of the chrominance at the quality 50, in the order of the rows (Annex K)
+const CHROMA_QUANTIZATION : [u16; 64] = [
+    17, 18, 24, 47, 99, 99, 99, 99, 18, 21, 26, 66, 99, 99, 99, 99,
+    24, 26, 56, 99, 99, 99, 99, 99, 47, 66, 99, 99, 99, 99, 99, 99,
+    99, 99, 99, 99, 99, 99, 99, 99, 99, 99, 99, 99, 99, 99, 99, 99,
+    99, 99, 99, 99, 99, 99, 99, 99, 99, 99, 99, 99, 99, 99, 99, 99];
+
+/// the number of codes of each length, 1 to 16 bits, of the DC luminance table (Annex K)
+const DC_LUMA_BITS : [u8; 16] = [0, 1, 5, 1, 1, 1, 1, 1, 1, 0, 0, 0, 0, 0, 0, 0];
+const DC_LUMA_VALUES : [u8; 12] = [0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11];
+const DC_CHROMA_BITS : [u8; 16] = [0, 3, 1, 1, 1, 1, 1, 1, 1, 1, 1, 0, 0, 0, 0, 0];
+const DC_CHROMA_VALUES : [u8; 12] = [0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11];
+const AC_LUMA_BITS : [u8; 16] = [0, 2, 1, 3, 3, 2, 4, 3, 5, 5, 4, 4, 0, 0, 1, 0x7d];
+const AC_LUMA_VALUES : [u8; 162] = [
+    0x01, 0x02, 0x03, 0x00, 0x04, 0x11, 0x05, 0x12, 0x21, 0x31, 0x41, 0x06, 0x13, 0x51, 0x61, 0x07,
+    0x22, 0x71, 0x14, 0x32, 0x81, 0x91, 0xa1, 0x08, 0x23, 0x42, 0xb1, 0xc1, 0x15, 0x52, 0xd1, 0xf0,
+    0x24, 0x33, 0x62, 0x72, 0x82, 0x09, 0x0a, 0x16, 0x17, 0x18, 0x19, 0x1a, 0x25, 0x26, 0x27, 0x28,
+    0x29, 0x2a, 0x34, 0x35, 0x36, 0x37, 0x38, 0x39, 0x3a, 0x43, 0x44, 0x45, 0x46, 0x47, 0x48, 0x49,
+    0x4a, 0x53, 0x54, 0x55, 0x56, 0x57, 0x58, 0x59, 0x5a, 0x63, 0x64, 0x65, 0x66, 0x67, 0x68, 0x69,
+    0x6a, 0x73, 0x74, 0x75, 0x76, 0x77, 0x78, 0x79, 0x7a, 0x83, 0x84, 0x85, 0x86, 0x87, 0x88, 0x89,
+    0x8a, 0x92, 0x93, 0x94, 0x95, 0x96, 0x97, 0x98, 0x99, 0x9a, 0xa2, 0xa3, 0xa4, 0xa5, 0xa6, 0xa7,
+    0xa8, 0xa9, 0xaa, 0xb2, 0xb3, 0xb4, 0xb5, 0xb6, 0xb7, 0xb8, 0xb9, 0xba, 0xc2, 0xc3, 0xc4, 0xc5,
+    0xc6, 0xc7, 0xc8, 0xc9, 0xca, 0xd2, 0xd3, 0xd4, 0xd5, 0xd6, 0xd7, 0xd8, 0xd9, 0xda, 0xe1, 0xe2,
+    0xe3, 0xe4, 0xe5, 0xe6, 0xe7, 0xe8, 0xe9, 0xea, 0xf1, 0xf2, 0xf3, 0xf4, 0xf5, 0xf6, 0xf7, 0xf8,
+    0xf9, 0xfa];
+const AC_CHROMA_BITS : [u8; 16] = [0, 2, 1, 2, 4, 4, 3, 4, 7, 5, 4, 4, 0, 1, 2, 0x77];
+const AC_CHROMA_VALUES : [u8; 162] = [
+    0x00, 0x01, 0x02, 0x03, 0x11, 0x04, 0x05, 0x21, 0x31, 0x06, 0x12, 0x41, 0x51, 0x07, 0x61, 0x71,
+    0x13, 0x22, 0x32, 0x81, 0x08, 0x14, 0x42, 0x91, 0xa1, 0xb1, 0xc1, 0x09, 0x23, 0x33, 0x52, 0xf0,
+    0x15, 0x62, 0x72, 0xd1, 0x0a, 0x16, 0x24, 0x34, 0xe1, 0x25, 0xf1, 0x17, 0x18, 0x19, 0x1a, 0x26,
+    0x27, 0x28, 0x29, 0x2a, 0x35, 0x36, 0x37, 0x38, 0x39, 0x3a, 0x43, 0x44, 0x45, 0x46, 0x47, 0x48,
+    0x49, 0x4a, 0x53, 0x54, 0x55, 0x56, 0x57, 0x58, 0x59, 0x5a, 0x63, 0x64, 0x65, 0x66, 0x67, 0x68,
+    0x69, 0x6a, 0x73, 0x74, 0x75, 0x76, 0x77, 0x78, 0x79, 0x7a, 0x82, 0x83, 0x84, 0x85, 0x86, 0x87,
+    0x88, 0x89, 0x8a, 0x92, 0x93, 0x94, 0x95, 0x96, 0x97, 0x98, 0x99, 0x9a, 0xa2, 0xa3, 0xa4, 0xa5,
+    0xa6, 0xa7, 0xa8, 0xa9, 0xaa, 0xb2, 0xb3, 0xb4, 0xb5, 0xb6, 0xb7, 0xb8, 0xb9, 0xba, 0xc2, 0xc3,
+    0xc4, 0xc5, 0xc6, 0xc7, 0xc8, 0xc9, 0xca, 0xd2, 0xd3, 0xd4, 0xd5, 0xd6, 0xd7, 0xd8, 0xd9, 0xda,
+    0xe2, 0xe3, 0xe4, 0xe5, 0xe6, 0xe7, 0xe8, 0xe9, 0xea, 0xf2, 0xf3, 0xf4, 0xf5, 0xf6, 0xf7, 0xf8,
+    0xf9, 0xfa];
+
+/// the sampling of the chrominance (Cb and Cr) relative to the luminance
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Subsampling{
+    /// the chrominance at full resolution
+    Chroma444,
+    /// the chrominance at half the width and half the heigth, the smallest files
+    Chroma420,
+}
+
+/// the table of a Huffman code of the encoder, the code and its length by symbol
+struct HuffmanTable{
+    codes : [(u16, u8); 256],
+}
+
+impl HuffmanTable {
+
+    /// build the canonical code from the number of codes of each length and the symbols
+    fn new(bits : &[u8; 16], values : &[u8]) -> HuffmanTable{
+        let mut codes = [(0, 0); 256];
+        let mut code = 0u16;
+        let mut index = 0;
+        for (len, &count) in bits.iter().enumerate(){
+            for _ in 0 .. count{
+                codes[values[index] as usize] = (code, len as u8 + 1);
+                code += 1;
+                index += 1;
+            }
+            code <<= 1;
+        }
+        HuffmanTable{codes}
+    }
+}
+
+/// a writer of the bits of the entropy-coded data, the first bit is the highest of a byte
+struct BitWriter{
+    out : Vec<u8>,
+    buffer : u32,
+    count : u32,
+}
+
+impl BitWriter {
+
+    fn bits(&mut self, value : u16, number : u8){
+        self.buffer = (self.buffer << number) | (value as u32 & ((1 << number) - 1));
+        self.count += number as u32;
+        while self.count >= 8 {
+            let byte = (self.buffer >> (self.count - 8)) as u8;
+            self.out.push(byte);
+            // a 0xff in the data is followed by a 0 to not be taken for a marker
+            if byte == 0xff {
+                self.out.push(0);
+            }
+            self.count -= 8;
+        }
+    }
+
+    fn code(&mut self, table : &HuffmanTable, symbol : u8){
+        let (code, len) = table.codes[symbol as usize];
+        self.bits(code, len);
+    }
+
+    /// fill the last byte with ones
+    fn finish(mut self) -> Vec<u8>{
+        if self.count > 0 {
+            let padding = 8 - self.count as u8;
+            self.bits((1 << padding) - 1, padding);
+        }
+        self.out
+    }
+}
+
+/// the quantization table for the quality, in the order of the rows
+///
+/// # Arguments
+///
+/// * `base` - the table at the quality 50
+/// * `quality` - the quality from 1 to 100
+fn quantization_table(base : &[u16; 64], quality : u8) -> [u16; 64]{
+    let quality = quality.clamp(1, 100) as u32;
+    let scale = if quality < 50 { 5000 / quality } else { 200 - quality * 2 };
+    let mut table = [0u16; 64];
+    for (value, &base) in table.iter_mut().zip(base.iter()){
+        *value = ((base as u32 * scale + 50) / 100).clamp(1, 255) as u16;
+    }
+    table
+}
+
+/// the number of bits of a value and the bits written for it, the negative values are written minus one
+fn magnitude(value : i32) -> (u8, u16){
+    let size = (32 - value.unsigned_abs().leading_zeros()) as u8;
+    let bits = if value < 0 { (value - 1) as u16 } else { value as u16 };
+    (size, bits & ((1u32 << size) - 1) as u16)
+}
+
+/// the discrete cosine transform of a block of samples centered on 0
+fn forward_dct(block : &[f32; 64], cosines : &[[f32; 8]; 8]) -> [f32; 64]{
+    let mut rows = [0f32; 64];
+    for y in 0 .. 8{
+        for u in 0 .. 8{
+            rows[y * 8 + u] = (0 .. 8).map(|x| block[y * 8 + x] * cosines[u][x]).sum();
+        }
+    }
+    let mut out = [0f32; 64];
+    for v in 0 .. 8{
+        for u in 0 .. 8{
+            out[v * 8 + u] = (0 .. 8).map(|y| rows[y * 8 + u] * cosines[v][y]).sum();
+        }
+    }
+    out
+}
+
+/// the cosines of the DCT with the normalization, `cosines[u][x]` is C(u)/2 cos((2x+1)uπ/16)
+fn dct_cosines() -> [[f32; 8]; 8]{
+    let mut cosines = [[0f32; 8]; 8];
+    for (u, row) in cosines.iter_mut().enumerate(){
+        let normalization = if u == 0 { 0.5 / 2f32.sqrt() } else { 0.5 };
+        for (x, cosine) in row.iter_mut().enumerate(){
+            *cosine = normalization * ((2 * x + 1) as f32 * u as f32 * PI / 16.0).cos();
+        }
+    }
+    cosines
+}
+
+/// write a marker segment with its length
+fn write_segment<W : Write>(writer : &mut W, marker : u8, data : &[u8]) -> Result<(), PpmError>{
+    writer.write_all(&[0xff, marker])?;
+    writer.write_all(&((data.len() + 2) as u16).to_be_bytes())?;
+    writer.write_all(data)?;
+    Ok(())
+}
+
+impl Image {
+
+    ///save the image in a file of jpeg, in the baseline JPEG format with a JFIF header
+    ///
+    /// # Arguments
+    ///
+    /// * `filename` - the name of the file
+    /// * `quality` - the quality from 1 to 100, the larger the better and the larger the file
+    /// * `subsampling` - the sampling of the chrominance
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// image.save_jpeg(Path::new("thumbnail.jpg"), 75, Subsampling::Chroma420)?;
+    /// ```
+    pub fn save_jpeg(&self, filename : &Path, quality : u8, subsampling : Subsampling) -> Result<(), PpmError>{
+        let mut writer = BufWriter::new(File::create(filename)?);
+        self.write_jpeg(&mut writer, quality, subsampling)?;
+        writer.flush()?;
+        Ok(())
+    }
+
+    ///write the image as baseline JPEG data to a writer
+    ///
+    /// the samples are scaled to 0 .. 255 and transformed to YCbCr, the blocks of the edges
+    /// are filled by repeating the last row and column
+    ///
+    /// # Arguments
+    ///
+    /// * `writer` - the writer of the JPEG data
+    /// * `quality` - the quality from 1 to 100, the larger the better and the larger the file
+    /// * `subsampling` - the sampling of the chrominance
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// let mut bytes = Vec::new();
+    /// image.write_jpeg(&mut bytes, 90, Subsampling::Chroma444)?;
+    /// ```
+    pub fn write_jpeg<W : Write>(&self, mut writer : W, quality : u8, subsampling : Subsampling) -> Result<(), PpmError>{
+        let (width, heigth) = (self.width, self.heigth);
+        if width == 0 || heigth == 0 || width > u16::MAX as usize || heigth > u16::MAX as usize {
+            return Err(PpmError::UnsupportedFormat(format!("JPEG image of {}x{} pixels", width, heigth)));
+        }
+        let luma_table = quantization_table(&LUMA_QUANTIZATION, quality);
+        let chroma_table = quantization_table(&CHROMA_QUANTIZATION, quality);
+
+        // the planes of Y, Cb and Cr
+        let mut planes : Vec<Vec<f32>> = (0 .. 3).map(|_| Vec::with_capacity(width * heigth)).collect();
+        for pixel in &self.pixels{
+            let red = images::scale_sample(pixel.red, self.maxValue, 255) as f32;
+            let green = images::scale_sample(pixel.green, self.maxValue, 255) as f32;
+            let blue = images::scale_sample(pixel.blue, self.maxValue, 255) as f32;
+            planes[0].push(0.299 * red + 0.587 * green + 0.114 * blue);
+            planes[1].push(-0.168_736 * red - 0.331_264 * green + 0.5 * blue + 128.0);
+            planes[2].push(0.5 * red - 0.418_688 * green - 0.081_312 * blue + 128.0);
+        }
+
+        writer.write_all(&[0xff, 0xd8])?;
+        // JFIF 1.01, no unit and an aspect ratio of 1:1, no thumbnail
+        write_segment(&mut writer, 0xe0, b"JFIF\0\x01\x01\0\0\x01\0\x01\0\0")?;
+        for (id, table) in [&luma_table, &chroma_table].iter().enumerate(){
+            let mut data = vec![id as u8];
+            data.extend(ZIGZAG.iter().map(|&index| table[index] as u8));
+            write_segment(&mut writer, 0xdb, &data)?;
+        }
+        let luma_sampling = match subsampling { Subsampling::Chroma444 => 0x11, Subsampling::Chroma420 => 0x22 };
+        let mut frame = vec![8];
+        frame.extend_from_slice(&(heigth as u16).to_be_bytes());
+        frame.extend_from_slice(&(width as u16).to_be_bytes());
+        frame.extend_from_slice(&[3, 1, luma_sampling, 0, 2, 0x11, 1, 3, 0x11, 1]);
+        write_segment(&mut writer, 0xc0, &frame)?;
+        let tables : [(u8, &[u8; 16], &[u8]); 4] = [(0x00, &DC_LUMA_BITS, &DC_LUMA_VALUES), (0x10, &AC_LUMA_BITS, &AC_LUMA_VALUES),
+            (0x01, &DC_CHROMA_BITS, &DC_CHROMA_VALUES), (0x11, &AC_CHROMA_BITS, &AC_CHROMA_VALUES)];
+        for &(class_id, bits, values) in tables.iter(){
+            let mut data = vec![class_id];
+            data.extend_from_slice(bits);
+            data.extend_from_slice(values);
+            write_segment(&mut writer, 0xc4, &data)?;
+        }
+        write_segment(&mut writer, 0xda, &[3, 1, 0x00, 2, 0x11, 3, 0x11, 0, 63, 0])?;
+
+        let huffman = [
+            (HuffmanTable::new(&DC_LUMA_BITS, &DC_LUMA_VALUES), HuffmanTable::new(&AC_LUMA_BITS, &AC_LUMA_VALUES)),
+            (HuffmanTable::new(&DC_CHROMA_BITS, &DC_CHROMA_VALUES), HuffmanTable::new(&AC_CHROMA_BITS, &AC_CHROMA_VALUES)),
+        ];
+        let cosines = dct_cosines();
+        let mut bits = BitWriter{out : Vec::new(), buffer : 0, count : 0};
+        let mut previous_dc = [0i32; 3];
+        let mcu_size = if subsampling == Subsampling::Chroma420 { 16 } else { 8 };
+        for mcu_y in (0 .. heigth).step_by(mcu_size){
+            for mcu_x in (0 .. width).step_by(mcu_size){
+                // the blocks of the MCU: the luminance blocks, then one block of Cb and of Cr
+                let mut blocks = Vec::with_capacity(6);
+                for block_y in (0 .. mcu_size).step_by(8){
+                    for block_x in (0 .. mcu_size).step_by(8){
+                        blocks.push((0, sample_block(&planes[0], width, heigth, mcu_x + block_x, mcu_y + block_y, 1)));
+                    }
+                }
+                let step = mcu_size / 8;
+                blocks.push((1, sample_block(&planes[1], width, heigth, mcu_x, mcu_y, step)));
+                blocks.push((2, sample_block(&planes[2], width, heigth, mcu_x, mcu_y, step)));
+
+                for (component, block) in blocks{
+                    let table = if component == 0 { &luma_table } else { &chroma_table };
+                    let (dc_table, ac_table) = &huffman[component.min(1)];
+                    let coefficients = forward_dct(&block, &cosines);
+                    let mut quantized = [0i32; 64];
+                    for (index, &zigzag) in ZIGZAG.iter().enumerate(){
+                        quantized[index] = (coefficients[zigzag] / table[zigzag] as f32).round() as i32;
+                    }
+
+                    let (size, value) = magnitude(quantized[0] - previous_dc[component]);
+                    previous_dc[component] = quantized[0];
+                    bits.code(dc_table, size);
+                    bits.bits(value, size);
+                    let mut zeros = 0;
+                    for &coefficient in &quantized[1 ..]{
+                        if coefficient == 0 {
+                            zeros += 1;
+                            continue;
+                        }
+                        // a run of 16 zeros is written alone
+                        while zeros >= 16 {
+                            bits.code(ac_table, 0xf0);
+                            zeros -= 16;
+                        }
+                        let (size, value) = magnitude(coefficient);
+                        bits.code(ac_table, (zeros << 4) | size);
+                        bits.bits(value, size);
+                        zeros = 0;
+                    }
+                    if zeros > 0 {
+                        bits.code(ac_table, 0x00);
+                    }
+                }
+            }
+        }
+        writer.write_all(&bits.finish())?;
+        writer.write_all(&[0xff, 0xd9])?;
+        Ok(())
+    }
+}
+
+/// the block of 8x8 samples at the column `x` and the row `y`, centered on 0
+///
+/// with a `step` of 2 each sample is the mean of 2x2 samples, for the subsampled chrominance;
+/// the samples after the edges of the image repeat the last row and column
+fn sample_block(plane : &[f32], width : usize, heigth : usize, x : usize, y : usize, step : usize) -> [f32; 64]{
+    let mut block = [0f32; 64];
+    for row in 0 .. 8{
+        for column in 0 .. 8{
+            let mut sum = 0.0;
+            for dy in 0 .. step{
+                for dx in 0 .. step{
+                    let sample_x = (x + column * step + dx).min(width - 1);
+                    let sample_y = (y + row * step + dy).min(heigth - 1);
+                    sum += plane[sample_y * width + sample_x];
+                }
+            }
+            block[row * 8 + column] = sum / (step * step) as f32 - 128.0;
+        }
+    }
+    block
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pixels::Pixels;
+    use std::fs;
+    use std::f64::consts::PI;
+
+    /// the index in the zigzag order of each coefficient of a block, in the order of the rows (Figure A.6),
+    /// written apart from `ZIGZAG` to check the encoder
+    const ZIGZAG_INDEX : [usize; 64] = [
+        0, 1, 5, 6, 14, 15, 27, 28,
+        2, 4, 7, 13, 16, 26, 29, 42,
+        3, 8, 12, 17, 25, 30, 41, 43,
+        9, 11, 18, 24, 31, 40, 44, 53,
+        10, 19, 23, 32, 39, 45, 52, 54,
+        20, 22, 33, 38, 46, 51, 55, 60,
+        21, 34, 37, 47, 50, 56, 59, 61,
+        35, 36, 48, 49, 57, 58, 62, 63];
+
+    /// the inverse DCT of a block, computed with the formula of the spec (A.3.3) and not with the cosines of the encoder
+    fn inverse_dct(coefficients : &[f32; 64]) -> [f32; 64]{
+        let c = |u : usize| if u == 0 { 1.0 / 2f64.sqrt() } else { 1.0 };
+        let mut samples = [0f32; 64];
+        for y in 0 .. 8{
+            for x in 0 .. 8{
+                let mut sum = 0.0;
+                for v in 0 .. 8{
+                    for u in 0 .. 8{
+                        sum += c(u) * c(v) * coefficients[v * 8 + u] as f64
+                            * ((2 * x + 1) as f64 * u as f64 * PI / 16.0).cos()
+                            * ((2 * y + 1) as f64 * v as f64 * PI / 16.0).cos();
+                    }
+                }
+                samples[y * 8 + x] = (sum / 4.0) as f32;
+            }
+        }
+        samples
+    }
+
+    /// a baseline decoder of the files of the encoder, to compare the images
+    fn decode(data : &[u8]) -> Image{
+        let mut position = 2;
+        assert_eq!(&[0xff, 0xd8], &data[.. 2]);
+        // the position in the block of each index of the zigzag order
+        let mut natural = [0usize; 64];
+        for (position, &index) in ZIGZAG_INDEX.iter().enumerate(){
+            natural[index] = position;
+        }
+        let mut quantization = [[0u16; 64]; 4];
+        let mut dc_tables = vec![Vec::new(); 4];
+        let mut ac_tables = vec![Vec::new(); 4];
+        let mut components = Vec::new();
+        let (mut width, mut heigth) = (0, 0);
+        loop {
+            assert_eq!(0xff, data[position]);
+            let marker = data[position + 1];
+            let len = u16::from_be_bytes([data[position + 2], data[position + 3]]) as usize;
+            let segment = &data[position + 4 .. position + 2 + len];
+            position += 2 + len;
+            match marker {
+                0xdb => {
+                    for (index, &position) in natural.iter().enumerate(){
+                        quantization[segment[0] as usize][position] = segment[1 + index] as u16;
+                    }
+                },
+                0xc0 => {
+                    heigth = u16::from_be_bytes([segment[1], segment[2]]) as usize;
+                    width = u16::from_be_bytes([segment[3], segment[4]]) as usize;
+                    for component in segment[6 ..].chunks(3){
+                        // the sampling factors and the quantization table
+                        components.push(((component[1] >> 4) as usize, (component[1] & 0x0f) as usize, component[2] as usize));
+                    }
+                },
+                0xc4 => {
+                    // the list of (length, code, symbol)
+                    let mut codes = Vec::new();
+                    let mut code = 0u16;
+                    let mut index = 17;
+                    for len in 1 .. 17{
+                        for _ in 0 .. segment[len]{
+                            codes.push((len as u8, code, segment[index]));
+                            code += 1;
+                            index += 1;
+                        }
+                        code <<= 1;
+                    }
+                    if segment[0] >> 4 == 0 {
+                        dc_tables[(segment[0] & 0x0f) as usize] = codes;
+                    } else {
+                        ac_tables[(segment[0] & 0x0f) as usize] = codes;
+                    }
+                },
+                0xda => break,
+                _ => {},
+            }
+        }
+        let scan : Vec<(usize, usize)> = data[position - 9 ..].chunks(2).take(3).map(|pair| ((pair[1] >> 4) as usize, (pair[1] & 0x0f) as usize)).collect();
+
+        // remove the stuffed zeros of the entropy-coded data
+        let mut bits = Vec::new();
+        while !(data[position] == 0xff && data[position + 1] != 0) {
+            let byte = data[position];
+            for bit in (0 .. 8).rev(){
+                bits.push((byte >> bit) & 1);
+            }
+            position += if byte == 0xff { 2 } else { 1 };
+        }
+        let mut bit = 0;
+        let mut read = |count : u8| -> i32 {
+            let mut value = 0;
+            for _ in 0 .. count{
+                value = (value << 1) | bits[bit] as i32;
+                bit += 1;
+            }
+            value
+        };
+        let decode_symbol = |read : &mut dyn FnMut(u8) -> i32, table : &Vec<(u8, u16, u8)>| -> u8 {
+            let mut code = 0u16;
+            for len in 1 .. 17{
+                code = (code << 1) | read(1) as u16;
+                if let Some(&(_, _, symbol)) = table.iter().find(|&&(l, c, _)| l == len && c == code) {
+                    return symbol;
+                }
+            }
+            panic!("bad Huffman code");
+        };
+        let extend = |value : i32, size : u8| if size > 0 && value < 1 << (size - 1) { value - (1 << size) + 1 } else { value };
+
+        let (max_h, max_v) = (components[0].0, components[0].1);
+        let (mcu_width, mcu_heigth) = (8 * max_h, 8 * max_v);
+        let mcus_x = width.div_ceil(mcu_width);
+        let mcus_y = heigth.div_ceil(mcu_heigth);
+        let mut planes = vec![vec![0f32; mcus_x * mcu_width * mcus_y * mcu_heigth]; 3];
+        let plane_width = mcus_x * mcu_width;
+        let mut previous_dc = [0i32; 3];
+        for mcu_y in 0 .. mcus_y{
+            for mcu_x in 0 .. mcus_x{
+                for (component, &(h, v, table)) in components.iter().enumerate(){
+                    for block_y in 0 .. v{
+                        for block_x in 0 .. h{
+                            let (dc_table, ac_table) = scan[component];
+                            let size = decode_symbol(&mut read, &dc_tables[dc_table]);
+                            let diff = extend(read(size), size);
+                            previous_dc[component] += diff;
+                            let mut coefficients = [0f32; 64];
+                            coefficients[0] = previous_dc[component] as f32 * quantization[table][0] as f32;
+                            let mut index = 1;
+                            while index < 64 {
+                                let symbol = decode_symbol(&mut read, &ac_tables[ac_table]);
+                                if symbol == 0 {
+                                    break;
+                                }
+                                index += (symbol >> 4) as usize;
+                                let size = symbol & 0x0f;
+                                let value = extend(read(size), size);
+                                coefficients[natural[index]] = value as f32 * quantization[table][natural[index]] as f32;
+                                index += 1;
+                            }
+                            let mut samples = inverse_dct(&coefficients);
+                            for sample in samples.iter_mut(){
+                                *sample += 128.0;
+                            }
+                            // the subsampled components cover the MCU with larger pixels
+                            let (scale_x, scale_y) = (max_h / h, max_v / v);
+                            for y in 0 .. mcu_heigth / v{
+                                for x in 0 .. mcu_width / h{
+                                    let plane_x = mcu_x * mcu_width + block_x * 8 * scale_x + x;
+                                    let plane_y = mcu_y * mcu_heigth + block_y * 8 * scale_y + y;
+                                    if x / scale_x < 8 && y / scale_y < 8 && plane_x < plane_width {
+                                        planes[component][plane_y * plane_width + plane_x] = samples[(y / scale_y) * 8 + x / scale_x];
+                                    }
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+        }
+
+        let mut pixels = Vec::with_capacity(width * heigth);
+        for y in 0 .. heigth{
+            for x in 0 .. width{
+                let index = y * plane_width + x;
+                let (luma, cb, cr) = (planes[0][index], planes[1][index] - 128.0, planes[2][index] - 128.0);
+                let clamp = |value : f32| value.round().clamp(0.0, 255.0) as u16;
+                pixels.push(Pixels::new(clamp(luma + 1.402 * cr), clamp(luma - 0.344_136 * cb - 0.714_136 * cr), clamp(luma + 1.772 * cb)));
+            }
+        }
+        Image::new(pixels, heigth, width, "P6".to_string(), 255)
+    }
+
+    /// the peak signal-to-noise ratio in dB of two images of 8 bits
+    fn psnr(original : &Image, decoded : &Image) -> f64{
+        let mut error = 0.0;
+        for (a, b) in original.pixels.iter().zip(decoded.pixels.iter()){
+            for &(x, y) in &[(a.red, b.red), (a.green, b.green), (a.blue, b.blue)]{
+                error += (x as f64 - y as f64).powi(2);
+            }
+        }
+        let mse = error / (original.pixels.len() * 3) as f64;
+        10.0 * (255.0 * 255.0 / mse).log10()
+    }
+
+    /// a smooth render with a sharp edge, of a size that is not a multiple of 16
+    fn render() -> Image{
+        let mut pixels = Vec::new();
+        for y in 0 .. 37{
+            for x in 0 .. 45{
+                let shade = if x > 30 && y > 20 { 200 } else { 0 };
+                pixels.push(Pixels::new((x * 5) as u16, (y * 6 + shade / 4) as u16, (255 - x * 3 - shade / 2) as u16));
+            }
+        }
+        Image::new(pixels, 37, 45, "P6".to_string(), 255)
+    }
+
+    #[test]
+    fn test_quantization_table(){
+        assert_eq!(LUMA_QUANTIZATION, quantization_table(&LUMA_QUANTIZATION, 50));
+        assert_eq!([1; 64], quantization_table(&LUMA_QUANTIZATION, 100));
+        assert_eq!(32, quantization_table(&LUMA_QUANTIZATION, 25)[0]);
+        assert_eq!(255, quantization_table(&CHROMA_QUANTIZATION, 1)[63]);
+    }
+
+    #[test]
+    fn test_zigzag(){
+        for (position, &index) in ZIGZAG_INDEX.iter().enumerate(){
+            assert_eq!(position, ZIGZAG[index]);
+        }
+    }
+
+    #[test]
+    fn test_dct(){
+        let mut block = [0f32; 64];
+        for (index, sample) in block.iter_mut().enumerate(){
+            *sample = ((index * 37) % 256) as f32 - 128.0;
+        }
+
+        let samples = inverse_dct(&forward_dct(&block, &dct_cosines()));
+
+        for (sample, decoded) in block.iter().zip(samples.iter()){
+            assert!((sample - decoded).abs() < 0.01, "{} decoded as {}", sample, decoded);
+        }
+    }
+
+    #[test]
+    fn test_magnitude(){
+        assert_eq!((0, 0), magnitude(0));
+        assert_eq!((1, 1), magnitude(1));
+        assert_eq!((1, 0), magnitude(-1));
+        assert_eq!((3, 0b010), magnitude(-5));
+        assert_eq!((11, 2047), magnitude(2047));
+    }
+
+    #[test]
+    fn test_jfif_structure() -> Result<(), PpmError>{
+        let mut data = Vec::new();
+        render().write_jpeg(&mut data, 75, Subsampling::Chroma420)?;
+        assert_eq!(&[0xff, 0xd8, 0xff, 0xe0, 0, 16], &data[.. 6]);
+        assert_eq!(b"JFIF\0", &data[6 .. 11]);
+        assert_eq!(&[0xff, 0xd9], &data[data.len() - 2 ..]);
+        // the entropy-coded data has no marker, each 0xff is followed by a 0
+        let start = data.windows(2).position(|pair| pair == [0xff, 0xda]).unwrap() + 14;
+        let scan = &data[start .. data.len() - 2];
+        assert!(scan.windows(2).all(|pair| pair[0] != 0xff || pair[1] == 0));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_psnr() -> Result<(), PpmError>{
+        let image = render();
+        let mut sizes = Vec::new();
+        for &(quality, subsampling, min_psnr) in [(95, Subsampling::Chroma444, 44.0), (75, Subsampling::Chroma444, 36.0),
+            (75, Subsampling::Chroma420, 28.0), (20, Subsampling::Chroma420, 25.0)].iter(){
+            let mut data = Vec::new();
+            image.write_jpeg(&mut data, quality, subsampling)?;
+            let decoded = decode(&data);
+            assert_eq!((image.heigth, image.width), (decoded.heigth, decoded.width));
+            let psnr = psnr(&image, &decoded);
+            assert!(psnr > min_psnr, "PSNR of {:.1} dB at the quality {} with {:?}", psnr, quality, subsampling);
+            sizes.push(data.len());
+        }
+        // the files get smaller with the quality and the subsampling
+        assert!(sizes.windows(2).all(|pair| pair[0] > pair[1]), "sizes {:?}", sizes);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_save_jpeg() -> Result<(), PpmError>{
+        let ppm = Image::new_with_file(Path::new("resource/ppmImage.ppm"))?;
+        ppm.save_jpeg(Path::new("test_save.jpg"), 90, Subsampling::Chroma444)?;
+        let decoded = decode(&fs::read(Path::new("test_save.jpg"))?);
+        assert!(psnr(&images::ppm_scaled_to(255)?, &decoded) > 25.0);
+        fs::remove_file(Path::new("test_save.jpg"))?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,418 @@
+use pixels::Pixels;
+use images::Image;
+use image_view::ImageView;
+use header::PnmReader;
+use error::{PpmError, Position};
+
+use libc;
+
+use std::fs::File;
+use std::io;
+use std::os::unix::io::AsRawFd;
+use std::path::Path;
+use std::ptr;
+use std::slice;
+
+/// a read-only view of a binary (P6) ppm file mapped in memory
+///
+/// only the header is parsed, the pixels are read from the file when they are used and are
+/// given as borrowed slices of the samples of the file: one byte by sample if the max value
+/// is less than 256, else two bytes in big-endian. The samples are not checked against the max value.
+///
+/// the file must not be modified while it is mapped, see the safety of `new_with_file`
+pub struct MappedImage{
+    map : *mut libc::c_void,
+    len : usize,
+    offset : usize,
+    heigth : usize,
+    width : usize,
+    max_value : usize,
+}
+
+// the mapping is read-only and owned by the view, it can be shared between threads
+unsafe impl Send for MappedImage {}
+unsafe impl Sync for MappedImage {}
+
+impl MappedImage {
+
+    ///map the file ppm in memory and read its header
+    ///
+    /// # Arguments
+    ///
+    /// * `filename` - the name of the file
+    ///
+    /// # Safety
+    ///
+    /// the slices given by the view are the pages of the file: the file must not be truncated
+    /// or modified, by this process or by another, until the view is dropped. A truncated file
+    /// raises SIGBUS when its missing pages are read, and a modified file changes the slices
+    /// already borrowed
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// let image = unsafe { MappedImage::new_with_file(Path::new("scan.ppm"))? };
+    /// let first_row = image.row(0);
+    /// ```
+    pub unsafe fn new_with_file(filename : &Path) -> Result<MappedImage, PpmError>{
+        let f = File::open(filename)?;
+        let len = f.metadata()?.len() as usize;
+        if len == 0 {
+            // an empty file can not be mapped
+            return Err(PpmError::BadMagic{ magic : String::new(), position : Position{line : 1, byte : 0} });
+        }
+        let map = unsafe {
+            libc::mmap(ptr::null_mut(), len, libc::PROT_READ, libc::MAP_PRIVATE, f.as_raw_fd(), 0)
+        };
+        if map == libc::MAP_FAILED {
+            return Err(PpmError::Io(io::Error::last_os_error()));
+        }
+        let mut image = MappedImage{map, len, offset : 0, heigth : 0, width : 0, max_value : 0};
+
+        let (header, position) = {
+            let mut reader = PnmReader::new(image.file());
+            let position = reader.position();
+            let header = reader.read_header()?;
+            if header.magic != "P6" {
+                return Err(PpmError::BadMagic{ magic : header.magic, position });
+            }
+            (header, reader.position())
+        };
+        image.offset = position.byte;
+        image.heigth = header.heigth;
+        image.width = header.width;
+        image.max_value = header.max_value;
+
+        let number = image.heigth * image.width * 3;
+        let available = (image.len - image.offset) / image.sample_size();
+        if available < number {
+            return Err(PpmError::Truncated{ expected : number, found : available, position });
+        }
+        Ok(image)
+    }
+
+    /// all the bytes of the file
+    fn file(&self) -> &[u8]{
+        unsafe { slice::from_raw_parts(self.map as *const u8, self.len) }
+    }
+
+    /// the number of bytes of a sample, 1 or 2
+    pub fn sample_size(&self) -> usize{
+        if self.max_value < 256 { 1 } else { 2 }
+    }
+
+    /// the samples of all the pixels, row after row from the top of the image
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// let bright = image.samples().iter().filter(|&&sample| sample > 200).count();
+    /// ```
+    pub fn samples(&self) -> &[u8]{
+        let len = self.heigth * self.width * 3 * self.sample_size();
+        &self.file()[self.offset .. self.offset + len]
+    }
+
+    /// the samples of the row `y`
+    ///
+    /// # Arguments
+    ///
+    /// * `y` - the row, from the top of the image
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// let first_row = image.row(0);
+    /// ```
+    pub fn row(&self, y : usize) -> &[u8]{
+        let row_len = self.width * 3 * self.sample_size();
+        &self.samples()[y * row_len .. (y + 1) * row_len]
+    }
+
+    /// an iterator on the samples of the rows, from the top of the image
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// for row in image.rows() {
+    ///     println!("{}", row.len());
+    /// }
+    /// ```
+    pub fn rows(&self) -> slice::Chunks<'_, u8>{
+        let row_len = self.width * 3 * self.sample_size();
+        self.samples().chunks(row_len.max(1))
+    }
+
+    /// a rectangle of the image, None if it is not inside the image or if its end overflows
+    ///
+    /// # Arguments
+    ///
+    /// * `x` - the left column of the rectangle
+    /// * `y` - the top row of the rectangle
+    /// * `width` - the width of the rectangle
+    /// * `heigth` - the heigth of the rectangle
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// let corner = image.region(0, 0, 16, 16).unwrap();
+    /// let first_row = corner.row(0);
+    /// ```
+    pub fn region(&self, x : usize, y : usize, width : usize, heigth : usize) -> Option<Region<'_>>{
+        if x.checked_add(width)? > self.width || y.checked_add(heigth)? > self.heigth {
+            return None;
+        }
+        Some(Region{image : self, x, y, width, heigth})
+    }
+
+    /// copy the pixels in an `Image`
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// let image = mapped.to_image();
+    /// ```
+    pub fn to_image(&self) -> Image{
+        let pixels = self.iter_pixels().collect();
+        Image::new(pixels, self.heigth, self.width, "P6".to_string(), self.max_value)
+    }
+}
+
+impl ImageView for MappedImage {
+
+    fn heigth(&self) -> usize{
+        self.heigth
+    }
+
+    fn width(&self) -> usize{
+        self.width
+    }
+
+    fn max_value(&self) -> usize{
+        self.max_value
+    }
+
+    fn file_type(&self) -> &str{
+        "P6"
+    }
+
+    fn pixel(&self, x : usize, y : usize) -> Pixels{
+        let sample_size = self.sample_size();
+        let start = x * 3 * sample_size;
+        pixel_from_samples(&self.row(y)[start .. start + 3 * sample_size], sample_size)
+    }
+}
+
+impl Drop for MappedImage {
+    fn drop(&mut self){
+        unsafe {
+            libc::munmap(self.map, self.len);
+        }
+    }
+}
+
+/// a rectangle of a `MappedImage`, each row is a borrowed slice of the file
+#[derive(Clone, Copy)]
+pub struct Region<'a>{
+    image : &'a MappedImage,
+    x : usize,
+    y : usize,
+    width : usize,
+    heigth : usize,
+}
+
+impl<'a> Region<'a> {
+
+    /// the samples of the row `y` of the rectangle
+    ///
+    /// # Arguments
+    ///
+    /// * `y` - the row, from the top of the rectangle
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// let first_row = region.row(0);
+    /// ```
+    pub fn row(&self, y : usize) -> &'a [u8]{
+        assert!(y < self.heigth, "the row {} is out of the region", y);
+        let pixel_len = 3 * self.image.sample_size();
+        &self.image.row(self.y + y)[self.x * pixel_len .. (self.x + self.width) * pixel_len]
+    }
+
+    /// an iterator on the samples of the rows of the rectangle
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// let len : usize = region.rows().map(|row| row.len()).sum();
+    /// ```
+    pub fn rows(&self) -> RegionRows<'a>{
+        RegionRows{region : *self, y : 0}
+    }
+}
+
+impl<'a> ImageView for Region<'a> {
+
+    fn heigth(&self) -> usize{
+        self.heigth
+    }
+
+    fn width(&self) -> usize{
+        self.width
+    }
+
+    fn max_value(&self) -> usize{
+        self.image.max_value
+    }
+
+    fn file_type(&self) -> &str{
+        "P6"
+    }
+
+    fn pixel(&self, x : usize, y : usize) -> Pixels{
+        assert!(x < self.width && y < self.heigth, "the pixel ({}, {}) is out of the region", x, y);
+        self.image.pixel(self.x + x, self.y + y)
+    }
+}
+
+/// the iterator on the rows of a `Region`
+pub struct RegionRows<'a>{
+    region : Region<'a>,
+    y : usize,
+}
+
+impl<'a> Iterator for RegionRows<'a> {
+    type Item = &'a [u8];
+
+    fn next(&mut self) -> Option<&'a [u8]>{
+        if self.y >= self.region.heigth {
+            return None;
+        }
+        let row = self.region.row(self.y);
+        self.y += 1;
+        Some(row)
+    }
+}
+
+/// read a pixel from its samples in the file
+fn pixel_from_samples(samples : &[u8], sample_size : usize) -> Pixels{
+    if sample_size == 1 {
+        return Pixels::new(samples[0] as u16, samples[1] as u16, samples[2] as u16);
+    }
+    let sample = |index : usize| ((samples[index * 2] as u16) << 8) | samples[index * 2 + 1] as u16;
+    Pixels::new(sample(0), sample(1), sample(2))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use images;
+    use gray_image::GrayImage;
+    use std::fs;
+    use std::panic;
+
+    fn gradient(heigth : usize, width : usize, max_value : usize) -> Image{
+        let mut pixels = Vec::new();
+        for y in 0 .. heigth {
+            for x in 0 .. width {
+                pixels.push(Pixels::new((x * max_value / width) as u16, (y * max_value / heigth) as u16, 7));
+            }
+        }
+        Image::new(pixels, heigth, width, "P6".to_string(), max_value)
+    }
+
+    #[test]
+    fn test_mapped_image() -> Result<(), PpmError>{
+        let image = gradient(4, 5, 255);
+        image.save(Path::new("test_mapped_image.ppm"))?;
+
+        let mapped = unsafe { MappedImage::new_with_file(Path::new("test_mapped_image.ppm")) }?;
+        assert_eq!((4, 5, 255), (mapped.heigth(), mapped.width(), mapped.max_value()));
+        assert_eq!(&images::write_binary_pixels(&image.pixels, 255)[..], mapped.samples());
+        assert_eq!(&images::write_binary_pixels(&image.pixels[10 .. 15], 255)[..], mapped.row(2));
+        assert_eq!(4, mapped.rows().count());
+        assert_eq!(image.pixel(3, 2), mapped.pixel(3, 2));
+        assert_eq!(image, mapped.to_image());
+
+        fs::remove_file(Path::new("test_mapped_image.ppm"))?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_mapped_image_16_bits() -> Result<(), PpmError>{
+        let image = gradient(3, 2, 65535);
+        image.save(Path::new("test_mapped_image_16.ppm"))?;
+
+        let mapped = unsafe { MappedImage::new_with_file(Path::new("test_mapped_image_16.ppm")) }?;
+        assert_eq!(2, mapped.sample_size());
+        assert_eq!(12, mapped.row(1).len());
+        assert_eq!(image.pixels, mapped.iter_pixels().collect::<Vec<Pixels>>());
+
+        fs::remove_file(Path::new("test_mapped_image_16.ppm"))?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_region() -> Result<(), PpmError>{
+        let image = gradient(4, 5, 255);
+        image.save(Path::new("test_mapped_region.ppm"))?;
+        let mapped = unsafe { MappedImage::new_with_file(Path::new("test_mapped_region.ppm")) }?;
+
+        assert!(mapped.region(3, 0, 3, 1).is_none());
+        assert!(mapped.region(1, 0, usize::MAX, 1).is_none());
+        assert!(mapped.region(0, usize::MAX, 1, 2).is_none());
+        assert!(mapped.region(usize::MAX, usize::MAX, usize::MAX, usize::MAX).is_none());
+        let region = mapped.region(1, 2, 3, 2).unwrap();
+        assert_eq!(&images::write_binary_pixels(&image.pixels[11 .. 14], 255)[..], region.row(0));
+        let rows = region.rows().collect::<Vec<&[u8]>>();
+        assert_eq!(&images::write_binary_pixels(&image.pixels[16 .. 19], 255)[..], rows[1]);
+        assert_eq!(2, rows.len());
+        assert_eq!(image.pixel(2, 3), region.pixel(1, 1));
+        // the pixels of the image around the region are not in the region
+        assert!(panic::catch_unwind(|| region.pixel(3, 0)).is_err());
+        assert!(panic::catch_unwind(|| region.pixel(0, 2)).is_err());
+
+        fs::remove_file(Path::new("test_mapped_region.ppm"))?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_read_only_operations() -> Result<(), PpmError>{
+        let image = gradient(3, 3, 255);
+        image.save(Path::new("test_mapped_operations.ppm"))?;
+        let mapped = unsafe { MappedImage::new_with_file(Path::new("test_mapped_operations.ppm")) }?;
+
+        assert_eq!(GrayImage::from_image(&image), GrayImage::from_image(&mapped));
+
+        fs::remove_file(Path::new("test_mapped_operations.ppm"))?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_mapped_image_errors() -> Result<(), PpmError>{
+        let image = Image::new(vec![Pixels::new(1, 2, 3)], 1, 1, "P3".to_string(), 255);
+        image.save(Path::new("test_mapped_errors.ppm"))?;
+        match unsafe { MappedImage::new_with_file(Path::new("test_mapped_errors.ppm")) } {
+            Err(PpmError::BadMagic{ magic, .. }) => assert_eq!("P3", magic),
+            _ => panic!("only P6 files can be mapped"),
+        }
+
+        fs::write(Path::new("test_mapped_errors.ppm"), b"P6\n2 1\n255\n\x01\x02\x03\x04")?;
+        match unsafe { MappedImage::new_with_file(Path::new("test_mapped_errors.ppm")) } {
+            Err(PpmError::Truncated{ expected, found, .. }) => assert_eq!((6, 4), (expected, found)),
+            _ => panic!("the second pixel is truncated"),
+        }
+
+        fs::write(Path::new("test_mapped_errors.ppm"), b"")?;
+        assert!(unsafe { MappedImage::new_with_file(Path::new("test_mapped_errors.ppm")) }.is_err());
+
+        fs::remove_file(Path::new("test_mapped_errors.ppm"))?;
+
+        Ok(())
+    }
+}
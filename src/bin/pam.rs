@@ -0,0 +1,424 @@
+use pixels::Pixels;
+use images::{self, Image};
+use header::{self, PnmReader};
+use error::{PpmError, Position};
+use gray_image::GrayImage;
+use bit_image::BitImage;
+use rgba_image::{RgbaPixels, RgbaImage, GrayAlphaImage};
+
+use std::fs::File;
+use std::io::BufReader;
+use std::io::prelude::*;
+use std::path::Path;
+
+/// an image of the PAM format (P7), the tuple type of the file choose the kind of image
+#[derive(Clone, Debug, PartialEq)]
+pub enum PamImage{
+    BlackAndWhite(BitImage),
+    Grayscale(GrayImage),
+    Rgb(Image),
+    GrayscaleAlpha(GrayAlphaImage),
+    RgbAlpha(RgbaImage),
+}
+
+impl PamImage {
+
+    ///load the image from the file pam
+    ///
+    /// the tuple types GRAYSCALE, RGB, GRAYSCALE_ALPHA, RGB_ALPHA and BLACKANDWHITE are supported,
+    /// without TUPLTYPE the tuple type is chosen by the depth
+    ///
+    /// # Arguments
+    ///
+    /// * `filename` - the name of the file
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// match PamImage::new_with_file(Path::new("image.pam"))? {
+    ///     PamImage::RgbAlpha(image) => println!("{} pixels", image.pixels.len()),
+    ///     _ => println!("no transparency"),
+    /// }
+    /// ```
+    pub fn new_with_file(filename: &Path) -> Result<PamImage, PpmError>{
+        let f = File::open(filename)?;
+        let mut reader = PnmReader::new(BufReader::new(f));
+
+        let position = reader.position();
+        let magic = reader.read_line()?.unwrap_or_default();
+        if magic.trim_end() != "P7" {
+            return Err(PpmError::BadMagic{ magic, position });
+        }
+
+        let mut width = None;
+        let mut heigth = None;
+        let mut depth = None;
+        let mut max_value = None;
+        let mut tuple_type = String::new();
+        let mut comments = Vec::new();
+        loop {
+            let position = reader.position();
+            let line = match reader.read_line()? {
+                Some(line) => line,
+                None => return Err(PpmError::bad_header("no ENDHDR in the PAM header", position)),
+            };
+            if let Some(comment) = line.trim_start().strip_prefix('#') {
+                comments.push(comment.trim_end_matches(['\r', '\n']).to_string());
+                continue;
+            }
+            let content = line.trim();
+            if content.is_empty() {
+                continue;
+            }
+            let mut content = content.splitn(2, char::is_whitespace);
+            let key = content.next().unwrap();
+            let value = content.next().unwrap_or("").trim();
+            match key {
+                "ENDHDR" => break,
+                "WIDTH" => width = Some(header_value(key, value, position)?),
+                "HEIGHT" => heigth = Some(header_value(key, value, position)?),
+                "DEPTH" => depth = Some(header_value(key, value, position)?),
+                "MAXVAL" => {
+                    let value = header_value(key, value, position)?;
+                    if value > 65535 {
+                        return Err(PpmError::Overflow{ value : value.to_string(), position });
+                    }
+                    max_value = Some(value);
+                },
+                "TUPLTYPE" => {
+                    if !tuple_type.is_empty() {
+                        tuple_type.push(' ');
+                    }
+                    tuple_type.push_str(value);
+                },
+                _ => return Err(PpmError::bad_header(format!("unknown PAM header key {}", key), position)),
+            }
+        }
+
+        let position = reader.position();
+        let (width, heigth, depth, max_value) = match (width, heigth, depth, max_value) {
+            (Some(width), Some(heigth), Some(depth), Some(max_value)) => (width, heigth, depth, max_value),
+            _ => return Err(PpmError::bad_header("missing WIDTH, HEIGHT, DEPTH or MAXVAL in the PAM header", position)),
+        };
+        if tuple_type.is_empty() {
+            tuple_type = match depth {
+                1 => "GRAYSCALE",
+                2 => "GRAYSCALE_ALPHA",
+                3 => "RGB",
+                4 => "RGB_ALPHA",
+                _ => "",
+            }.to_string();
+        }
+        let expected_depth = match &*tuple_type {
+            "BLACKANDWHITE" | "GRAYSCALE" => 1,
+            "GRAYSCALE_ALPHA" => 2,
+            "RGB" => 3,
+            "RGB_ALPHA" => 4,
+            _ => return Err(PpmError::bad_header(format!("unsupported PAM tuple type {}", tuple_type), position)),
+        };
+        if depth != expected_depth {
+            return Err(PpmError::bad_header(format!("DEPTH {} does not match the tuple type {}", depth, tuple_type), position));
+        }
+        if tuple_type == "BLACKANDWHITE" && max_value != 1 {
+            return Err(PpmError::bad_header("the MAXVAL of a BLACKANDWHITE image must be 1", position));
+        }
+        let number = match heigth.checked_mul(width).and_then(|size| size.checked_mul(depth)) {
+            Some(number) => number,
+            None => return Err(PpmError::Overflow{ value : format!("{}x{}x{}", width, heigth, depth), position }),
+        };
+
+        let samples = reader.read_binary_samples(number, max_value)?;
+
+        let mut image = match &*tuple_type {
+            "BLACKANDWHITE" => {
+                let mut image = BitImage::new(heigth, width, "P4".to_string());
+                for (index, &sample) in samples.iter().enumerate(){
+                    image.set(index % width, index / width, sample == 0);
+                }
+                PamImage::BlackAndWhite(image)
+            },
+            "GRAYSCALE" => PamImage::Grayscale(GrayImage::new(samples, heigth, width, "P5".to_string(), max_value)),
+            "GRAYSCALE_ALPHA" => {
+                let pixels = samples.iter().step_by(2).cloned().collect();
+                let alpha = samples.iter().skip(1).step_by(2).cloned().collect();
+                PamImage::GrayscaleAlpha(GrayAlphaImage::new(pixels, alpha, heigth, width, max_value))
+            },
+            "RGB" => {
+                let pixels = samples.chunks(3).map(|rgb| Pixels::new(rgb[0], rgb[1], rgb[2])).collect();
+                PamImage::Rgb(Image::new(pixels, heigth, width, "P6".to_string(), max_value))
+            },
+            _ => {
+                let pixels = samples.chunks(4).map(|rgba| RgbaPixels::new(rgba[0], rgba[1], rgba[2], rgba[3])).collect();
+                PamImage::RgbAlpha(RgbaImage::new(pixels, heigth, width, max_value))
+            },
+        };
+        *image.comments_mut() = comments;
+        Ok(image)
+    }
+
+    ///save the image in a file of pam
+    ///
+    /// the commentaires of the image are written after the magic number
+    ///
+    /// # Arguments
+    ///
+    /// * `filename` - the name of the file
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// let image = PamImage::RgbAlpha(RgbaImage::from_image(&image));
+    /// image.save(Path::new("image.pam"))?;
+    /// ```
+    pub fn save(&self, filename : &Path) -> Result<(), PpmError>{
+        let mut f = File::create(filename)?;
+        let (heigth, width, max_value) = self.size();
+        let mut samples = Vec::new();
+        match self {
+            PamImage::BlackAndWhite(image) => {
+                for y in 0 .. image.heigth{
+                    for x in 0 .. image.width{
+                        samples.push(if image.get(x, y) { 0 } else { 1 });
+                    }
+                }
+            },
+            PamImage::Grayscale(image) => samples.extend_from_slice(&image.pixels),
+            PamImage::GrayscaleAlpha(image) => {
+                for (&gray, &alpha) in image.pixels.iter().zip(image.alpha.iter()){
+                    samples.push(gray);
+                    samples.push(alpha);
+                }
+            },
+            PamImage::Rgb(image) => {
+                for pixel in &image.pixels{
+                    samples.extend_from_slice(&[pixel.red, pixel.green, pixel.blue]);
+                }
+            },
+            PamImage::RgbAlpha(image) => {
+                for pixel in &image.pixels{
+                    samples.extend_from_slice(&[pixel.red, pixel.green, pixel.blue, pixel.alpha]);
+                }
+            },
+        }
+
+        let mut buf = String::from("P7\n");
+        images::write_comments(&mut buf, self.comments(), "\n");
+        buf += &format!("WIDTH {}\nHEIGHT {}\n", width, heigth);
+        buf += &format!("DEPTH {}\nMAXVAL {}\n", self.depth(), max_value);
+        buf += &format!("TUPLTYPE {}\nENDHDR\n", self.tuple_type());
+        f.write_all(buf.as_bytes())?;
+        f.write_all(&images::write_binary_samples(&samples, max_value))?;
+        Ok(())
+    }
+
+    /// the commentaires of the header, kept in the image of the tuple type
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// let image = PamImage::new_with_file(Path::new("image.pam"))?;
+    /// println!("{:?}", image.comments());
+    /// ```
+    pub fn comments(&self) -> &[String]{
+        match self {
+            PamImage::BlackAndWhite(image) => &image.comments,
+            PamImage::Grayscale(image) => &image.comments,
+            PamImage::Rgb(image) => &image.comments,
+            PamImage::GrayscaleAlpha(image) => &image.comments,
+            PamImage::RgbAlpha(image) => &image.comments,
+        }
+    }
+
+    /// the commentaires of the header, to add or remove some before saving
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// image.comments_mut().push(" made by hand".to_string());
+    /// ```
+    pub fn comments_mut(&mut self) -> &mut Vec<String>{
+        match self {
+            PamImage::BlackAndWhite(image) => &mut image.comments,
+            PamImage::Grayscale(image) => &mut image.comments,
+            PamImage::Rgb(image) => &mut image.comments,
+            PamImage::GrayscaleAlpha(image) => &mut image.comments,
+            PamImage::RgbAlpha(image) => &mut image.comments,
+        }
+    }
+
+    /// the tuple type of the image in the PAM header
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// let image = PamImage::Rgb(image);
+    /// assert_eq!("RGB", image.tuple_type());
+    /// ```
+    pub fn tuple_type(&self) -> &'static str{
+        match self {
+            PamImage::BlackAndWhite(_) => "BLACKANDWHITE",
+            PamImage::Grayscale(_) => "GRAYSCALE",
+            PamImage::Rgb(_) => "RGB",
+            PamImage::GrayscaleAlpha(_) => "GRAYSCALE_ALPHA",
+            PamImage::RgbAlpha(_) => "RGB_ALPHA",
+        }
+    }
+
+    /// the number of samples of each pixel
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// let image = PamImage::Rgb(image);
+    /// assert_eq!(3, image.depth());
+    /// ```
+    pub fn depth(&self) -> usize{
+        match self {
+            PamImage::BlackAndWhite(_) | PamImage::Grayscale(_) => 1,
+            PamImage::GrayscaleAlpha(_) => 2,
+            PamImage::Rgb(_) => 3,
+            PamImage::RgbAlpha(_) => 4,
+        }
+    }
+
+    /// the heigth, the width and the max value of the image
+    fn size(&self) -> (usize, usize, usize){
+        match self {
+            PamImage::BlackAndWhite(image) => (image.heigth, image.width, 1),
+            PamImage::Grayscale(image) => (image.heigth, image.width, image.maxValue),
+            PamImage::Rgb(image) => (image.heigth, image.width, image.maxValue),
+            PamImage::GrayscaleAlpha(image) => (image.heigth, image.width, image.maxValue),
+            PamImage::RgbAlpha(image) => (image.heigth, image.width, image.maxValue),
+        }
+    }
+}
+
+/// parse the number of a key of the PAM header
+///
+/// # Arguments
+///
+/// `key` - the key of the line
+/// `value` - the text after the key
+/// `position` - the position of the line, for the errors
+///
+/// # Example
+///
+/// ```
+/// assert_eq!(12, header_value("WIDTH", "12", Position{line : 2, byte : 3})?);
+/// ```
+fn header_value(key : &str, value : &str, position : Position) -> Result<usize, PpmError>{
+    header::parse_number(value, position).map_err(|error| match error {
+        PpmError::BadHeader{ .. } => PpmError::bad_header(format!("bad value {:?} for {} in the PAM header", value, key), position),
+        error => error,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    fn round_trip(image : PamImage, filename : &str) -> Result<(), PpmError>{
+        image.save(Path::new(filename))?;
+        let image_load = PamImage::new_with_file(Path::new(filename))?;
+        assert_eq!(image, image_load);
+        assert_eq!(image.comments(), image_load.comments());
+
+        fs::remove_file(Path::new(filename))?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_header_value(){
+        let position = Position{line : 2, byte : 3};
+        assert_eq!(12, header_value("WIDTH", "12", position).unwrap());
+        match header_value("WIDTH", "twelve", position) {
+            Err(PpmError::BadHeader{ message, .. }) => assert_eq!("bad value \"twelve\" for WIDTH in the PAM header", message),
+            _ => panic!("twelve is not a number"),
+        }
+    }
+
+    #[test]
+    fn test_save_black_and_white() -> Result<(), PpmError>{
+        let mut image = BitImage::new(2, 3, "P4".to_string());
+        image.set(1, 0, true);
+        image.set(2, 1, true);
+        image.comments = vec![" camera: 12".to_string(), String::new()];
+        round_trip(PamImage::BlackAndWhite(image), "test_save_pam_bw.pam")
+    }
+
+    #[test]
+    fn test_save_grayscale() -> Result<(), PpmError>{
+        let image = GrayImage::new(vec![7, 910, 65535, 0], 2, 2, "P5".to_string(), 65535);
+        round_trip(PamImage::Grayscale(image), "test_save_pam_gray.pam")
+    }
+
+    #[test]
+    fn test_save_grayscale_alpha() -> Result<(), PpmError>{
+        let image = GrayAlphaImage::new(vec![7, 91, 43], vec![255, 0, 128], 1, 3, 255);
+        round_trip(PamImage::GrayscaleAlpha(image), "test_save_pam_gray_alpha.pam")
+    }
+
+    #[test]
+    fn test_save_rgb() -> Result<(), PpmError>{
+        let pixels = vec![Pixels::new(7, 91, 43), Pixels::new(14, 32, 56)];
+        let image = Image::new(pixels, 2, 1, "P6".to_string(), 91);
+        round_trip(PamImage::Rgb(image), "test_save_pam_rgb.pam")
+    }
+
+    #[test]
+    fn test_save_rgb_alpha() -> Result<(), PpmError>{
+        let pixels = vec![RgbaPixels::new(7, 91, 43, 0), RgbaPixels::new(14, 32, 56, 1000)];
+        let mut image = PamImage::RgbAlpha(RgbaImage::new(pixels, 1, 2, 1000));
+        image.comments_mut().push("".to_string());
+        image.comments_mut().push(" source: a1b2c3".to_string());
+        round_trip(image, "test_save_pam_rgba.pam")
+    }
+
+    #[test]
+    fn test_new_with_file_header() -> std::io::Result<()>{
+        let mut f_w = File::create(Path::new("test_pam_header.pam"))?;
+        f_w.write_all(b"P7\n# a comment\nWIDTH 2\nHEIGHT 1\n\nDEPTH 4\nMAXVAL 255\nTUPLTYPE RGB_ALPHA\nENDHDR\n")?;
+        f_w.write_all(&[1, 2, 3, 4, 5, 6, 7, 8])?;
+
+        let image_load = PamImage::new_with_file(Path::new("test_pam_header.pam"))?;
+        let pixels = vec![RgbaPixels::new(1, 2, 3, 4), RgbaPixels::new(5, 6, 7, 8)];
+        assert_eq!(PamImage::RgbAlpha(RgbaImage::new(pixels, 1, 2, 255)), image_load);
+        assert_eq!(vec![" a comment"], image_load.comments());
+
+        fs::remove_file(Path::new("test_pam_header.pam"))?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_new_with_file_no_tuple_type() -> std::io::Result<()>{
+        let mut f_w = File::create(Path::new("test_pam_no_tuple_type.pam"))?;
+        f_w.write_all(b"P7\nWIDTH 2\nHEIGHT 1\nDEPTH 1\nMAXVAL 255\nENDHDR\n")?;
+        f_w.write_all(&[1, 2])?;
+
+        let image_load = PamImage::new_with_file(Path::new("test_pam_no_tuple_type.pam"))?;
+        assert_eq!("GRAYSCALE", image_load.tuple_type());
+
+        fs::remove_file(Path::new("test_pam_no_tuple_type.pam"))?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_new_with_file_bad_depth() -> std::io::Result<()>{
+        let mut f_w = File::create(Path::new("test_pam_bad_depth.pam"))?;
+        f_w.write_all(b"P7\nWIDTH 1\nHEIGHT 1\nDEPTH 3\nMAXVAL 255\nTUPLTYPE RGB_ALPHA\nENDHDR\n")?;
+        f_w.write_all(&[1, 2, 3, 4])?;
+
+        match PamImage::new_with_file(Path::new("test_pam_bad_depth.pam")) {
+            Err(PpmError::BadHeader{ position, .. }) => assert_eq!(Position{line : 8, byte : 65}, position),
+            _ => panic!("the depth of RGB_ALPHA is 4"),
+        }
+
+        fs::remove_file(Path::new("test_pam_bad_depth.pam"))?;
+
+        Ok(())
+    }
+}
@@ -1,16 +1,36 @@
 
 #[derive(Clone, Copy, Debug)]
 pub struct Pixels{
-    pub red : u8,
-    pub green : u8,
-    pub blue : u8,
+    pub red : u16,
+    pub green : u16,
+    pub blue : u16,
 }
 
+/// the ways to transform a RGB pixel to a gray level
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum GrayMethod{
+    /// the luma of the standard definition video, 0.299 R + 0.587 G + 0.114 B
+    Rec601,
+    /// the luma of the high definition video and of sRGB, 0.2126 R + 0.7152 G + 0.0722 B
+    Rec709,
+    /// the mean of the three channels
+    Average,
+    /// the mean of the largest and of the smallest channel
+    Lightness,
+    /// the red channel alone
+    Red,
+    /// the green channel alone
+    Green,
+    /// the blue channel alone
+    Blue,
+}
 
 impl Pixels {
 
     /// create a new pixel
     /// 
+    /// the samples are 16 bits so that images with a max value up to 65535 are kept without loss
+    /// 
     /// # Arguments
     /// 
     /// * `red` - the number of red for pixel
@@ -22,7 +42,7 @@ impl Pixels {
     /// ```
     /// let pixel = Pixels::new(12, 11, 9);
     /// ```
-    pub fn new(red: u8, green: u8, blue: u8) -> Pixels {
+    pub fn new(red: u16, green: u16, blue: u16) -> Pixels {
         Pixels{red, green, blue}
     }
 
@@ -42,17 +62,74 @@ impl Pixels {
 
     }
 
-    /// transform the RGB pixel to gray pixel
+    /// transform the RGB pixel to gray pixel, with the Rec.601 luma in the three channels
     /// 
     /// # Example
     /// 
     /// ```
     /// let pixel = Pixels::new(34, 56, 102);
-    /// pixel.grayscale()
+    /// assert_eq!(Pixels::new(55, 55, 55), pixel.grayscale());
     /// ```
     pub fn grayscale(&self) -> Self{
-        let grep = ((0.299 as u8) * self.red) + ((0.587 as u8) * &self.green) + ((0.114 as u8)* &self.blue);
-        Pixels::new(grep, grep, grep)
+        self.grayscale_with(GrayMethod::Rec601)
+    }
+
+    /// transform the RGB pixel to gray pixel, with the gray level of the method in the three channels
+    /// 
+    /// # Arguments
+    /// 
+    /// * `method` - the weights of the channels
+    /// 
+    /// # Example
+    /// 
+    /// ```
+    /// let pixel = Pixels::new(34, 56, 102);
+    /// assert_eq!(Pixels::new(64, 64, 64), pixel.grayscale_with(GrayMethod::Average));
+    /// ```
+    pub fn grayscale_with(&self, method : GrayMethod) -> Self{
+        let gray = self.gray(method);
+        Pixels::new(gray, gray, gray)
+    }
+
+    /// the gray level of the pixel, rounded to the nearest sample
+    /// 
+    /// the sums are computed on 32 bits, so the samples up to 65535 do not overflow
+    /// 
+    /// # Arguments
+    /// 
+    /// * `method` - the weights of the channels
+    /// 
+    /// # Example
+    /// 
+    /// ```
+    /// let pixel = Pixels::new(34, 56, 102);
+    /// assert_eq!(55, pixel.gray(GrayMethod::Rec709));
+    /// assert_eq!(68, pixel.gray(GrayMethod::Lightness));
+    /// ```
+    pub fn gray(&self, method : GrayMethod) -> u16{
+        let (red, green, blue) = (self.red as u32, self.green as u32, self.blue as u32);
+        let gray = match method {
+            GrayMethod::Rec601 => (299 * red + 587 * green + 114 * blue + 500) / 1000,
+            GrayMethod::Rec709 => (2126 * red + 7152 * green + 722 * blue + 5000) / 10000,
+            GrayMethod::Average => (red + green + blue + 1) / 3,
+            GrayMethod::Lightness => (red.max(green).max(blue) + red.min(green).min(blue)).div_ceil(2),
+            GrayMethod::Red => red,
+            GrayMethod::Green => green,
+            GrayMethod::Blue => blue,
+        };
+        gray as u16
+    }
+
+    /// the luma of the pixel, weighted with the Rec.601 coefficients
+    /// 
+    /// # Example
+    /// 
+    /// ```
+    /// let pixel = Pixels::new(34, 56, 102);
+    /// assert_eq!(55, pixel.luma());
+    /// ```
+    pub fn luma(&self) -> u16{
+        self.gray(GrayMethod::Rec601)
     }
 }
 
@@ -72,6 +149,9 @@ mod tests {
     fn test_pixel_disply(){
         let pixel = Pixels::new(12, 11, 9);
         assert_eq!("12 11 9".to_string(), pixel.display());
+
+        let pixel = Pixels::new(65535, 256, 1000);
+        assert_eq!("65535 256 1000".to_string(), pixel.display());
     }
 
     #[test]
@@ -86,9 +166,48 @@ mod tests {
 
     #[test]
     fn test_pixel_grayscale(){
+        // 0.299 * 34 + 0.587 * 56 + 0.114 * 102 = 54.666
         let pixels_origin = Pixels::new(34, 56, 102);
-        let pixels_gray = Pixels::new(34 * (0.299 as u8), 56 * (0.587 as u8), 102 * (0.114 as u8));
-        assert_eq!(pixels_gray, pixels_origin.grayscale());
+        assert_eq!(Pixels::new(55, 55, 55), pixels_origin.grayscale());
+        assert_eq!(pixels_origin.grayscale(), pixels_origin.grayscale_with(GrayMethod::Rec601));
+        assert_eq!(Pixels::new(102, 102, 102), pixels_origin.grayscale_with(GrayMethod::Blue));
+
+        // the gray of a gray pixel is the same gray
+        assert_eq!(Pixels::new(128, 128, 128), Pixels::new(128, 128, 128).grayscale());
+    }
+
+    #[test]
+    fn test_pixel_gray(){
+        let pixel = Pixels::new(34, 56, 102);
+        // 0.2126 * 34 + 0.7152 * 56 + 0.0722 * 102 = 54.6188
+        assert_eq!(55, pixel.gray(GrayMethod::Rec709));
+        // 192 / 3 and (102 + 34) / 2
+        assert_eq!(64, pixel.gray(GrayMethod::Average));
+        assert_eq!(68, pixel.gray(GrayMethod::Lightness));
+        assert_eq!((34, 56, 102), (pixel.gray(GrayMethod::Red), pixel.gray(GrayMethod::Green), pixel.gray(GrayMethod::Blue)));
+
+        // a pure green is brighter in Rec.709, a pure blue darker
+        assert_eq!((150, 182), (Pixels::new(0, 255, 0).gray(GrayMethod::Rec601), Pixels::new(0, 255, 0).gray(GrayMethod::Rec709)));
+        assert_eq!((29, 18), (Pixels::new(0, 0, 255).gray(GrayMethod::Rec601), Pixels::new(0, 0, 255).gray(GrayMethod::Rec709)));
+        // the rounding of the halves
+        assert_eq!(1, Pixels::new(0, 1, 1).gray(GrayMethod::Average));
+        assert_eq!(1, Pixels::new(0, 0, 1).gray(GrayMethod::Lightness));
+
+        // the samples of 16 bits do not overflow
+        let white = Pixels::new(65535, 65535, 65535);
+        for &method in [GrayMethod::Rec601, GrayMethod::Rec709, GrayMethod::Average, GrayMethod::Lightness,
+            GrayMethod::Red, GrayMethod::Green, GrayMethod::Blue].iter(){
+            assert_eq!(65535, white.gray(method));
+            assert_eq!(0, Pixels::new(0, 0, 0).gray(method));
+        }
+        assert_eq!(43690, Pixels::new(65535, 65535, 0).gray(GrayMethod::Average));
+    }
+
+    #[test]
+    fn test_pixel_luma(){
+        assert_eq!(55, Pixels::new(34, 56, 102).luma());
+        assert_eq!(0, Pixels::new(0, 0, 0).luma());
+        assert_eq!(65535, Pixels::new(65535, 65535, 65535).luma());
     }
 
 
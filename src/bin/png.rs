@@ -0,0 +1,664 @@
+use pixels::Pixels;
+use images::{self, Image};
+use error::{PpmError, Position};
+use gray_image::GrayImage;
+use rgba_image::{RgbaPixels, RgbaImage, GrayAlphaImage};
+use deflate;
+
+use std::fs::File;
+use std::io::BufWriter;
+use std::io::prelude::*;
+use std::path::Path;
+
+/// the first bytes of all the PNG files
+pub(crate) const SIGNATURE : [u8; 8] = [0x89, b'P', b'N', b'G', b'\r', b'\n', 0x1a, b'\n'];
+/// the largest IDAT chunk written, the data is split in several chunks after this size
+const MAX_IDAT : usize = 1 << 20;
+/// the start column, the start row and the steps of the passes of the Adam7 interlace
+const ADAM7 : [(usize, usize, usize, usize); 7] = [(0, 0, 8, 8), (4, 0, 8, 8), (0, 4, 4, 8), (2, 0, 4, 4),
+    (0, 2, 2, 4), (1, 0, 2, 2), (0, 1, 1, 2)];
+
+/// an image of the PNG format, the color type of the file choose the kind of image
+///
+/// the samples are 8 bits if the max value is 255 or less and 16 bits else, the samples of
+/// an image with another max value than 255 or 65535 are scaled to the range of the PNG
+#[derive(Clone, Debug, PartialEq)]
+pub enum PngImage{
+    Gray(GrayImage),
+    GrayAlpha(GrayAlphaImage),
+    Rgb(Image),
+    Rgba(RgbaImage),
+}
+
+/// the filter applied to the rows of the image before the compression
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum PngFilter{
+    None,
+    /// the difference with the pixel on the left
+    Sub,
+    /// the difference with the pixel above
+    Up,
+    /// the difference with the mean of the pixels on the left and above
+    Average,
+    /// the difference with the nearest of the pixels on the left, above and above left
+    Paeth,
+    /// the filter with the smallest sum of the differences, chosen for each row
+    Adaptive,
+}
+
+impl PngImage {
+
+    ///load the image from the file png
+    ///
+    /// the gray, gray with alpha, RGB and RGBA color types of 8 and 16 bits are supported,
+    /// interlaced or not
+    ///
+    /// # Arguments
+    ///
+    /// * `filename` - the name of the file
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// match PngImage::new_with_file(Path::new("image.png"))? {
+    ///     PngImage::Rgba(image) => println!("{} pixels", image.pixels.len()),
+    ///     image => println!("{} bits", image.bit_depth()),
+    /// }
+    /// ```
+    pub fn new_with_file(filename: &Path) -> Result<PngImage, PpmError>{
+        PngImage::read_from(File::open(filename)?)
+    }
+
+    ///load the image from a reader of PNG data
+    ///
+    /// # Arguments
+    ///
+    /// * `reader` - the reader of the PNG data
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// let image = PngImage::read_from(&bytes[..])?;
+    /// ```
+    pub fn read_from<R : Read>(mut reader : R) -> Result<PngImage, PpmError>{
+        let mut data = Vec::new();
+        reader.read_to_end(&mut data)?;
+        decode(&data)
+    }
+
+    ///save the image in a file of png, the filter of each row is chosen to compress the best
+    ///
+    /// # Arguments
+    ///
+    /// * `filename` - the name of the file
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// let image = PngImage::Rgb(image);
+    /// image.save(Path::new("image.png"))?;
+    /// ```
+    pub fn save(&self, filename : &Path) -> Result<(), PpmError>{
+        self.save_with_filter(filename, PngFilter::Adaptive)
+    }
+
+    ///save the image in a file of png with the given filter
+    ///
+    /// # Arguments
+    ///
+    /// * `filename` - the name of the file
+    /// * `filter` - the filter of the rows
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// image.save_with_filter(Path::new("image.png"), PngFilter::Paeth)?;
+    /// ```
+    pub fn save_with_filter(&self, filename : &Path, filter : PngFilter) -> Result<(), PpmError>{
+        let mut writer = BufWriter::new(File::create(filename)?);
+        self.write_to(&mut writer, filter)?;
+        writer.flush()?;
+        Ok(())
+    }
+
+    ///write the image as PNG data to a writer
+    ///
+    /// # Arguments
+    ///
+    /// * `writer` - the writer of the PNG data
+    /// * `filter` - the filter of the rows
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// let mut bytes = Vec::new();
+    /// image.write_to(&mut bytes, PngFilter::Adaptive)?;
+    /// ```
+    pub fn write_to<W : Write>(&self, mut writer : W, filter : PngFilter) -> Result<(), PpmError>{
+        let (heigth, width, max_value) = self.size();
+        if width == 0 || heigth == 0 || width > i32::MAX as usize || heigth > i32::MAX as usize {
+            return Err(PpmError::UnsupportedFormat(format!("PNG image of {}x{} pixels", width, heigth)));
+        }
+        let depth = self.bit_depth();
+        let target = (1usize << depth) - 1;
+        let samples = self.samples();
+
+        let bytes_per_sample = depth as usize / 8;
+        let row_len = width * self.channels() * bytes_per_sample;
+        let mut raw = Vec::with_capacity(samples.len() * bytes_per_sample);
+        for &sample in &samples{
+            let sample = images::scale_sample(sample, max_value, target);
+            if depth == 16 {
+                raw.extend_from_slice(&sample.to_be_bytes());
+            } else {
+                raw.push(sample as u8);
+            }
+        }
+        let filtered = filter_rows(&raw, row_len, self.channels() * bytes_per_sample, filter);
+
+        let mut ihdr = Vec::with_capacity(13);
+        ihdr.extend_from_slice(&(width as u32).to_be_bytes());
+        ihdr.extend_from_slice(&(heigth as u32).to_be_bytes());
+        ihdr.extend_from_slice(&[depth, self.color_type(), 0, 0, 0]);
+
+        writer.write_all(&SIGNATURE)?;
+        write_chunk(&mut writer, b"IHDR", &ihdr)?;
+        for idat in deflate::zlib_compress(&filtered).chunks(MAX_IDAT){
+            write_chunk(&mut writer, b"IDAT", idat)?;
+        }
+        write_chunk(&mut writer, b"IEND", &[])?;
+        Ok(())
+    }
+
+    ///transform the image to a RGB image, the alpha channel is dropped
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// let image = PngImage::new_with_file(Path::new("image.png"))?.to_image();
+    /// ```
+    pub fn to_image(&self) -> Image{
+        match self {
+            PngImage::Gray(image) => image.to_image(),
+            PngImage::GrayAlpha(image) => image.to_gray().to_image(),
+            PngImage::Rgb(image) => image.clone(),
+            PngImage::Rgba(image) => image.to_image(),
+        }
+    }
+
+    /// the number of bits of the samples in the file, 8 or 16
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// let image = PngImage::Rgb(image);
+    /// assert_eq!(8, image.bit_depth());
+    /// ```
+    pub fn bit_depth(&self) -> u8{
+        if self.size().2 > 255 { 16 } else { 8 }
+    }
+
+    /// the color type of the IHDR chunk
+    fn color_type(&self) -> u8{
+        match self {
+            PngImage::Gray(_) => 0,
+            PngImage::Rgb(_) => 2,
+            PngImage::GrayAlpha(_) => 4,
+            PngImage::Rgba(_) => 6,
+        }
+    }
+
+    /// the number of samples of each pixel
+    fn channels(&self) -> usize{
+        match self {
+            PngImage::Gray(_) => 1,
+            PngImage::GrayAlpha(_) => 2,
+            PngImage::Rgb(_) => 3,
+            PngImage::Rgba(_) => 4,
+        }
+    }
+
+    /// the heigth, the width and the max value of the image
+    fn size(&self) -> (usize, usize, usize){
+        match self {
+            PngImage::Gray(image) => (image.heigth, image.width, image.maxValue),
+            PngImage::GrayAlpha(image) => (image.heigth, image.width, image.maxValue),
+            PngImage::Rgb(image) => (image.heigth, image.width, image.maxValue),
+            PngImage::Rgba(image) => (image.heigth, image.width, image.maxValue),
+        }
+    }
+
+    /// the samples of the pixels, the channels of a pixel are next to each other
+    fn samples(&self) -> Vec<u16>{
+        let mut samples = Vec::new();
+        match self {
+            PngImage::Gray(image) => samples.extend_from_slice(&image.pixels),
+            PngImage::GrayAlpha(image) => {
+                for (&gray, &alpha) in image.pixels.iter().zip(image.alpha.iter()){
+                    samples.push(gray);
+                    samples.push(alpha);
+                }
+            },
+            PngImage::Rgb(image) => {
+                for pixel in &image.pixels{
+                    samples.extend_from_slice(&[pixel.red, pixel.green, pixel.blue]);
+                }
+            },
+            PngImage::Rgba(image) => {
+                for pixel in &image.pixels{
+                    samples.extend_from_slice(&[pixel.red, pixel.green, pixel.blue, pixel.alpha]);
+                }
+            },
+        }
+        samples
+    }
+}
+
+/// the table of the CRC-32 of the chunks, for the polynomial 0xedb88320
+fn crc_table() -> [u32; 256]{
+    let mut table = [0u32; 256];
+    for (index, entry) in table.iter_mut().enumerate(){
+        let mut crc = index as u32;
+        for _ in 0 .. 8{
+            crc = if crc & 1 == 1 { 0xedb8_8320 ^ (crc >> 1) } else { crc >> 1 };
+        }
+        *entry = crc;
+    }
+    table
+}
+
+/// the CRC-32 of the type and the data of a chunk
+///
+/// # Example
+///
+/// ```
+/// assert_eq!(0xae426082, crc32(b"IEND", &[]));
+/// ```
+pub(crate) fn crc32(kind : &[u8], data : &[u8]) -> u32{
+    let table = crc_table();
+    let mut crc = 0xffff_ffffu32;
+    for &byte in kind.iter().chain(data){
+        crc = table[((crc ^ byte as u32) & 0xff) as usize] ^ (crc >> 8);
+    }
+    crc ^ 0xffff_ffff
+}
+
+/// write a chunk with its length and its CRC
+fn write_chunk<W : Write>(writer : &mut W, kind : &[u8; 4], data : &[u8]) -> Result<(), PpmError>{
+    writer.write_all(&(data.len() as u32).to_be_bytes())?;
+    writer.write_all(kind)?;
+    writer.write_all(data)?;
+    writer.write_all(&crc32(kind, data).to_be_bytes())?;
+    Ok(())
+}
+
+/// the Paeth predictor, the nearest of the left, above and above left bytes to their linear estimate
+fn paeth(left : u8, above : u8, above_left : u8) -> u8{
+    let estimate = left as i16 + above as i16 - above_left as i16;
+    let distance_left = (estimate - left as i16).abs();
+    let distance_above = (estimate - above as i16).abs();
+    let distance_above_left = (estimate - above_left as i16).abs();
+    if distance_left <= distance_above && distance_left <= distance_above_left {
+        left
+    } else if distance_above <= distance_above_left {
+        above
+    } else {
+        above_left
+    }
+}
+
+/// the prediction of a byte by the filter type 0 .. 4
+fn predict(filter_type : u8, row : &[u8], prior : &[u8], index : usize, bpp : usize) -> u8{
+    let left = if index >= bpp { row[index - bpp] } else { 0 };
+    let above = prior[index];
+    let above_left = if index >= bpp { prior[index - bpp] } else { 0 };
+    match filter_type {
+        1 => left,
+        2 => above,
+        3 => ((left as u16 + above as u16) / 2) as u8,
+        4 => paeth(left, above, above_left),
+        _ => 0,
+    }
+}
+
+/// filter the rows of the raw data, each row is preceded by its filter type
+fn filter_rows(raw : &[u8], row_len : usize, bpp : usize, filter : PngFilter) -> Vec<u8>{
+    let mut filtered = Vec::with_capacity(raw.len() + raw.len() / row_len.max(1));
+    let zeros = vec![0; row_len];
+    let mut prior : &[u8] = &zeros;
+    let mut candidate = vec![0; row_len];
+    for row in raw.chunks(row_len){
+        let filter_types : &[u8] = match filter {
+            PngFilter::None => &[0],
+            PngFilter::Sub => &[1],
+            PngFilter::Up => &[2],
+            PngFilter::Average => &[3],
+            PngFilter::Paeth => &[4],
+            PngFilter::Adaptive => &[0, 1, 2, 3, 4],
+        };
+        let mut best : Option<(usize, u8, Vec<u8>)> = None;
+        for &filter_type in filter_types{
+            for index in 0 .. row_len{
+                candidate[index] = row[index].wrapping_sub(predict(filter_type, row, prior, index, bpp));
+            }
+            // the bytes are taken as signed, the smallest sum of their magnitudes compress the best
+            let sum = candidate.iter().map(|&byte| (byte as i8).unsigned_abs() as usize).sum();
+            if best.as_ref().is_none_or(|best| sum < best.0) {
+                best = Some((sum, filter_type, candidate.clone()));
+            }
+        }
+        let (_, filter_type, bytes) = best.unwrap();
+        filtered.push(filter_type);
+        filtered.extend_from_slice(&bytes);
+        prior = row;
+    }
+    filtered
+}
+
+/// undo the filters of the rows, `data` hold the filter type and the bytes of each row
+fn unfilter_rows(data : &[u8], row_len : usize, rows : usize, bpp : usize) -> Result<Vec<u8>, PpmError>{
+    let mut raw = vec![0; row_len * rows];
+    for y in 0 .. rows{
+        let filter_type = data[y * (row_len + 1)];
+        if filter_type > 4 {
+            return Err(corrupt(format!("unknown filter type {}", filter_type)));
+        }
+        let line = &data[y * (row_len + 1) + 1 .. (y + 1) * (row_len + 1)];
+        let (before, after) = raw.split_at_mut(y * row_len);
+        let zeros = vec![0; if y == 0 { row_len } else { 0 }];
+        let prior = if y == 0 { &zeros[..] } else { &before[(y - 1) * row_len ..] };
+        let row = &mut after[.. row_len];
+        for index in 0 .. row_len{
+            let value = line[index].wrapping_add(predict(filter_type, row, prior, index, bpp));
+            row[index] = value;
+        }
+    }
+    Ok(raw)
+}
+
+/// an error of the PNG data
+fn corrupt<S : Into<String>>(message : S) -> PpmError{
+    PpmError::Corrupt{ format : "PNG", message : message.into() }
+}
+
+/// the big-endian number of 4 bytes at the start of the data
+fn read_u32(data : &[u8]) -> u32{
+    u32::from_be_bytes([data[0], data[1], data[2], data[3]])
+}
+
+/// decode the PNG data of a file
+fn decode(data : &[u8]) -> Result<PngImage, PpmError>{
+    if data.len() < 8 || data[.. 8] != SIGNATURE {
+        let magic = String::from_utf8_lossy(&data[.. data.len().min(8)]).into_owned();
+        return Err(PpmError::BadMagic{ magic, position : Position{line : 1, byte : 0} });
+    }
+
+    let mut header = None;
+    let mut compressed = Vec::new();
+    let mut position = 8;
+    loop {
+        if position + 12 > data.len() {
+            return Err(corrupt("the file end before the IEND chunk"));
+        }
+        let len = read_u32(&data[position ..]) as usize;
+        let kind = &data[position + 4 .. position + 8];
+        if len > data.len() - position - 12 {
+            return Err(corrupt(format!("the {} chunk is truncated", String::from_utf8_lossy(kind))));
+        }
+        let chunk = &data[position + 8 .. position + 8 + len];
+        if read_u32(&data[position + 8 + len ..]) != crc32(kind, chunk) {
+            return Err(corrupt(format!("bad CRC of the {} chunk", String::from_utf8_lossy(kind))));
+        }
+        position += 12 + len;
+
+        match kind {
+            b"IHDR" => {
+                if len != 13 || header.is_some() {
+                    return Err(corrupt("bad IHDR chunk"));
+                }
+                header = Some(chunk);
+            },
+            _ if header.is_none() => return Err(corrupt("the first chunk is not IHDR")),
+            b"IDAT" => compressed.extend_from_slice(chunk),
+            b"IEND" => break,
+            // the palette is a suggestion for the true color images, the ancillary chunks are ignored
+            b"PLTE" => {},
+            _ if kind[0] & 0x20 != 0 => {},
+            _ => return Err(corrupt(format!("unknown critical chunk {}", String::from_utf8_lossy(kind)))),
+        }
+    }
+
+    let header = header.unwrap();
+    let width = read_u32(header) as usize;
+    let heigth = read_u32(&header[4 ..]) as usize;
+    let (depth, color_type, interlace) = (header[8], header[9], header[12]);
+    if width == 0 || heigth == 0 {
+        return Err(corrupt("the image is empty"));
+    }
+    if header[10] != 0 || header[11] != 0 || interlace > 1 {
+        return Err(corrupt("unknown compression, filter or interlace method"));
+    }
+    let channels = match color_type {
+        0 => 1,
+        2 => 3,
+        4 => 2,
+        6 => 4,
+        _ => return Err(PpmError::UnsupportedFormat(format!("PNG color type {}", color_type))),
+    };
+    if depth != 8 && depth != 16 {
+        return Err(PpmError::UnsupportedFormat(format!("PNG bit depth {}", depth)));
+    }
+    let bpp = channels * depth as usize / 8;
+
+    // the passes of the image, the whole image if it is not interlaced
+    let passes : Vec<(usize, usize, usize, usize)> = if interlace == 1 { ADAM7.to_vec() } else { vec![(0, 0, 1, 1)] };
+    let mut expected = 0usize;
+    for &(x0, y0, dx, dy) in &passes{
+        let (pass_width, pass_heigth) = ((width + dx - 1 - x0) / dx, (heigth + dy - 1 - y0) / dy);
+        if pass_width > 0 && pass_heigth > 0 {
+            expected = pass_width.checked_mul(bpp).and_then(|len| len.checked_add(1))
+                .and_then(|len| len.checked_mul(pass_heigth)).and_then(|len| len.checked_add(expected))
+                .ok_or_else(|| corrupt("the image is too large"))?;
+        }
+    }
+    let filtered = deflate::zlib_decompress(&compressed, expected).map_err(corrupt)?;
+    if filtered.len() != expected {
+        return Err(corrupt(format!("{} bytes of image data, {} expected", filtered.len(), expected)));
+    }
+
+    let mut samples = vec![0u16; width * heigth * channels];
+    let mut offset = 0;
+    for &(x0, y0, dx, dy) in &passes{
+        let (pass_width, pass_heigth) = ((width + dx - 1 - x0) / dx, (heigth + dy - 1 - y0) / dy);
+        if pass_width == 0 || pass_heigth == 0 {
+            continue;
+        }
+        let row_len = pass_width * bpp;
+        let len = (row_len + 1) * pass_heigth;
+        let raw = unfilter_rows(&filtered[offset .. offset + len], row_len, pass_heigth, bpp)?;
+        offset += len;
+        for y in 0 .. pass_heigth{
+            for x in 0 .. pass_width{
+                let pixel = (y0 + y * dy) * width + x0 + x * dx;
+                for channel in 0 .. channels{
+                    let index = y * row_len + x * bpp + channel * depth as usize / 8;
+                    samples[pixel * channels + channel] = if depth == 16 {
+                        u16::from_be_bytes([raw[index], raw[index + 1]])
+                    } else {
+                        raw[index] as u16
+                    };
+                }
+            }
+        }
+    }
+
+    let max_value = (1usize << depth) - 1;
+    let image = match color_type {
+        0 => PngImage::Gray(GrayImage::new(samples, heigth, width, "P5".to_string(), max_value)),
+        2 => {
+            let pixels = samples.chunks(3).map(|pixel| Pixels::new(pixel[0], pixel[1], pixel[2])).collect();
+            PngImage::Rgb(Image::new(pixels, heigth, width, "P6".to_string(), max_value))
+        },
+        4 => {
+            let pixels = samples.iter().step_by(2).cloned().collect();
+            let alpha = samples.iter().skip(1).step_by(2).cloned().collect();
+            PngImage::GrayAlpha(GrayAlphaImage::new(pixels, alpha, heigth, width, max_value))
+        },
+        _ => {
+            let pixels = samples.chunks(4).map(|pixel| RgbaPixels::new(pixel[0], pixel[1], pixel[2], pixel[3])).collect();
+            PngImage::Rgba(RgbaImage::new(pixels, heigth, width, max_value))
+        },
+    };
+    Ok(image)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    fn rgb(max_value : usize) -> Image{
+        let mut pixels = Vec::new();
+        for y in 0 .. 9{
+            for x in 0 .. 13{
+                let value = |scale : usize| ((x * scale + y * 7) % (max_value + 1)) as u16;
+                pixels.push(Pixels::new(value(3), value(11), value(29)));
+            }
+        }
+        Image::new(pixels, 9, 13, "P6".to_string(), max_value)
+    }
+
+    #[test]
+    fn test_crc32(){
+        assert_eq!(0xae426082, crc32(b"IEND", &[]));
+        assert_eq!(0xcbf43926, crc32(b"1234", b"56789"));
+    }
+
+    #[test]
+    fn test_paeth(){
+        assert_eq!(10, paeth(10, 20, 20));
+        assert_eq!(20, paeth(10, 20, 10));
+        assert_eq!(20, paeth(10, 20, 5));
+        assert_eq!(11, paeth(10, 12, 11));
+    }
+
+    #[test]
+    fn test_round_trip() -> Result<(), PpmError>{
+        let gray = GrayImage::new((0 .. 117).map(|value| value * 500).collect(), 9, 13, "P5".to_string(), 65535);
+        let images = vec![
+            PngImage::Rgb(rgb(255)),
+            PngImage::Rgb(rgb(65535)),
+            PngImage::Gray(gray.clone()),
+            PngImage::Gray(GrayImage::from_image(&rgb(255))),
+            PngImage::GrayAlpha(GrayAlphaImage::new(gray.pixels.clone(), gray.pixels.iter().rev().cloned().collect(), 9, 13, 65535)),
+            PngImage::Rgba(RgbaImage::from_image(&rgb(255))),
+            PngImage::Rgba(RgbaImage::from_image(&rgb(65535))),
+        ];
+        let filters = [PngFilter::None, PngFilter::Sub, PngFilter::Up, PngFilter::Average, PngFilter::Paeth, PngFilter::Adaptive];
+        for image in &images{
+            for &filter in filters.iter(){
+                let mut bytes = Vec::new();
+                image.write_to(&mut bytes, filter)?;
+                assert_eq!(*image, PngImage::read_from(&bytes[..])?);
+            }
+        }
+
+        PngImage::Rgb(rgb(255)).save(Path::new("test_save.png"))?;
+        assert_eq!(PngImage::Rgb(rgb(255)), PngImage::new_with_file(Path::new("test_save.png"))?);
+        fs::remove_file(Path::new("test_save.png"))?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_scale_max_value() -> Result<(), PpmError>{
+        let image = PngImage::Gray(GrayImage::new(vec![0, 5, 10, 15], 2, 2, "P2".to_string(), 15));
+        assert_eq!(8, image.bit_depth());
+        let mut bytes = Vec::new();
+        image.write_to(&mut bytes, PngFilter::None)?;
+        match PngImage::read_from(&bytes[..])? {
+            PngImage::Gray(gray) => {
+                assert_eq!(vec![0, 85, 170, 255], gray.pixels);
+                assert_eq!(255, gray.maxValue);
+            },
+            _ => panic!("the image is gray"),
+        }
+
+        let image = PngImage::Gray(GrayImage::new(vec![0, 500, 1000], 1, 3, "P5".to_string(), 1000));
+        assert_eq!(16, image.bit_depth());
+        let mut bytes = Vec::new();
+        image.write_to(&mut bytes, PngFilter::None)?;
+        assert_eq!(vec![0, 32768, 65535], PngImage::read_from(&bytes[..])?.to_image().pixels.iter().map(|pixel| pixel.red).collect::<Vec<u16>>());
+
+        // a sample over the max value is not wrapped in the byte
+        let image = PngImage::Gray(GrayImage::new(vec![300, 10], 1, 2, "P5".to_string(), 255));
+        let mut bytes = Vec::new();
+        image.write_to(&mut bytes, PngFilter::None)?;
+        assert_eq!(vec![255, 10], PngImage::read_from(&bytes[..])?.to_image().pixels.iter().map(|pixel| pixel.red).collect::<Vec<u16>>());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_adaptive_filter(){
+        // a gradient is only zeros after the Sub filter
+        let raw : Vec<u8> = (0 .. 4).flat_map(|_| (0 .. 16u8).map(|x| x * 4)).collect();
+        let filtered = filter_rows(&raw, 16, 1, PngFilter::Adaptive);
+        assert_eq!(1, filtered[0]);
+        assert!(filtered[2 .. 17].iter().all(|&byte| byte == 4));
+        assert_eq!(raw, unfilter_rows(&filtered, 16, 4, 1).unwrap());
+    }
+
+    #[test]
+    fn test_read_fixtures() -> Result<(), PpmError>{
+        // written by another encoder, with zlib and the Adam7 interlace
+        match PngImage::new_with_file(Path::new("resource/png/interlaced_rgb.png"))? {
+            PngImage::Rgb(image) => {
+                assert_eq!((5, 7), (image.heigth, image.width));
+                for y in 0 .. 5{
+                    for x in 0 .. 7{
+                        let expected = Pixels::new(x as u16 * 30, y as u16 * 50, (x + y) as u16 * 10);
+                        assert_eq!(expected, image.pixels[y * 7 + x]);
+                    }
+                }
+            },
+            _ => panic!("the image is RGB"),
+        }
+
+        match PngImage::new_with_file(Path::new("resource/png/gray16.png"))? {
+            PngImage::Gray(image) => {
+                assert_eq!((4, 6, 65535), (image.heigth, image.width, image.maxValue));
+                assert_eq!((0 .. 24).map(|index| index * 2000).collect::<Vec<u16>>(), image.pixels);
+            },
+            _ => panic!("the image is gray"),
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_read_errors() -> Result<(), PpmError>{
+        match PngImage::read_from(&b"P6\n1 1\n255\n"[..]) {
+            Err(PpmError::BadMagic{ .. }) => {},
+            _ => panic!("the file is not a PNG"),
+        }
+
+        let mut bytes = Vec::new();
+        PngImage::Rgb(rgb(255)).write_to(&mut bytes, PngFilter::Adaptive)?;
+        // a bit of the IHDR is changed
+        bytes[20] ^= 1;
+        match PngImage::read_from(&bytes[..]) {
+            Err(error) => assert_eq!("corrupt PNG data: bad CRC of the IHDR chunk", error.to_string()),
+            _ => panic!("the CRC does not match"),
+        }
+        bytes[20] ^= 1;
+
+        let len = bytes.len();
+        match PngImage::read_from(&bytes[.. len - 20]) {
+            Err(PpmError::Corrupt{ .. }) => {},
+            _ => panic!("the file is truncated"),
+        }
+
+        Ok(())
+    }
+}
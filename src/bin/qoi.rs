@@ -0,0 +1,388 @@
+use pixels::Pixels;
+use images::{self, Image};
+use error::{PpmError, Position};
+use rgba_image::{RgbaPixels, RgbaImage};
+
+use std::fs::File;
+use std::io::BufWriter;
+use std::io::prelude::*;
+use std::path::Path;
+
+/// the magic number at the start of the QOI files
+pub(crate) const MAGIC : &[u8] = b"qoif";
+/// the size of the header, magic number included
+const HEADER_SIZE : usize = 14;
+/// the bytes at the end of the QOI files
+const END_MARKER : [u8; 8] = [0, 0, 0, 0, 0, 0, 0, 1];
+/// the index of a previously seen pixel
+const OP_INDEX : u8 = 0x00;
+/// a small difference with the previous pixel, 2 bits by channel
+const OP_DIFF : u8 = 0x40;
+/// a difference with the previous pixel, relative to the difference of the green
+const OP_LUMA : u8 = 0x80;
+/// a repetition of the previous pixel, 1 to 62 times
+const OP_RUN : u8 = 0xc0;
+/// a pixel with its red, green and blue
+const OP_RGB : u8 = 0xfe;
+/// a pixel with its red, green, blue and alpha
+const OP_RGBA : u8 = 0xff;
+/// the mask of the 2 bits of the tag of the operations
+const MASK_2 : u8 = 0xc0;
+/// the longest run of an OP_RUN
+const MAX_RUN : u8 = 62;
+
+/// an image of the QOI format, "Quite OK Image", a lossless format with 8 bits samples
+///
+/// the samples of an image with another max value than 255 are scaled to 0 .. 255
+#[derive(Clone, Debug, PartialEq)]
+pub enum QoiImage{
+    Rgb(Image),
+    Rgba(RgbaImage),
+}
+
+/// the position of a pixel in the table of the previously seen pixels
+fn hash(pixel : [u8; 4]) -> usize{
+    (pixel[0] as usize * 3 + pixel[1] as usize * 5 + pixel[2] as usize * 7 + pixel[3] as usize * 11) % 64
+}
+
+impl QoiImage {
+
+    ///load the image from the file qoi
+    ///
+    /// # Arguments
+    ///
+    /// * `filename` - the name of the file
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// let image = QoiImage::new_with_file(Path::new("image.qoi"))?.to_image();
+    /// ```
+    pub fn new_with_file(filename: &Path) -> Result<QoiImage, PpmError>{
+        QoiImage::read_from(File::open(filename)?)
+    }
+
+    ///load the image from a reader of QOI data
+    ///
+    /// # Arguments
+    ///
+    /// * `reader` - the reader of the QOI data
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// let image = QoiImage::read_from(&bytes[..])?;
+    /// ```
+    pub fn read_from<R : Read>(mut reader : R) -> Result<QoiImage, PpmError>{
+        let mut data = Vec::new();
+        reader.read_to_end(&mut data)?;
+        QoiImage::from_bytes(&data)
+    }
+
+    ///decode the image from the QOI data in memory
+    ///
+    /// the image is RGBA if the header has 4 channels, RGB else
+    ///
+    /// # Arguments
+    ///
+    /// * `data` - the QOI data
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// let image = QoiImage::from_bytes(&QoiImage::Rgb(image).to_bytes()?)?;
+    /// ```
+    pub fn from_bytes(data : &[u8]) -> Result<QoiImage, PpmError>{
+        if data.len() < MAGIC.len() || &data[.. MAGIC.len()] != MAGIC {
+            let magic = String::from_utf8_lossy(&data[.. data.len().min(MAGIC.len())]).into_owned();
+            return Err(PpmError::BadMagic{ magic, position : Position{line : 1, byte : 0} });
+        }
+        if data.len() < HEADER_SIZE {
+            return Err(corrupt("the header is truncated"));
+        }
+        let width = u32::from_be_bytes([data[4], data[5], data[6], data[7]]) as usize;
+        let heigth = u32::from_be_bytes([data[8], data[9], data[10], data[11]]) as usize;
+        let channels = data[12];
+        if channels != 3 && channels != 4 {
+            return Err(corrupt(format!("bad number of channels {}", channels)));
+        }
+        let size = width.checked_mul(heigth).ok_or_else(|| corrupt("the image is too large"))?;
+        // each pixel take at least one byte, except in a run of 62 pixels
+        if size / MAX_RUN as usize > data.len() {
+            return Err(corrupt("the data is too short for the size of the image"));
+        }
+
+        let mut pixels = Vec::with_capacity(size);
+        let mut seen = [[0u8; 4]; 64];
+        let mut pixel = [0, 0, 0, 255];
+        let mut position = HEADER_SIZE;
+        let truncated = |position : usize, found : usize| PpmError::Truncated{ expected : size, found, position : Position{line : 1, byte : position} };
+        while pixels.len() < size {
+            let op = *data.get(position).ok_or_else(|| truncated(position, pixels.len()))?;
+            position += 1;
+            let mut run = 1;
+            match op {
+                OP_RGB | OP_RGBA => {
+                    let len = if op == OP_RGB { 3 } else { 4 };
+                    let bytes = data.get(position .. position + len).ok_or_else(|| truncated(position, pixels.len()))?;
+                    pixel[.. len].copy_from_slice(bytes);
+                    position += len;
+                },
+                _ => match op & MASK_2 {
+                    OP_INDEX => pixel = seen[op as usize],
+                    OP_DIFF => {
+                        pixel[0] = pixel[0].wrapping_add((op >> 4) & 0x03).wrapping_sub(2);
+                        pixel[1] = pixel[1].wrapping_add((op >> 2) & 0x03).wrapping_sub(2);
+                        pixel[2] = pixel[2].wrapping_add(op & 0x03).wrapping_sub(2);
+                    },
+                    OP_LUMA => {
+                        let next = *data.get(position).ok_or_else(|| truncated(position, pixels.len()))?;
+                        position += 1;
+                        let green = (op & 0x3f).wrapping_sub(32);
+                        pixel[0] = pixel[0].wrapping_add(green).wrapping_add(next >> 4).wrapping_sub(8);
+                        pixel[1] = pixel[1].wrapping_add(green);
+                        pixel[2] = pixel[2].wrapping_add(green).wrapping_add(next & 0x0f).wrapping_sub(8);
+                    },
+                    _ => run = (op & 0x3f) as usize + 1,
+                },
+            }
+            seen[hash(pixel)] = pixel;
+            for _ in 0 .. run.min(size - pixels.len()){
+                pixels.push(pixel);
+            }
+        }
+
+        let image = if channels == 4 {
+            let pixels = pixels.iter().map(|pixel| RgbaPixels::new(pixel[0] as u16, pixel[1] as u16, pixel[2] as u16, pixel[3] as u16)).collect();
+            QoiImage::Rgba(RgbaImage::new(pixels, heigth, width, 255))
+        } else {
+            let pixels = pixels.iter().map(|pixel| Pixels::new(pixel[0] as u16, pixel[1] as u16, pixel[2] as u16)).collect();
+            QoiImage::Rgb(Image::new(pixels, heigth, width, "P6".to_string(), 255))
+        };
+        Ok(image)
+    }
+
+    ///save the image in a file of qoi
+    ///
+    /// # Arguments
+    ///
+    /// * `filename` - the name of the file
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// QoiImage::Rgb(image).save(Path::new("image.qoi"))?;
+    /// ```
+    pub fn save(&self, filename : &Path) -> Result<(), PpmError>{
+        let mut writer = BufWriter::new(File::create(filename)?);
+        self.write_to(&mut writer)?;
+        writer.flush()?;
+        Ok(())
+    }
+
+    ///write the image as QOI data to a writer
+    ///
+    /// # Arguments
+    ///
+    /// * `writer` - the writer of the QOI data
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// let mut bytes = Vec::new();
+    /// image.write_to(&mut bytes)?;
+    /// ```
+    pub fn write_to<W : Write>(&self, mut writer : W) -> Result<(), PpmError>{
+        writer.write_all(&self.to_bytes()?)?;
+        Ok(())
+    }
+
+    ///encode the image as QOI data in memory
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// let data = QoiImage::Rgb(image).to_bytes()?;
+    /// ```
+    pub fn to_bytes(&self) -> Result<Vec<u8>, PpmError>{
+        let (heigth, width, max_value, channels) = match self {
+            QoiImage::Rgb(image) => (image.heigth, image.width, image.maxValue, 3u8),
+            QoiImage::Rgba(image) => (image.heigth, image.width, image.maxValue, 4u8),
+        };
+        if width > u32::MAX as usize || heigth > u32::MAX as usize {
+            return Err(PpmError::UnsupportedFormat(format!("QOI image of {}x{} pixels", width, heigth)));
+        }
+        let scale = |sample : u16| images::scale_sample(sample, max_value, 255) as u8;
+        let pixels : Vec<[u8; 4]> = match self {
+            QoiImage::Rgb(image) => image.pixels.iter().map(|pixel| [scale(pixel.red), scale(pixel.green), scale(pixel.blue), 255]).collect(),
+            QoiImage::Rgba(image) => image.pixels.iter().map(|pixel| [scale(pixel.red), scale(pixel.green), scale(pixel.blue), scale(pixel.alpha)]).collect(),
+        };
+
+        let mut data = Vec::with_capacity(HEADER_SIZE + pixels.len() * 2 + END_MARKER.len());
+        data.extend_from_slice(MAGIC);
+        data.extend_from_slice(&(width as u32).to_be_bytes());
+        data.extend_from_slice(&(heigth as u32).to_be_bytes());
+        // the samples are sRGB with a linear alpha
+        data.extend_from_slice(&[channels, 0]);
+
+        let mut seen = [[0u8; 4]; 64];
+        let mut previous = [0, 0, 0, 255];
+        let mut run = 0u8;
+        for &pixel in &pixels{
+            if pixel == previous {
+                run += 1;
+                if run == MAX_RUN {
+                    data.push(OP_RUN | (run - 1));
+                    run = 0;
+                }
+                continue;
+            }
+            if run > 0 {
+                data.push(OP_RUN | (run - 1));
+                run = 0;
+            }
+
+            let index = hash(pixel);
+            if seen[index] == pixel {
+                data.push(OP_INDEX | index as u8);
+            } else if pixel[3] != previous[3] {
+                data.push(OP_RGBA);
+                data.extend_from_slice(&pixel);
+            } else {
+                let red = pixel[0].wrapping_sub(previous[0]) as i8;
+                let green = pixel[1].wrapping_sub(previous[1]) as i8;
+                let blue = pixel[2].wrapping_sub(previous[2]) as i8;
+                let red_green = red.wrapping_sub(green);
+                let blue_green = blue.wrapping_sub(green);
+                if (-2 .. 2).contains(&red) && (-2 .. 2).contains(&green) && (-2 .. 2).contains(&blue) {
+                    data.push(OP_DIFF | ((red + 2) as u8) << 4 | ((green + 2) as u8) << 2 | (blue + 2) as u8);
+                } else if (-32 .. 32).contains(&green) && (-8 .. 8).contains(&red_green) && (-8 .. 8).contains(&blue_green) {
+                    data.push(OP_LUMA | (green + 32) as u8);
+                    data.push(((red_green + 8) as u8) << 4 | (blue_green + 8) as u8);
+                } else {
+                    data.push(OP_RGB);
+                    data.extend_from_slice(&pixel[.. 3]);
+                }
+            }
+            seen[index] = pixel;
+            previous = pixel;
+        }
+        if run > 0 {
+            data.push(OP_RUN | (run - 1));
+        }
+        data.extend_from_slice(&END_MARKER);
+        Ok(data)
+    }
+
+    ///transform the image to a RGB image, the alpha channel is dropped
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// let image = QoiImage::new_with_file(Path::new("image.qoi"))?.to_image();
+    /// ```
+    pub fn to_image(&self) -> Image{
+        match self {
+            QoiImage::Rgb(image) => image.clone(),
+            QoiImage::Rgba(image) => image.to_image(),
+        }
+    }
+}
+
+/// an error of the QOI data
+fn corrupt<S : Into<String>>(message : S) -> PpmError{
+    PpmError::Corrupt{ format : "QOI", message : message.into() }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    /// a render with flat areas, gradients and noise
+    fn render() -> Image{
+        let mut pixels = Vec::new();
+        for y in 0 .. 40u16{
+            for x in 0 .. 50u16{
+                pixels.push(match (x / 10 + y / 10) % 3 {
+                    0 => Pixels::new(200, 30, 30),
+                    1 => Pixels::new(x * 5, y * 6, 128),
+                    _ => Pixels::new((x * 37 + y * 91) % 256, (x * y) % 256, (x ^ y) * 4),
+                });
+            }
+        }
+        Image::new(pixels, 40, 50, "P6".to_string(), 255)
+    }
+
+    #[test]
+    fn test_encode_ops() -> Result<(), PpmError>{
+        let pixels = vec![Pixels::new(0, 0, 0), Pixels::new(0, 0, 0), Pixels::new(1, 255, 0), Pixels::new(11, 5, 2),
+            Pixels::new(200, 100, 50), Pixels::new(1, 255, 0)];
+        let data = QoiImage::Rgb(Image::new(pixels, 1, 6, "P6".to_string(), 255)).to_bytes()?;
+        assert_eq!(b"qoif\0\0\0\x06\0\0\0\x01\x03\0", &data[.. HEADER_SIZE]);
+        let ops = &data[HEADER_SIZE .. data.len() - END_MARKER.len()];
+        // a run of 2, a diff, a luma, a RGB and an index
+        assert_eq!(&[OP_RUN | 1, OP_DIFF | 3 << 4 | 1 << 2 | 2, OP_LUMA | 38, 0xc4, OP_RGB, 200, 100, 50, OP_INDEX | hash([1, 255, 0, 255]) as u8], ops);
+        assert_eq!(&END_MARKER, &data[data.len() - END_MARKER.len() ..]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_round_trip() -> Result<(), PpmError>{
+        let image = render();
+        assert_eq!(QoiImage::Rgb(image.clone()), QoiImage::from_bytes(&QoiImage::Rgb(image.clone()).to_bytes()?)?);
+
+        let pixels = image.pixels.iter().enumerate().map(|(index, &pixel)| RgbaPixels::from_pixels(pixel, if index % 17 < 8 { 255 } else { (index % 256) as u16 })).collect();
+        let image_rgba = QoiImage::Rgba(RgbaImage::new(pixels, 40, 50, 255));
+        image_rgba.save(Path::new("test_save.qoi"))?;
+        assert_eq!(image_rgba, QoiImage::new_with_file(Path::new("test_save.qoi"))?);
+        fs::remove_file(Path::new("test_save.qoi"))?;
+
+        // a long run is split in runs of 62 pixels
+        let flat = Image::new(vec![Pixels::new(9, 9, 9); 200], 10, 20, "P6".to_string(), 255);
+        let data = QoiImage::Rgb(flat.clone()).to_bytes()?;
+        // a luma for the first pixel, then runs of 62, 62, 62 and 13
+        assert_eq!(HEADER_SIZE + 2 + 4 + END_MARKER.len(), data.len());
+        assert_eq!(QoiImage::Rgb(flat), QoiImage::from_bytes(&data)?);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_smaller_than_ppm() -> Result<(), PpmError>{
+        let image = render();
+        let qoi = QoiImage::Rgb(image.clone()).to_bytes()?;
+        assert!(qoi.len() < image.to_bytes()?.len());
+
+        // the samples of the PPM are scaled to 0 .. 255
+        let ppm = Image::new_with_file(Path::new("resource/ppmImage.ppm"))?;
+        let image_load = QoiImage::from_bytes(&QoiImage::Rgb(ppm.clone()).to_bytes()?)?.to_image();
+        assert_eq!(ppm.pixels.iter().map(|pixel| pixel.red * 17).collect::<Vec<u16>>(), image_load.pixels.iter().map(|pixel| pixel.red).collect::<Vec<u16>>());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_read_errors() -> Result<(), PpmError>{
+        match QoiImage::from_bytes(b"P6\n1 1\n255\n") {
+            Err(PpmError::BadMagic{ .. }) => {},
+            _ => panic!("the file is not a QOI"),
+        }
+
+        let data = QoiImage::Rgb(render()).to_bytes()?;
+        match QoiImage::from_bytes(&data[.. 100]) {
+            Err(PpmError::Truncated{ expected, .. }) => assert_eq!(2000, expected),
+            _ => panic!("the data is truncated"),
+        }
+
+        let mut data = data.clone();
+        data[12] = 5;
+        match QoiImage::from_bytes(&data) {
+            Err(PpmError::Corrupt{ .. }) => {},
+            _ => panic!("5 channels is not valid"),
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,334 @@
+use pixels::Pixels;
+use images::{self, Image};
+use image_view::ImageView;
+use gray_image::GrayImage;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct RgbaPixels{
+    pub red : u16,
+    pub green : u16,
+    pub blue : u16,
+    pub alpha : u16,
+}
+
+impl RgbaPixels {
+
+    /// create a new pixel with an alpha channel
+    ///
+    /// # Arguments
+    ///
+    /// * `red` - the number of red for pixel
+    /// * `green` - the number of green for pixel
+    /// * `blue` - the number of blue for pixel
+    /// * `alpha` - the opacity of the pixel, 0 is transparent
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// let pixel = RgbaPixels::new(12, 11, 9, 255);
+    /// ```
+    pub fn new(red: u16, green: u16, blue: u16, alpha: u16) -> RgbaPixels {
+        RgbaPixels{red, green, blue, alpha}
+    }
+
+    /// create a pixel from a RGB pixel and an alpha
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// let pixel = RgbaPixels::from_pixels(Pixels::new(12, 11, 9), 255);
+    /// assert_eq!(RgbaPixels::new(12, 11, 9, 255), pixel);
+    /// ```
+    pub fn from_pixels(pixel : Pixels, alpha : u16) -> RgbaPixels{
+        RgbaPixels::new(pixel.red, pixel.green, pixel.blue, alpha)
+    }
+
+    /// create an opaque pixel from a RGB pixel, the samples are scaled from a max value to another
+    ///
+    /// # Arguments
+    ///
+    /// * `pixel` - the RGB pixel
+    /// * `max_value` - the max value of the samples of the RGB pixel
+    /// * `target` - the max value of the samples of the new pixel, the alpha is this value
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// let pixel = RgbaPixels::from_pixels_scaled(Pixels::new(255, 0, 1), 255, 65535);
+    /// assert_eq!(RgbaPixels::new(65535, 0, 257, 65535), pixel);
+    /// ```
+    pub fn from_pixels_scaled(pixel : Pixels, max_value : usize, target : usize) -> RgbaPixels{
+        RgbaPixels::from_pixels(pixel, max_value as u16).rescale(max_value, target)
+    }
+
+    /// the pixel with its samples, alpha included, scaled from a max value to another
+    ///
+    /// # Arguments
+    ///
+    /// * `max_value` - the max value of the samples of the pixel
+    /// * `target` - the max value of the samples of the new pixel
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// let pixel = RgbaPixels::new(65535, 32768, 0, 65535).rescale(65535, 255);
+    /// assert_eq!(RgbaPixels::new(255, 128, 0, 255), pixel);
+    /// ```
+    pub fn rescale(&self, max_value : usize, target : usize) -> RgbaPixels{
+        let scale = |sample| images::scale_sample(sample, max_value, target);
+        RgbaPixels::new(scale(self.red), scale(self.green), scale(self.blue), scale(self.alpha))
+    }
+
+    /// the RGB part of the pixel, the alpha is dropped
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// let pixel = RgbaPixels::new(12, 11, 9, 0);
+    /// assert_eq!(Pixels::new(12, 11, 9), pixel.to_pixels());
+    /// ```
+    pub fn to_pixels(&self) -> Pixels{
+        Pixels::new(self.red, self.green, self.blue)
+    }
+
+    /// display the pixel
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// let pixel = RgbaPixels::new(12, 11, 9, 255);
+    /// assert_eq!("12 11 9 255".to_string(), pixel.display());
+    /// ```
+    pub fn display(self) -> String{
+        format!("{} {} {} {}",self.red,self.green,self.blue,self.alpha)
+    }
+}
+
+// the field `maxValue` mirror the field of `Image`
+#[allow(non_snake_case)]
+#[derive(Clone, Debug)]
+pub struct RgbaImage{
+    pub pixels : Vec<RgbaPixels>,
+    pub heigth : usize,
+    pub width : usize,
+    pub maxValue : usize,
+    /// the commentaires of the header, the text after the `#` of each line
+    pub comments : Vec<String>,
+}
+
+impl RgbaImage {
+
+    /// create a new image with an alpha channel
+    ///
+    /// # Arguments
+    ///
+    /// * `pixels` - a vec contain the pixel in the image
+    /// * `heigth` - heigth of the image
+    /// * `width` - width of the image
+    /// * `max_value` - the max value of the samples in the image, alpha included
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// let image = RgbaImage::new(vec![RgbaPixels::new(12, 11, 9, 255)], 1, 1, 255);
+    /// ```
+    pub fn new(pixels : Vec<RgbaPixels>, heigth : usize, width : usize, max_value : usize) -> RgbaImage{
+        RgbaImage{pixels, heigth, width, maxValue : max_value, comments : Vec::new()}
+    }
+
+    ///transform the RGB image to an opaque image with an alpha channel
+    ///
+    /// # Arguments
+    ///
+    /// * `image` - the RGB image
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// let image_rgba = RgbaImage::from_image(&image);
+    /// ```
+    pub fn from_image<I : ImageView>(image : &I) -> RgbaImage{
+        let alpha = image.max_value() as u16;
+        let pixels = image.iter_pixels().map(|pixel| RgbaPixels::from_pixels(pixel, alpha)).collect();
+
+        RgbaImage::new(pixels, image.heigth(), image.width(), image.max_value())
+    }
+
+    ///transform the RGB image to an opaque image with an alpha channel and another max value,
+    ///as `RgbaImage::from_image(image).rescale(max_value)`
+    ///
+    /// # Arguments
+    ///
+    /// * `image` - the RGB image
+    /// * `max_value` - the max value of the samples of the new image
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// let image_16 = RgbaImage::from_image_scaled(&image, 65535);
+    /// ```
+    pub fn from_image_scaled<I : ImageView>(image : &I, max_value : usize) -> RgbaImage{
+        let pixels = image.iter_pixels().map(|pixel| RgbaPixels::from_pixels_scaled(pixel, image.max_value(), max_value)).collect();
+
+        RgbaImage::new(pixels, image.heigth(), image.width(), max_value)
+    }
+
+    ///the image with its samples scaled to another max value, rounded to the nearest value
+    ///
+    /// # Arguments
+    ///
+    /// * `max_value` - the max value of the samples of the new image
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// let image_8 = image_16.rescale(255);
+    /// ```
+    pub fn rescale(&self, max_value : usize) -> RgbaImage{
+        let pixels = self.pixels.iter().map(|pixel| pixel.rescale(self.maxValue, max_value)).collect();
+
+        RgbaImage::new(pixels, self.heigth, self.width, max_value)
+    }
+
+    ///transform the image to a binary (P6) RGB image, the alpha channel is dropped
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// let image = image_rgba.to_image();
+    /// ```
+    pub fn to_image(&self) -> Image{
+        let pixels = self.pixels.iter().map(|pixel| pixel.to_pixels()).collect();
+
+        Image::new(pixels, self.heigth, self.width, "P6".to_string(), self.maxValue)
+    }
+}
+
+// the field `maxValue` mirror the field of `Image`
+#[allow(non_snake_case)]
+#[derive(Clone, Debug)]
+pub struct GrayAlphaImage{
+    pub pixels : Vec<u16>,
+    pub alpha : Vec<u16>,
+    pub heigth : usize,
+    pub width : usize,
+    pub maxValue : usize,
+    /// the commentaires of the header, the text after the `#` of each line
+    pub comments : Vec<String>,
+}
+
+impl GrayAlphaImage {
+
+    /// create a new gray image with an alpha channel
+    ///
+    /// # Arguments
+    ///
+    /// * `pixels` - a vec contain the gray level of each pixel in the image
+    /// * `alpha` - a vec contain the opacity of each pixel in the image, 0 is transparent
+    /// * `heigth` - heigth of the image
+    /// * `width` - width of the image
+    /// * `max_value` - the max value of the samples in the image, alpha included
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// let image = GrayAlphaImage::new(vec![7, 91], vec![255, 0], 1, 2, 255);
+    /// ```
+    pub fn new(pixels : Vec<u16>, alpha : Vec<u16>, heigth : usize, width : usize, max_value : usize) -> GrayAlphaImage{
+        GrayAlphaImage{pixels, alpha, heigth, width, maxValue : max_value, comments : Vec::new()}
+    }
+
+    ///transform the gray image to an opaque image with an alpha channel
+    ///
+    /// # Arguments
+    ///
+    /// * `image` - the gray image
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// let image_alpha = GrayAlphaImage::from_gray(&image_gray);
+    /// ```
+    pub fn from_gray(image : &GrayImage) -> GrayAlphaImage{
+        let alpha = vec![image.maxValue as u16; image.pixels.len()];
+
+        GrayAlphaImage::new(image.pixels.clone(), alpha, image.heigth, image.width, image.maxValue)
+    }
+
+    ///transform the image to a binary (P5) gray image, the alpha channel is dropped
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// let image_gray = image_alpha.to_gray();
+    /// ```
+    pub fn to_gray(&self) -> GrayImage{
+        GrayImage::new(self.pixels.clone(), self.heigth, self.width, "P5".to_string(), self.maxValue)
+    }
+}
+
+impl PartialEq for RgbaImage {
+    fn eq(&self, other: &Self) -> bool {
+        // the commentaires are metadata, they are not compared
+        self.pixels == other.pixels &&
+        self.maxValue == other.maxValue &&
+        self.heigth == other.heigth &&
+        self.width == other.width
+    }
+}
+
+impl PartialEq for GrayAlphaImage {
+    fn eq(&self, other: &Self) -> bool {
+        // the commentaires are metadata, they are not compared
+        self.pixels == other.pixels &&
+        self.alpha == other.alpha &&
+        self.maxValue == other.maxValue &&
+        self.heigth == other.heigth &&
+        self.width == other.width
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rgba_pixel_display(){
+        let pixel = RgbaPixels::new(12, 11, 9, 255);
+        assert_eq!("12 11 9 255".to_string(), pixel.display());
+    }
+
+    #[test]
+    fn test_rgba_from_image(){
+        let pixels = vec![Pixels::new(7, 91, 43), Pixels::new(14, 32, 56)];
+        let image = Image::new(pixels, 1, 2, "P6".to_string(), 91);
+
+        let image_rgba = RgbaImage::from_image(&image);
+
+        assert_eq!(vec![RgbaPixels::new(7, 91, 43, 91), RgbaPixels::new(14, 32, 56, 91)], image_rgba.pixels);
+        assert_eq!(image, image_rgba.to_image());
+    }
+
+    #[test]
+    fn test_rescale(){
+        let pixel = RgbaPixels::from_pixels_scaled(Pixels::new(15, 7, 0), 15, 65535);
+        assert_eq!(RgbaPixels::new(65535, 30583, 0, 65535), pixel);
+        assert_eq!(RgbaPixels::new(15, 7, 0, 15), pixel.rescale(65535, 15));
+
+        let image = Image::new(vec![Pixels::new(255, 128, 0), Pixels::new(1, 2, 3)], 1, 2, "P6".to_string(), 255);
+        let image_16 = RgbaImage::from_image_scaled(&image, 65535);
+        assert_eq!(65535, image_16.maxValue);
+        assert_eq!(vec![RgbaPixels::new(65535, 32896, 0, 65535), RgbaPixels::new(257, 514, 771, 65535)], image_16.pixels);
+        assert_eq!(image, image_16.rescale(255).to_image());
+    }
+
+    #[test]
+    fn test_gray_alpha_from_gray(){
+        let image_gray = GrayImage::new(vec![7, 91], 1, 2, "P5".to_string(), 255);
+
+        let image_alpha = GrayAlphaImage::from_gray(&image_gray);
+
+        assert_eq!(vec![255, 255], image_alpha.alpha);
+        assert_eq!(image_gray, image_alpha.to_gray());
+    }
+}
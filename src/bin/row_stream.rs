@@ -0,0 +1,355 @@
+use pixels::Pixels;
+use images;
+use header::PnmReader;
+use error::PpmError;
+
+use std::fs::File;
+use std::io::{BufReader, BufWriter};
+use std::io::prelude::*;
+use std::path::Path;
+
+/// a reader of a ppm image, ASCII (P3) or binary (P6), that read the header first and then the rows one by one
+///
+/// only one row is in memory at a time, for the images too large to be loaded in an `Image`
+pub struct RowReader<R>{
+    reader : PnmReader<BufReader<R>>,
+    heigth : usize,
+    width : usize,
+    file_type : String,
+    max_value : usize,
+    rows : usize,
+    done : bool,
+}
+
+impl<R : Read> RowReader<R> {
+
+    /// read the header of the image, the rows are read later
+    ///
+    /// # Arguments
+    ///
+    /// * `reader` - the reader of the image, a file, a pipe or a slice of bytes
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// let rows = RowReader::new("P3\n2 1\n255\n7 91 43\n10 32 13\n".as_bytes())?;
+    /// assert_eq!(2, rows.width());
+    /// ```
+    pub fn new(reader : R) -> Result<RowReader<R>, PpmError>{
+        let mut reader = PnmReader::new(BufReader::new(reader));
+        let position = reader.position();
+        let header = reader.read_header()?;
+        if header.magic != "P3" && header.magic != "P6" {
+            return Err(PpmError::BadMagic{ magic : header.magic, position });
+        }
+
+        Ok(RowReader{reader, heigth : header.heigth, width : header.width, file_type : header.magic,
+            max_value : header.max_value, rows : 0, done : false})
+    }
+
+    /// the heigth of the image, the number of rows
+    pub fn heigth(&self) -> usize{
+        self.heigth
+    }
+
+    /// the width of the image, the number of pixels in a row
+    pub fn width(&self) -> usize{
+        self.width
+    }
+
+    /// the type of the image, "P3" or "P6"
+    pub fn file_type(&self) -> &str{
+        &self.file_type
+    }
+
+    /// the max value of the pixels in the image
+    pub fn max_value(&self) -> usize{
+        self.max_value
+    }
+
+    /// read the next row of the image, None after the last row
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// let mut rows = RowReader::new("P3\n2 1\n255\n7 91 43\n10 32 13\n".as_bytes())?;
+    /// assert_eq!(Some(vec![Pixels::new(7, 91, 43), Pixels::new(10, 32, 13)]), rows.read_row()?);
+    /// assert_eq!(None, rows.read_row()?);
+    /// ```
+    pub fn read_row(&mut self) -> Result<Option<Vec<Pixels>>, PpmError>{
+        if self.rows == self.heigth {
+            return Ok(None);
+        }
+        let number = self.width * 3;
+        let samples = if self.file_type == "P6" {
+            self.reader.read_binary_samples(number, self.max_value)?
+        } else {
+            self.reader.read_plain_samples(number, self.max_value)?
+        };
+        self.rows += 1;
+        Ok(Some(images::samples_to_pixels(&samples)))
+    }
+}
+
+impl RowReader<File> {
+
+    /// read the header of the image in a file ppm
+    ///
+    /// # Arguments
+    ///
+    /// * `filename` - the name of the file
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// let rows = RowReader::new_with_file(Path::new("scan.ppm"))?;
+    /// ```
+    pub fn new_with_file(filename : &Path) -> Result<RowReader<File>, PpmError>{
+        let f = File::open(filename)?;
+        RowReader::new(f)
+    }
+}
+
+impl<R : Read> Iterator for RowReader<R> {
+    type Item = Result<Vec<Pixels>, PpmError>;
+
+    fn next(&mut self) -> Option<Result<Vec<Pixels>, PpmError>>{
+        if self.done {
+            return None;
+        }
+        match self.read_row() {
+            Ok(row) => row.map(Ok),
+            Err(error) => {
+                self.done = true;
+                Some(Err(error))
+            },
+        }
+    }
+}
+
+/// a writer of a ppm image, ASCII (P3) or binary (P6), that write the header first and then the rows one by one
+///
+/// `finish` must be called after the last row, it check that all the rows of the header are written
+pub struct RowWriter<W>{
+    writer : W,
+    heigth : usize,
+    width : usize,
+    file_type : String,
+    max_value : usize,
+    rows : usize,
+    index : usize,
+}
+
+impl<W : Write> RowWriter<W> {
+
+    /// write the header of the image, the rows are written later
+    ///
+    /// # Arguments
+    ///
+    /// * `writer` - the writer of the image, a file, a pipe or a vec of bytes
+    /// * `heigth` - heigth of the image
+    /// * `width` - width of the image
+    /// * `file_type` - "P6" for a binary image, else an ASCII image
+    /// * `max_value` - the max value of the pixels in the image
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// let mut rows = RowWriter::new(Vec::new(), 2, 1, "P6".to_string(), 255)?;
+    /// ```
+    pub fn new(mut writer : W, heigth : usize, width : usize, file_type : String, max_value : usize) -> Result<RowWriter<W>, PpmError>{
+        let file_type = if file_type == "P6" { file_type } else { "P3".to_string() };
+        let end_line = if file_type == "P6" { "\n" } else { "\r\n" };
+        let mut buf = String::new();
+        buf = buf + &file_type + end_line;
+        buf = buf + &format!("{} {}", width, heigth) + end_line;
+        buf = buf + &max_value.to_string() + end_line;
+        writer.write_all(buf.as_bytes())?;
+
+        Ok(RowWriter{writer, heigth, width, file_type, max_value, rows : 0, index : 0})
+    }
+
+    /// write the next row of the image
+    ///
+    /// # Arguments
+    ///
+    /// * `row` - the pixels of the row, as many as the width of the image
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// rows.write_row(&[Pixels::new(7, 91, 43)])?;
+    /// ```
+    pub fn write_row(&mut self, row : &[Pixels]) -> Result<(), PpmError>{
+        if self.rows == self.heigth {
+            return Err(PpmError::RowCount{ expected : self.heigth, found : self.rows + 1 });
+        }
+        if row.len() != self.width {
+            return Err(PpmError::RowLength{ expected : self.width, found : row.len() });
+        }
+        if self.file_type == "P6" {
+            self.writer.write_all(&images::write_binary_pixels(row, self.max_value))?;
+        } else {
+            // the pixels are grouped three by line as in `Image::save`, the lines go over the rows
+            let mut buf = String::new();
+            for pixel in row{
+                buf = buf + &pixel.display() + " ";
+                self.index += 1;
+                if self.index > 2{
+                    buf += "\r\n";
+                    self.index = 0;
+                }
+            }
+            self.writer.write_all(buf.as_bytes())?;
+        }
+        self.rows += 1;
+        Ok(())
+    }
+
+    /// end the image and give back the writer, an error if rows are missing
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// let mut rows = RowWriter::new(Vec::new(), 1, 1, "P6".to_string(), 255)?;
+    /// rows.write_row(&[Pixels::new(7, 91, 43)])?;
+    /// let data = rows.finish()?;
+    /// ```
+    pub fn finish(mut self) -> Result<W, PpmError>{
+        if self.rows != self.heigth {
+            return Err(PpmError::RowCount{ expected : self.heigth, found : self.rows });
+        }
+        self.writer.flush()?;
+        Ok(self.writer)
+    }
+}
+
+impl RowWriter<BufWriter<File>> {
+
+    /// write the header of the image in a new file ppm
+    ///
+    /// # Arguments
+    ///
+    /// * `filename` - the name of the file
+    /// * `heigth` - heigth of the image
+    /// * `width` - width of the image
+    /// * `file_type` - "P6" for a binary image, else an ASCII image
+    /// * `max_value` - the max value of the pixels in the image
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// let mut rows = RowWriter::new_with_file(Path::new("scan.ppm"), 40000, 30000, "P6".to_string(), 65535)?;
+    /// ```
+    pub fn new_with_file(filename : &Path, heigth : usize, width : usize, file_type : String, max_value : usize)
+        -> Result<RowWriter<BufWriter<File>>, PpmError>{
+        let f = File::create(filename)?;
+        RowWriter::new(BufWriter::new(f), heigth, width, file_type, max_value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use images::Image;
+    use std::fs;
+
+    fn gradient(heigth : usize, width : usize, file_type : &str, max_value : usize) -> Image{
+        let mut pixels = Vec::new();
+        for y in 0 .. heigth {
+            for x in 0 .. width {
+                pixels.push(Pixels::new((x * max_value / width) as u16, (y * max_value / heigth) as u16, 7));
+            }
+        }
+        Image::new(pixels, heigth, width, file_type.to_string(), max_value)
+    }
+
+    #[test]
+    fn test_write_rows() -> Result<(), PpmError>{
+        for &(file_type, max_value) in &[("P3", 255), ("P6", 255), ("P6", 65535)] {
+            let image = gradient(5, 7, file_type, max_value);
+            let mut rows = RowWriter::new(Vec::new(), 5, 7, file_type.to_string(), max_value)?;
+            for row in image.pixels.chunks(7) {
+                rows.write_row(row)?;
+            }
+            assert_eq!(image.to_bytes()?, rows.finish()?);
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_read_rows() -> Result<(), PpmError>{
+        for &(file_type, max_value) in &[("P3", 255), ("P6", 255), ("P6", 65535)] {
+            let image = gradient(5, 7, file_type, max_value);
+            let data = image.to_bytes()?;
+
+            let rows = RowReader::new(&data[..])?;
+            assert_eq!((5, 7, file_type, max_value), (rows.heigth(), rows.width(), rows.file_type(), rows.max_value()));
+            let rows = rows.collect::<Result<Vec<Vec<Pixels>>, PpmError>>()?;
+            assert_eq!(5, rows.len());
+            assert_eq!(image.pixels, rows.concat());
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_read_rows_errors(){
+        match RowReader::new("P2\n1 1\n255\n7\n".as_bytes()) {
+            Err(PpmError::BadMagic{ magic, .. }) => assert_eq!("P2", magic),
+            _ => panic!("P2 is not a RGB image"),
+        }
+
+        let mut rows = RowReader::new("P3\n1 2\n255\n7 91 43\n10 32\n".as_bytes()).unwrap();
+        assert!(rows.next().unwrap().is_ok());
+        match rows.next() {
+            Some(Err(PpmError::Truncated{ expected, found, .. })) => assert_eq!((3, 2), (expected, found)),
+            _ => panic!("the second row is truncated"),
+        }
+        assert!(rows.next().is_none());
+    }
+
+    #[test]
+    fn test_write_rows_errors() -> Result<(), PpmError>{
+        let mut rows = RowWriter::new(Vec::new(), 2, 1, "P6".to_string(), 255)?;
+        match rows.write_row(&[Pixels::new(1, 2, 3), Pixels::new(4, 5, 6)]) {
+            Err(PpmError::RowLength{ expected, found }) => assert_eq!((1, 2), (expected, found)),
+            _ => panic!("the row is too long"),
+        }
+        rows.write_row(&[Pixels::new(1, 2, 3)])?;
+        match rows.finish() {
+            Err(PpmError::RowCount{ expected, found }) => assert_eq!((2, 1), (expected, found)),
+            _ => panic!("a row is missing"),
+        }
+
+        let mut rows = RowWriter::new(Vec::new(), 1, 1, "P3".to_string(), 255)?;
+        rows.write_row(&[Pixels::new(1, 2, 3)])?;
+        match rows.write_row(&[Pixels::new(4, 5, 6)]) {
+            Err(PpmError::RowCount{ expected, found }) => assert_eq!((1, 2), (expected, found)),
+            _ => panic!("one row too many"),
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_rows_file() -> Result<(), PpmError>{
+        let image = gradient(4, 3, "P6", 255);
+        let mut rows = RowWriter::new_with_file(Path::new("test_rows_file.ppm"), 4, 3, "P6".to_string(), 255)?;
+        for row in image.pixels.chunks(3) {
+            rows.write_row(row)?;
+        }
+        rows.finish()?;
+
+        let mut pixels = Vec::new();
+        for row in RowReader::new_with_file(Path::new("test_rows_file.ppm"))? {
+            pixels.extend(row?);
+        }
+        assert_eq!(image.pixels, pixels);
+
+        fs::remove_file(Path::new("test_rows_file.ppm"))?;
+
+        Ok(())
+    }
+}
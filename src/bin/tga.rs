@@ -0,0 +1,429 @@
+use pixels::Pixels;
+use images::{self, Image};
+use error::{PpmError, Position};
+use gray_image::GrayImage;
+use rgba_image::{RgbaPixels, RgbaImage};
+use bmp::RowOrder;
+
+use std::fs::File;
+use std::io::BufWriter;
+use std::io::prelude::*;
+use std::path::Path;
+
+/// the size of the header, before the id and the color map
+const HEADER_SIZE : usize = 18;
+/// the signature at the end of the footer of the TGA 2.0 files
+const FOOTER_SIGNATURE : &[u8] = b"TRUEVISION-XFILE.\0";
+/// the largest number of pixels of a packet of the run-length encoding
+const MAX_PACKET : usize = 128;
+
+/// the compression of the pixels of a TGA file
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum TgaCompression{
+    Uncompressed,
+    /// the run-length encoding, the repeated pixels are written once with their count
+    Rle,
+}
+
+/// an image of the TGA format, 8 bits for the gray images, 24 bits for the RGB images and
+/// 32 bits for the images with alpha
+///
+/// the samples of an image with another max value than 255 are scaled to 0 .. 255
+#[derive(Clone, Debug, PartialEq)]
+pub enum TgaImage{
+    Gray(GrayImage),
+    Rgb(Image),
+    Rgba(RgbaImage),
+}
+
+impl TgaImage {
+
+    ///load the image from the file tga
+    ///
+    /// the true color and gray images are supported, uncompressed or with the run-length
+    /// encoding, in any order of the rows and of the columns
+    ///
+    /// # Arguments
+    ///
+    /// * `filename` - the name of the file
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// let image = TgaImage::new_with_file(Path::new("image.tga"))?.to_image();
+    /// ```
+    pub fn new_with_file(filename: &Path) -> Result<TgaImage, PpmError>{
+        TgaImage::read_from(File::open(filename)?)
+    }
+
+    ///load the image from a reader of TGA data
+    ///
+    /// # Arguments
+    ///
+    /// * `reader` - the reader of the TGA data
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// let image = TgaImage::read_from(&bytes[..])?;
+    /// ```
+    pub fn read_from<R : Read>(mut reader : R) -> Result<TgaImage, PpmError>{
+        let mut data = Vec::new();
+        reader.read_to_end(&mut data)?;
+        decode(&data)
+    }
+
+    ///save the image in a file of tga, uncompressed and bottom-up
+    ///
+    /// # Arguments
+    ///
+    /// * `filename` - the name of the file
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// TgaImage::Rgb(image).save(Path::new("image.tga"))?;
+    /// ```
+    pub fn save(&self, filename : &Path) -> Result<(), PpmError>{
+        self.save_with_options(filename, RowOrder::BottomUp, TgaCompression::Uncompressed)
+    }
+
+    ///save the image in a file of tga with the given order of the rows and compression
+    ///
+    /// # Arguments
+    ///
+    /// * `filename` - the name of the file
+    /// * `order` - the order of the rows in the file
+    /// * `compression` - the compression of the pixels
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// image.save_with_options(Path::new("image.tga"), RowOrder::TopDown, TgaCompression::Rle)?;
+    /// ```
+    pub fn save_with_options(&self, filename : &Path, order : RowOrder, compression : TgaCompression) -> Result<(), PpmError>{
+        let mut writer = BufWriter::new(File::create(filename)?);
+        self.write_to(&mut writer, order, compression)?;
+        writer.flush()?;
+        Ok(())
+    }
+
+    ///write the image as TGA data to a writer
+    ///
+    /// # Arguments
+    ///
+    /// * `writer` - the writer of the TGA data
+    /// * `order` - the order of the rows in the file
+    /// * `compression` - the compression of the pixels
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// let mut bytes = Vec::new();
+    /// image.write_to(&mut bytes, RowOrder::BottomUp, TgaCompression::Rle)?;
+    /// ```
+    pub fn write_to<W : Write>(&self, mut writer : W, order : RowOrder, compression : TgaCompression) -> Result<(), PpmError>{
+        let (heigth, width, max_value) = self.size();
+        if width > u16::MAX as usize || heigth > u16::MAX as usize {
+            return Err(PpmError::UnsupportedFormat(format!("TGA image of {}x{} pixels", width, heigth)));
+        }
+        let (image_type, bits, alpha_bits) = match self {
+            TgaImage::Gray(_) => (3, 8, 0),
+            TgaImage::Rgb(_) => (2, 24, 0),
+            TgaImage::Rgba(_) => (2, 32, 8),
+        };
+        let image_type = if compression == TgaCompression::Rle { image_type + 8 } else { image_type };
+        // the bit 5 of the descriptor is set for the images with the first row at the top
+        let descriptor = alpha_bits | if order == RowOrder::TopDown { 0x20 } else { 0 };
+
+        let mut header = vec![0, 0, image_type, 0, 0, 0, 0, 0, 0, 0, 0, 0];
+        header.extend_from_slice(&(width as u16).to_le_bytes());
+        header.extend_from_slice(&(heigth as u16).to_le_bytes());
+        header.extend_from_slice(&[bits, descriptor]);
+        writer.write_all(&header)?;
+
+        let bytes_per_pixel = bits as usize / 8;
+        let mut row = vec![0u8; width * bytes_per_pixel];
+        for y in 0 .. heigth{
+            let y = if order == RowOrder::TopDown { y } else { heigth - 1 - y };
+            for x in 0 .. width{
+                let index = y * width + x;
+                let samples = match self {
+                    TgaImage::Gray(image) => [image.pixels[index], 0, 0, 0],
+                    TgaImage::Rgb(image) => {
+                        let pixel = image.pixels[index];
+                        [pixel.blue, pixel.green, pixel.red, 0]
+                    },
+                    TgaImage::Rgba(image) => {
+                        let pixel = image.pixels[index];
+                        [pixel.blue, pixel.green, pixel.red, pixel.alpha]
+                    },
+                };
+                for (channel, &sample) in samples.iter().take(bytes_per_pixel).enumerate(){
+                    row[x * bytes_per_pixel + channel] = images::scale_sample(sample, max_value, 255) as u8;
+                }
+            }
+            match compression {
+                TgaCompression::Uncompressed => writer.write_all(&row)?,
+                TgaCompression::Rle => writer.write_all(&encode_rle(&row, bytes_per_pixel))?,
+            }
+        }
+
+        // the footer of TGA 2.0, without extension area and developer directory
+        writer.write_all(&[0; 8])?;
+        writer.write_all(FOOTER_SIGNATURE)?;
+        Ok(())
+    }
+
+    ///transform the image to a RGB image, the alpha channel is dropped
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// let image = TgaImage::new_with_file(Path::new("image.tga"))?.to_image();
+    /// ```
+    pub fn to_image(&self) -> Image{
+        match self {
+            TgaImage::Gray(image) => image.to_image(),
+            TgaImage::Rgb(image) => image.clone(),
+            TgaImage::Rgba(image) => image.to_image(),
+        }
+    }
+
+    /// the heigth, the width and the max value of the image
+    fn size(&self) -> (usize, usize, usize){
+        match self {
+            TgaImage::Gray(image) => (image.heigth, image.width, image.maxValue),
+            TgaImage::Rgb(image) => (image.heigth, image.width, image.maxValue),
+            TgaImage::Rgba(image) => (image.heigth, image.width, image.maxValue),
+        }
+    }
+}
+
+/// the run-length encoding of a row, the packets do not cross the rows
+///
+/// # Example
+///
+/// ```
+/// assert_eq!(vec![0x82, 7, 0x00, 9], encode_rle(&[7, 7, 7, 9], 1));
+/// ```
+fn encode_rle(row : &[u8], bytes_per_pixel : usize) -> Vec<u8>{
+    let pixels : Vec<&[u8]> = row.chunks(bytes_per_pixel).collect();
+    let mut out = Vec::with_capacity(row.len() + row.len() / bytes_per_pixel / MAX_PACKET + 1);
+    let mut start = 0;
+    while start < pixels.len() {
+        let run = pixels[start ..].iter().take(MAX_PACKET).take_while(|&&pixel| pixel == pixels[start]).count();
+        if run > 1 {
+            out.push(0x80 | (run - 1) as u8);
+            out.extend_from_slice(pixels[start]);
+            start += run;
+        } else {
+            // the raw packet end before the next run of 2 pixels
+            let mut end = start + 1;
+            while end < pixels.len() && end - start < MAX_PACKET && (end + 1 >= pixels.len() || pixels[end] != pixels[end + 1]) {
+                end += 1;
+            }
+            out.push((end - start - 1) as u8);
+            for pixel in &pixels[start .. end]{
+                out.extend_from_slice(pixel);
+            }
+            start = end;
+        }
+    }
+    out
+}
+
+/// an error of the TGA data
+fn corrupt<S : Into<String>>(message : S) -> PpmError{
+    PpmError::Corrupt{ format : "TGA", message : message.into() }
+}
+
+/// decode the TGA data of a file
+fn decode(data : &[u8]) -> Result<TgaImage, PpmError>{
+    if data.len() < HEADER_SIZE {
+        return Err(corrupt("the header is truncated"));
+    }
+    let id_len = data[0] as usize;
+    let color_map_type = data[1];
+    let image_type = data[2];
+    let color_map_len = u16::from_le_bytes([data[5], data[6]]) as usize;
+    let color_map_bits = data[7] as usize;
+    let width = u16::from_le_bytes([data[12], data[13]]) as usize;
+    let heigth = u16::from_le_bytes([data[14], data[15]]) as usize;
+    let bits = data[16];
+    let descriptor = data[17];
+
+    let rle = match image_type {
+        2 | 3 => false,
+        10 | 11 => true,
+        _ => return Err(PpmError::UnsupportedFormat(format!("TGA image type {}", image_type))),
+    };
+    let gray = image_type & 0x03 == 3;
+    match (gray, bits) {
+        (true, 8) | (false, 24) | (false, 32) => {},
+        _ => return Err(PpmError::UnsupportedFormat(format!("TGA of {} bits", bits))),
+    }
+    if color_map_type > 1 {
+        return Err(corrupt(format!("bad color map type {}", color_map_type)));
+    }
+    if width == 0 || heigth == 0 {
+        return Err(corrupt("the image is empty"));
+    }
+
+    // the color map of a true color image is not used
+    let offset = HEADER_SIZE + id_len + if color_map_type == 1 { color_map_len * color_map_bits.div_ceil(8) } else { 0 };
+    let bytes_per_pixel = bits as usize / 8;
+    let size = width * heigth * bytes_per_pixel;
+    let raw = if rle {
+        decode_rle(data.get(offset ..).unwrap_or(&[]), size, bytes_per_pixel)
+    } else {
+        data.get(offset .. offset + size).map(|raw| raw.to_vec())
+    };
+    let raw = match raw {
+        Some(raw) => raw,
+        None => return Err(PpmError::Truncated{ expected : size, found : data.len().saturating_sub(offset), position : Position{line : 1, byte : offset} }),
+    };
+
+    let top_down = descriptor & 0x20 != 0;
+    let right_to_left = descriptor & 0x10 != 0;
+    let alpha = bits == 32 && descriptor & 0x0f != 0;
+    let mut samples = Vec::with_capacity(width * heigth);
+    for y in 0 .. heigth{
+        let row = if top_down { y } else { heigth - 1 - y };
+        for x in 0 .. width{
+            let column = if right_to_left { width - 1 - x } else { x };
+            let start = (row * width + column) * bytes_per_pixel;
+            let pixel = &raw[start .. start + bytes_per_pixel];
+            samples.push(match bits {
+                8 => [pixel[0] as u16, 0, 0, 0],
+                24 => [pixel[2] as u16, pixel[1] as u16, pixel[0] as u16, 255],
+                _ => [pixel[2] as u16, pixel[1] as u16, pixel[0] as u16, pixel[3] as u16],
+            });
+        }
+    }
+
+    let image = if gray {
+        TgaImage::Gray(GrayImage::new(samples.iter().map(|sample| sample[0]).collect(), heigth, width, "P5".to_string(), 255))
+    } else if alpha {
+        let pixels = samples.iter().map(|sample| RgbaPixels::new(sample[0], sample[1], sample[2], sample[3])).collect();
+        TgaImage::Rgba(RgbaImage::new(pixels, heigth, width, 255))
+    } else {
+        let pixels = samples.iter().map(|sample| Pixels::new(sample[0], sample[1], sample[2])).collect();
+        TgaImage::Rgb(Image::new(pixels, heigth, width, "P6".to_string(), 255))
+    };
+    Ok(image)
+}
+
+/// decode the packets of the run-length encoding until `size` bytes, None if the data end before
+fn decode_rle(data : &[u8], size : usize, bytes_per_pixel : usize) -> Option<Vec<u8>>{
+    // a run packet of 1 + bytes_per_pixel bytes gives 128 pixels at most
+    let mut out = Vec::with_capacity(images::bounded_capacity(size, data.len(), MAX_PACKET));
+    let mut position = 0;
+    while out.len() < size {
+        let packet = *data.get(position)?;
+        let count = (packet & 0x7f) as usize + 1;
+        position += 1;
+        if packet & 0x80 != 0 {
+            let pixel = data.get(position .. position + bytes_per_pixel)?;
+            for _ in 0 .. count{
+                out.extend_from_slice(pixel);
+            }
+            position += bytes_per_pixel;
+        } else {
+            out.extend_from_slice(data.get(position .. position + count * bytes_per_pixel)?);
+            position += count * bytes_per_pixel;
+        }
+    }
+    // the last packet may go after the end of the image
+    out.truncate(size);
+    Some(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn test_encode_rle(){
+        assert_eq!(vec![0x82, 7, 0x00, 9], encode_rle(&[7, 7, 7, 9], 1));
+        assert_eq!(vec![0x01, 1, 2, 0x81, 3, 0x00, 4], encode_rle(&[1, 2, 3, 3, 4], 1));
+        assert_eq!(vec![0x81, 1, 2, 0x00, 3, 4], encode_rle(&[1, 2, 1, 2, 3, 4], 2));
+
+        let row = vec![5; 300];
+        assert_eq!(vec![0xff, 5, 0xff, 5, 0xab, 5], encode_rle(&row, 1));
+        let row : Vec<u8> = (0 .. 200).map(|value| value as u8).collect();
+        let encoded = encode_rle(&row, 1);
+        assert_eq!((0x7f, 0x47), (encoded[0], encoded[129]));
+        assert_eq!(Some(row), decode_rle(&encoded, 200, 1));
+    }
+
+    #[test]
+    fn test_read_rle(){
+        // a 3x2 image of 24 bits from the bottom, with a run packet across the rows
+        let mut data = vec![0, 0, 10, 0, 0, 0, 0, 0, 0, 0, 0, 0, 3, 0, 2, 0, 24, 0];
+        data.extend_from_slice(&[0x83, 0, 0, 255]);
+        data.extend_from_slice(&[0x01, 255, 0, 0, 0, 255, 0]);
+
+        let pixels = vec![Pixels::new(255, 0, 0), Pixels::new(0, 0, 255), Pixels::new(0, 255, 0),
+            Pixels::new(255, 0, 0), Pixels::new(255, 0, 0), Pixels::new(255, 0, 0)];
+        assert_eq!(TgaImage::Rgb(Image::new(pixels, 2, 3, "P6".to_string(), 255)), TgaImage::read_from(&data[..]).unwrap());
+
+        data.truncate(25);
+        match TgaImage::read_from(&data[..]) {
+            Err(PpmError::Truncated{ .. }) => {},
+            _ => panic!("the packets are truncated"),
+        }
+
+        // 65535x65535 pixels of 32 bits declared by 23 bytes
+        let mut data = vec![0, 0, 10, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0xff, 0xff, 0xff, 0xff, 32, 8];
+        data.extend_from_slice(&[0xff, 1, 2, 3, 4]);
+        assert_eq!(23, data.len());
+        match TgaImage::read_from(&data[..]) {
+            Err(PpmError::Truncated{ expected, .. }) => assert_eq!(65535 * 65535 * 4, expected),
+            _ => panic!("the packets are truncated"),
+        }
+    }
+
+    #[test]
+    fn test_round_trip_ppm() -> Result<(), PpmError>{
+        let image = images::ppm_scaled_to(255)?;
+        let images = vec![
+            TgaImage::Rgb(image.clone()),
+            TgaImage::Gray(image.grayscale()),
+            TgaImage::Rgba(RgbaImage::new(image.pixels.iter().enumerate().map(|(index, &pixel)| RgbaPixels::from_pixels(pixel, (index % 7) as u16 * 40)).collect(),
+                image.heigth, image.width, 255)),
+        ];
+
+        for image_tga in &images{
+            for &order in [RowOrder::BottomUp, RowOrder::TopDown].iter(){
+                for &compression in [TgaCompression::Uncompressed, TgaCompression::Rle].iter(){
+                    image_tga.save_with_options(Path::new("test_save.tga"), order, compression)?;
+                    assert_eq!(*image_tga, TgaImage::new_with_file(Path::new("test_save.tga"))?);
+                }
+            }
+        }
+
+        // the same pixels after the TGA and after the PPM
+        let ppm = Image::new_with_file(Path::new("resource/ppmImage.ppm"))?;
+        TgaImage::Rgb(ppm).save_with_options(Path::new("test_save.tga"), RowOrder::BottomUp, TgaCompression::Rle)?;
+        TgaImage::new_with_file(Path::new("test_save.tga"))?.to_image().save(Path::new("test_save_tga.ppm"))?;
+        assert_eq!(image, Image::new_with_file(Path::new("test_save_tga.ppm"))?);
+        fs::remove_file(Path::new("test_save.tga"))?;
+        fs::remove_file(Path::new("test_save_tga.ppm"))?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_write_layout() -> Result<(), PpmError>{
+        let image = GrayImage::new(vec![1, 2, 3, 4], 2, 2, "P5".to_string(), 255);
+        let mut bytes = Vec::new();
+        TgaImage::Gray(image).write_to(&mut bytes, RowOrder::BottomUp, TgaCompression::Uncompressed)?;
+        assert_eq!(&[3, 4, 1, 2], &bytes[18 .. 22]);
+        assert_eq!(FOOTER_SIGNATURE, &bytes[bytes.len() - 18 ..]);
+        assert_eq!((3, 8, 0), (bytes[2], bytes[16], bytes[17]));
+
+        Ok(())
+    }
+}
@@ -0,0 +1,653 @@
+use pixels::Pixels;
+use images::{self, Image};
+use error::{PpmError, Position};
+use gray_image::GrayImage;
+use rgba_image::{RgbaPixels, RgbaImage, GrayAlphaImage};
+
+use std::fs::File;
+use std::io::BufWriter;
+use std::io::prelude::*;
+use std::path::Path;
+
+/// the header of the little-endian TIFF files
+pub(crate) const MAGIC_LITTLE_ENDIAN : &[u8] = b"II*\0";
+/// the header of the big-endian TIFF files
+pub(crate) const MAGIC_BIG_ENDIAN : &[u8] = b"MM\0*";
+/// the size of the header, with the offset of the first directory
+const HEADER_SIZE : usize = 8;
+/// the size of an entry of a directory
+const ENTRY_SIZE : usize = 12;
+/// the size of the strips written, a strip holds as many rows as fit in it, one at least
+const STRIP_SIZE : usize = 8192;
+/// the longest run or literal of the PackBits compression
+const MAX_PACKET : usize = 128;
+
+const TAG_IMAGE_WIDTH : u16 = 256;
+const TAG_IMAGE_LENGTH : u16 = 257;
+const TAG_BITS_PER_SAMPLE : u16 = 258;
+const TAG_COMPRESSION : u16 = 259;
+const TAG_PHOTOMETRIC : u16 = 262;
+const TAG_STRIP_OFFSETS : u16 = 273;
+const TAG_SAMPLES_PER_PIXEL : u16 = 277;
+const TAG_ROWS_PER_STRIP : u16 = 278;
+const TAG_STRIP_BYTE_COUNTS : u16 = 279;
+const TAG_X_RESOLUTION : u16 = 282;
+const TAG_Y_RESOLUTION : u16 = 283;
+const TAG_PLANAR_CONFIGURATION : u16 = 284;
+const TAG_RESOLUTION_UNIT : u16 = 296;
+const TAG_EXTRA_SAMPLES : u16 = 338;
+
+const TYPE_BYTE : u16 = 1;
+const TYPE_SHORT : u16 = 3;
+const TYPE_LONG : u16 = 4;
+const TYPE_RATIONAL : u16 = 5;
+
+const COMPRESSION_NONE : u32 = 1;
+const COMPRESSION_PACKBITS : u32 = 32773;
+
+const PHOTOMETRIC_WHITE_IS_ZERO : u32 = 0;
+const PHOTOMETRIC_BLACK_IS_ZERO : u32 = 1;
+const PHOTOMETRIC_RGB : u32 = 2;
+
+/// the order of the bytes of the numbers of a TIFF file
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ByteOrder{
+    /// "II", the lowest byte first
+    LittleEndian,
+    /// "MM", the highest byte first
+    BigEndian,
+}
+
+/// the compression of the strips of a TIFF file
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum TiffCompression{
+    Uncompressed,
+    /// the run-length encoding of the bytes of each row, the repeated bytes are written once with their count
+    PackBits,
+}
+
+/// an image of the baseline TIFF format, with 8 or 16 bits samples in chunky strips
+///
+/// the images with a max value larger than 255 are written with 16 bits samples, the samples
+/// of an image with another max value than 255 or 65535 are scaled
+#[derive(Clone, Debug, PartialEq)]
+pub enum TiffImage{
+    Gray(GrayImage),
+    GrayAlpha(GrayAlphaImage),
+    Rgb(Image),
+    Rgba(RgbaImage),
+}
+
+impl ByteOrder {
+
+    fn u16(self, bytes : &[u8]) -> u16{
+        match self {
+            ByteOrder::LittleEndian => u16::from_le_bytes([bytes[0], bytes[1]]),
+            ByteOrder::BigEndian => u16::from_be_bytes([bytes[0], bytes[1]]),
+        }
+    }
+
+    fn u32(self, bytes : &[u8]) -> u32{
+        match self {
+            ByteOrder::LittleEndian => u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]),
+            ByteOrder::BigEndian => u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]),
+        }
+    }
+
+    fn u16_bytes(self, value : u16) -> [u8; 2]{
+        match self {
+            ByteOrder::LittleEndian => value.to_le_bytes(),
+            ByteOrder::BigEndian => value.to_be_bytes(),
+        }
+    }
+
+    fn u32_bytes(self, value : u32) -> [u8; 4]{
+        match self {
+            ByteOrder::LittleEndian => value.to_le_bytes(),
+            ByteOrder::BigEndian => value.to_be_bytes(),
+        }
+    }
+}
+
+impl TiffImage {
+
+    ///load the image from the file tiff
+    ///
+    /// the first image of the file is read, it must be gray or RGB, with or without alpha,
+    /// with samples of 8 or 16 bits, uncompressed or compressed with PackBits
+    ///
+    /// # Arguments
+    ///
+    /// * `filename` - the name of the file
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// let image = TiffImage::new_with_file(Path::new("scan.tif"))?.to_image();
+    /// ```
+    pub fn new_with_file(filename: &Path) -> Result<TiffImage, PpmError>{
+        TiffImage::read_from(File::open(filename)?)
+    }
+
+    ///load the image from a reader of TIFF data
+    ///
+    /// # Arguments
+    ///
+    /// * `reader` - the reader of the TIFF data
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// let image = TiffImage::read_from(&bytes[..])?;
+    /// ```
+    pub fn read_from<R : Read>(mut reader : R) -> Result<TiffImage, PpmError>{
+        let mut data = Vec::new();
+        reader.read_to_end(&mut data)?;
+        decode(&data)
+    }
+
+    ///save the image in a file of tiff, little-endian and uncompressed
+    ///
+    /// # Arguments
+    ///
+    /// * `filename` - the name of the file
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// TiffImage::Rgb(image).save(Path::new("image.tif"))?;
+    /// ```
+    pub fn save(&self, filename : &Path) -> Result<(), PpmError>{
+        self.save_with_options(filename, ByteOrder::LittleEndian, TiffCompression::Uncompressed)
+    }
+
+    ///save the image in a file of tiff with the given byte order and compression
+    ///
+    /// # Arguments
+    ///
+    /// * `filename` - the name of the file
+    /// * `order` - the order of the bytes of the numbers
+    /// * `compression` - the compression of the strips
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// image.save_with_options(Path::new("image.tif"), ByteOrder::BigEndian, TiffCompression::PackBits)?;
+    /// ```
+    pub fn save_with_options(&self, filename : &Path, order : ByteOrder, compression : TiffCompression) -> Result<(), PpmError>{
+        let mut writer = BufWriter::new(File::create(filename)?);
+        self.write_to(&mut writer, order, compression)?;
+        writer.flush()?;
+        Ok(())
+    }
+
+    ///write the image as TIFF data to a writer
+    ///
+    /// the directory is written after the header, before the strips
+    ///
+    /// # Arguments
+    ///
+    /// * `writer` - the writer of the TIFF data
+    /// * `order` - the order of the bytes of the numbers
+    /// * `compression` - the compression of the strips
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// let mut bytes = Vec::new();
+    /// image.write_to(&mut bytes, ByteOrder::LittleEndian, TiffCompression::Uncompressed)?;
+    /// ```
+    pub fn write_to<W : Write>(&self, mut writer : W, order : ByteOrder, compression : TiffCompression) -> Result<(), PpmError>{
+        let (heigth, width, max_value) = self.size();
+        if width == 0 || heigth == 0 || width > u32::MAX as usize || heigth > u32::MAX as usize {
+            return Err(PpmError::UnsupportedFormat(format!("TIFF image of {}x{} pixels", width, heigth)));
+        }
+        let (photometric, samples_per_pixel) = match self {
+            TiffImage::Gray(_) => (PHOTOMETRIC_BLACK_IS_ZERO, 1),
+            TiffImage::GrayAlpha(_) => (PHOTOMETRIC_BLACK_IS_ZERO, 2),
+            TiffImage::Rgb(_) => (PHOTOMETRIC_RGB, 3),
+            TiffImage::Rgba(_) => (PHOTOMETRIC_RGB, 4),
+        };
+        let (bits, target) = if max_value > 255 { (16, 65535) } else { (8, 255) };
+        let row_len = width * samples_per_pixel * bits / 8;
+        let rows_per_strip = (STRIP_SIZE / row_len).clamp(1, heigth);
+
+        // the strips, each row of a strip is compressed alone
+        let mut strips = Vec::with_capacity(heigth.div_ceil(rows_per_strip));
+        let mut row = Vec::with_capacity(row_len);
+        for first in (0 .. heigth).step_by(rows_per_strip){
+            let mut strip = Vec::new();
+            for y in first .. (first + rows_per_strip).min(heigth){
+                row.clear();
+                for x in 0 .. width{
+                    let index = y * width + x;
+                    let samples = match self {
+                        TiffImage::Gray(image) => [image.pixels[index], 0, 0, 0],
+                        TiffImage::GrayAlpha(image) => [image.pixels[index], image.alpha[index], 0, 0],
+                        TiffImage::Rgb(image) => {
+                            let pixel = image.pixels[index];
+                            [pixel.red, pixel.green, pixel.blue, 0]
+                        },
+                        TiffImage::Rgba(image) => {
+                            let pixel = image.pixels[index];
+                            [pixel.red, pixel.green, pixel.blue, pixel.alpha]
+                        },
+                    };
+                    for &sample in &samples[.. samples_per_pixel]{
+                        let sample = images::scale_sample(sample, max_value, target);
+                        if bits == 16 {
+                            row.extend_from_slice(&order.u16_bytes(sample));
+                        } else {
+                            row.push(sample as u8);
+                        }
+                    }
+                }
+                match compression {
+                    TiffCompression::Uncompressed => strip.extend_from_slice(&row),
+                    TiffCompression::PackBits => strip.extend_from_slice(&encode_packbits(&row)),
+                }
+            }
+            strips.push(strip);
+        }
+
+        // the entries of the directory, sorted by tag, with their type and their values
+        let strip_count = strips.len();
+        let mut entries = vec![
+            (TAG_IMAGE_WIDTH, TYPE_LONG, vec![width as u32]),
+            (TAG_IMAGE_LENGTH, TYPE_LONG, vec![heigth as u32]),
+            (TAG_BITS_PER_SAMPLE, TYPE_SHORT, vec![bits as u32; samples_per_pixel]),
+            (TAG_COMPRESSION, TYPE_SHORT, vec![if compression == TiffCompression::PackBits { COMPRESSION_PACKBITS } else { COMPRESSION_NONE }]),
+            (TAG_PHOTOMETRIC, TYPE_SHORT, vec![photometric]),
+            (TAG_STRIP_OFFSETS, TYPE_LONG, vec![0; strip_count]),
+            (TAG_SAMPLES_PER_PIXEL, TYPE_SHORT, vec![samples_per_pixel as u32]),
+            (TAG_ROWS_PER_STRIP, TYPE_LONG, vec![rows_per_strip as u32]),
+            (TAG_STRIP_BYTE_COUNTS, TYPE_LONG, strips.iter().map(|strip| strip.len() as u32).collect()),
+            // 72 pixels by inch
+            (TAG_X_RESOLUTION, TYPE_RATIONAL, vec![72, 1]),
+            (TAG_Y_RESOLUTION, TYPE_RATIONAL, vec![72, 1]),
+            (TAG_PLANAR_CONFIGURATION, TYPE_SHORT, vec![1]),
+            (TAG_RESOLUTION_UNIT, TYPE_SHORT, vec![2]),
+        ];
+        if samples_per_pixel % 2 == 0 {
+            // an alpha not premultiplied
+            entries.push((TAG_EXTRA_SAMPLES, TYPE_SHORT, vec![2]));
+        }
+
+        // the values longer than 4 bytes are after the directory, then the strips
+        let value_bytes = |kind : u16, values : &[u32]| -> Vec<u8> {
+            let mut bytes = Vec::new();
+            for &value in values{
+                match kind {
+                    TYPE_SHORT => bytes.extend_from_slice(&order.u16_bytes(value as u16)),
+                    _ => bytes.extend_from_slice(&order.u32_bytes(value)),
+                }
+            }
+            bytes
+        };
+        let directory_end = HEADER_SIZE + 2 + entries.len() * ENTRY_SIZE + 4;
+        let values_len : usize = entries.iter().map(|(_, kind, values)| value_bytes(*kind, values).len()).filter(|&len| len > 4).sum();
+        let mut offset = directory_end + values_len;
+        let total = offset + strips.iter().map(|strip| strip.len()).sum::<usize>();
+        if total > u32::MAX as usize {
+            return Err(PpmError::UnsupportedFormat(format!("TIFF file of {} bytes", total)));
+        }
+        for (strip, strip_offset) in strips.iter().zip(entries[5].2.iter_mut()){
+            *strip_offset = offset as u32;
+            offset += strip.len();
+        }
+
+        let mut out = Vec::with_capacity(directory_end + values_len);
+        out.extend_from_slice(if order == ByteOrder::LittleEndian { MAGIC_LITTLE_ENDIAN } else { MAGIC_BIG_ENDIAN });
+        out.extend_from_slice(&order.u32_bytes(HEADER_SIZE as u32));
+        out.extend_from_slice(&order.u16_bytes(entries.len() as u16));
+        let mut values = Vec::with_capacity(values_len);
+        for (tag, kind, entry_values) in &entries{
+            out.extend_from_slice(&order.u16_bytes(*tag));
+            out.extend_from_slice(&order.u16_bytes(*kind));
+            let count = if *kind == TYPE_RATIONAL { entry_values.len() / 2 } else { entry_values.len() };
+            out.extend_from_slice(&order.u32_bytes(count as u32));
+            let mut bytes = value_bytes(*kind, entry_values);
+            if bytes.len() > 4 {
+                out.extend_from_slice(&order.u32_bytes((directory_end + values.len()) as u32));
+                values.append(&mut bytes);
+            } else {
+                // the short values are at the start of the field
+                bytes.resize(4, 0);
+                out.extend_from_slice(&bytes);
+            }
+        }
+        // no next directory
+        out.extend_from_slice(&[0; 4]);
+        out.append(&mut values);
+        writer.write_all(&out)?;
+        for strip in &strips{
+            writer.write_all(strip)?;
+        }
+        Ok(())
+    }
+
+    ///transform the image to a RGB image, the alpha channel is dropped
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// let image = TiffImage::new_with_file(Path::new("scan.tif"))?.to_image();
+    /// ```
+    pub fn to_image(&self) -> Image{
+        match self {
+            TiffImage::Gray(image) => image.to_image(),
+            TiffImage::GrayAlpha(image) => image.to_gray().to_image(),
+            TiffImage::Rgb(image) => image.clone(),
+            TiffImage::Rgba(image) => image.to_image(),
+        }
+    }
+
+    /// the heigth, the width and the max value of the image
+    fn size(&self) -> (usize, usize, usize){
+        match self {
+            TiffImage::Gray(image) => (image.heigth, image.width, image.maxValue),
+            TiffImage::GrayAlpha(image) => (image.heigth, image.width, image.maxValue),
+            TiffImage::Rgb(image) => (image.heigth, image.width, image.maxValue),
+            TiffImage::Rgba(image) => (image.heigth, image.width, image.maxValue),
+        }
+    }
+}
+
+/// the PackBits compression of a row, the runs of less than 3 bytes are kept in the literals
+///
+/// # Example
+///
+/// ```
+/// assert_eq!(vec![0xfe, 7, 0x00, 9], encode_packbits(&[7, 7, 7, 9]));
+/// ```
+fn encode_packbits(row : &[u8]) -> Vec<u8>{
+    let run_at = |start : usize| row[start ..].iter().take(MAX_PACKET).take_while(|&&byte| byte == row[start]).count();
+    let mut out = Vec::with_capacity(row.len() + row.len() / MAX_PACKET + 1);
+    let mut start = 0;
+    while start < row.len() {
+        let run = run_at(start);
+        if run >= 3 {
+            out.push((1 - run as i32) as u8);
+            out.push(row[start]);
+            start += run;
+        } else {
+            let mut end = start + run;
+            while end < row.len() && end - start < MAX_PACKET && run_at(end) < 3 {
+                end += run_at(end);
+            }
+            let end = end.min(start + MAX_PACKET);
+            out.push((end - start - 1) as u8);
+            out.extend_from_slice(&row[start .. end]);
+            start = end;
+        }
+    }
+    out
+}
+
+/// decode the PackBits data until `size` bytes, None if the data end before
+fn decode_packbits(data : &[u8], size : usize) -> Option<Vec<u8>>{
+    // a run of 2 bytes gives 128 bytes at most
+    let mut out = Vec::with_capacity(images::bounded_capacity(size, data.len(), MAX_PACKET));
+    let mut position = 0;
+    while out.len() < size {
+        let header = *data.get(position)? as i8;
+        position += 1;
+        match header {
+            // a no-op
+            -128 => {},
+            0 ..= 127 => {
+                let count = header as usize + 1;
+                out.extend_from_slice(data.get(position .. position + count)?);
+                position += count;
+            },
+            _ => {
+                let byte = *data.get(position)?;
+                position += 1;
+                out.extend(std::iter::repeat_n(byte, (1 - header as i32) as usize));
+            },
+        }
+    }
+    out.truncate(size);
+    Some(out)
+}
+
+/// an error of the TIFF data
+fn corrupt<S : Into<String>>(message : S) -> PpmError{
+    PpmError::Corrupt{ format : "TIFF", message : message.into() }
+}
+
+/// decode the first image of the TIFF data of a file
+fn decode(data : &[u8]) -> Result<TiffImage, PpmError>{
+    let order = match data.get(.. 4) {
+        Some(MAGIC_LITTLE_ENDIAN) => ByteOrder::LittleEndian,
+        Some(MAGIC_BIG_ENDIAN) => ByteOrder::BigEndian,
+        _ => {
+            let magic = String::from_utf8_lossy(&data[.. data.len().min(4)]).into_owned();
+            return Err(PpmError::BadMagic{ magic, position : Position{line : 1, byte : 0} });
+        },
+    };
+    if data.len() < HEADER_SIZE {
+        return Err(corrupt("the header is truncated"));
+    }
+    let directory = order.u32(&data[4 ..]) as usize;
+    let count = data.get(directory .. directory + 2).map(|bytes| order.u16(bytes) as usize)
+        .ok_or_else(|| corrupt("the directory is after the end of the data"))?;
+    let entries = data.get(directory + 2 .. directory + 2 + count * ENTRY_SIZE)
+        .ok_or_else(|| corrupt("the directory is truncated"))?;
+
+    // the values of the entries of integers, the other entries are not used
+    let mut tags = Vec::with_capacity(count);
+    for entry in entries.chunks(ENTRY_SIZE){
+        let tag = order.u16(&entry[0 ..]);
+        let size = match order.u16(&entry[2 ..]) {
+            TYPE_BYTE => 1,
+            TYPE_SHORT => 2,
+            TYPE_LONG => 4,
+            _ => continue,
+        };
+        let count = order.u32(&entry[4 ..]) as usize;
+        let len = count.checked_mul(size).ok_or_else(|| corrupt(format!("the tag {} is too long", tag)))?;
+        let bytes = if len <= 4 {
+            &entry[8 .. 8 + len]
+        } else {
+            let offset = order.u32(&entry[8 ..]) as usize;
+            data.get(offset .. offset + len).ok_or_else(|| corrupt(format!("the values of the tag {} are after the end of the data", tag)))?
+        };
+        let values : Vec<usize> = bytes.chunks(size).map(|value| match size {
+            1 => value[0] as usize,
+            2 => order.u16(value) as usize,
+            _ => order.u32(value) as usize,
+        }).collect();
+        tags.push((tag, values));
+    }
+    let values = |tag : u16| tags.iter().find(|(found, _)| *found == tag).map(|(_, values)| values.as_slice());
+    let value = |tag : u16, default : Option<usize>| -> Result<usize, PpmError> {
+        match values(tag) {
+            Some(values) if !values.is_empty() => Ok(values[0]),
+            Some(_) => Err(corrupt(format!("the tag {} has no value", tag))),
+            None => default.ok_or_else(|| corrupt(format!("the tag {} is missing", tag))),
+        }
+    };
+
+    let width = value(TAG_IMAGE_WIDTH, None)?;
+    let heigth = value(TAG_IMAGE_LENGTH, None)?;
+    let samples_per_pixel = value(TAG_SAMPLES_PER_PIXEL, Some(1))?;
+    let photometric = value(TAG_PHOTOMETRIC, None)? as u32;
+    let compression = value(TAG_COMPRESSION, Some(COMPRESSION_NONE as usize))? as u32;
+    let rows_per_strip = value(TAG_ROWS_PER_STRIP, Some(u32::MAX as usize))?.clamp(1, heigth.max(1));
+    let bits = values(TAG_BITS_PER_SAMPLE).unwrap_or(&[1]);
+    if width == 0 || heigth == 0 {
+        return Err(corrupt("the image is empty"));
+    }
+    match (photometric, samples_per_pixel) {
+        (PHOTOMETRIC_WHITE_IS_ZERO, 1 ..= 2) | (PHOTOMETRIC_BLACK_IS_ZERO, 1 ..= 2) | (PHOTOMETRIC_RGB, 3 ..= 4) => {},
+        _ => return Err(PpmError::UnsupportedFormat(format!("TIFF with the photometric interpretation {} and {} samples", photometric, samples_per_pixel))),
+    }
+    let bits = match bits.first() {
+        Some(&first) if (first == 8 || first == 16) && bits.iter().all(|&other| other == first) => first,
+        _ => return Err(PpmError::UnsupportedFormat(format!("TIFF of {:?} bits", bits))),
+    };
+    if value(TAG_PLANAR_CONFIGURATION, Some(1))? != 1 {
+        return Err(PpmError::UnsupportedFormat("TIFF with planar samples".to_string()));
+    }
+    if compression != COMPRESSION_NONE && compression != COMPRESSION_PACKBITS {
+        return Err(PpmError::UnsupportedFormat(format!("TIFF compression {}", compression)));
+    }
+    let offsets = values(TAG_STRIP_OFFSETS).ok_or_else(|| PpmError::UnsupportedFormat("TIFF without strips".to_string()))?;
+    let byte_counts = values(TAG_STRIP_BYTE_COUNTS).ok_or_else(|| corrupt("the byte counts of the strips are missing"))?;
+    if offsets.len() != byte_counts.len() || offsets.len() < heigth.div_ceil(rows_per_strip) {
+        return Err(corrupt(format!("{} strips for {} offsets", byte_counts.len(), offsets.len())));
+    }
+
+    let row_len = width.checked_mul(samples_per_pixel * bits / 8).ok_or_else(|| corrupt("the image is too large"))?;
+    let size = row_len.checked_mul(heigth).ok_or_else(|| corrupt("the image is too large"))?;
+    let mut raw = Vec::with_capacity(size.min(data.len() * 2));
+    for (strip, (&offset, &byte_count)) in offsets.iter().zip(byte_counts.iter()).enumerate().take(heigth.div_ceil(rows_per_strip)){
+        let strip_len = row_len * rows_per_strip.min(heigth - strip * rows_per_strip);
+        let found = raw.len();
+        let truncated = || PpmError::Truncated{ expected : size, found, position : Position{line : 1, byte : offset} };
+        let bytes = data.get(offset .. offset.saturating_add(byte_count)).ok_or_else(truncated)?;
+        if compression == COMPRESSION_PACKBITS {
+            raw.extend(decode_packbits(bytes, strip_len).ok_or_else(truncated)?);
+        } else {
+            raw.extend_from_slice(bytes.get(.. strip_len).ok_or_else(truncated)?);
+        }
+    }
+
+    let (samples, max_value) = if bits == 16 {
+        (raw.chunks(2).map(|sample| order.u16(sample)).collect::<Vec<u16>>(), 65535)
+    } else {
+        (raw.iter().map(|&sample| sample as u16).collect(), 255)
+    };
+    let gray = |sample : u16| if photometric == PHOTOMETRIC_WHITE_IS_ZERO { max_value - sample } else { sample };
+    let pixels = samples.chunks(samples_per_pixel);
+    let image = match samples_per_pixel {
+        1 => TiffImage::Gray(GrayImage::new(samples.iter().map(|&sample| gray(sample)).collect(), heigth, width, "P5".to_string(), max_value as usize)),
+        2 => TiffImage::GrayAlpha(GrayAlphaImage::new(pixels.clone().map(|pixel| gray(pixel[0])).collect(), pixels.map(|pixel| pixel[1]).collect(),
+            heigth, width, max_value as usize)),
+        3 => TiffImage::Rgb(Image::new(pixels.map(|pixel| Pixels::new(pixel[0], pixel[1], pixel[2])).collect(), heigth, width, "P6".to_string(), max_value as usize)),
+        _ => TiffImage::Rgba(RgbaImage::new(pixels.map(|pixel| RgbaPixels::new(pixel[0], pixel[1], pixel[2], pixel[3])).collect(), heigth, width, max_value as usize)),
+    };
+    Ok(image)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn test_packbits(){
+        // the example of the TIFF specification
+        let row = [0xaa, 0xaa, 0xaa, 0x80, 0x00, 0x2a, 0xaa, 0xaa, 0xaa, 0xaa, 0x80, 0x00, 0x2a, 0x22,
+            0xaa, 0xaa, 0xaa, 0xaa, 0xaa, 0xaa, 0xaa, 0xaa, 0xaa, 0xaa];
+        let packed = vec![0xfe, 0xaa, 0x02, 0x80, 0x00, 0x2a, 0xfd, 0xaa, 0x03, 0x80, 0x00, 0x2a, 0x22, 0xf7, 0xaa];
+        assert_eq!(packed, encode_packbits(&row));
+        assert_eq!(Some(row.to_vec()), decode_packbits(&packed, row.len()));
+
+        let row = vec![5; 300];
+        assert_eq!(vec![0x81, 5, 0x81, 5, 0xd5, 5], encode_packbits(&row));
+        let row : Vec<u8> = (0 .. 200).map(|value| (value / 2) as u8).collect();
+        let packed = encode_packbits(&row);
+        assert_eq!((0x7f, 0x47), (packed[0], packed[129]));
+        assert_eq!(Some(row), decode_packbits(&packed, 200));
+
+        // the no-op header and a truncated run
+        assert_eq!(Some(vec![1, 1]), decode_packbits(&[0x80, 0xff, 1], 2));
+        assert_eq!(None, decode_packbits(&[0x02, 1, 2], 3));
+    }
+
+    #[test]
+    fn test_round_trip() -> Result<(), PpmError>{
+        let image = images::ppm_scaled_to(255)?;
+        let wide : Vec<Pixels> = (0 .. 300 * 40).map(|index| Pixels::new((index * 7 % 65536) as u16, (index / 300 * 1000) as u16, 65535)).collect();
+        let gray = GrayImage::new(image.pixels.iter().map(|pixel| pixel.red).collect(), image.heigth, image.width, "P5".to_string(), 255);
+        let images = vec![
+            TiffImage::Rgb(image.clone()),
+            TiffImage::Gray(gray.clone()),
+            TiffImage::GrayAlpha(GrayAlphaImage::new(gray.pixels.clone(), (0 .. 16).map(|index| index * 16).collect(), 4, 4, 255)),
+            TiffImage::Rgba(RgbaImage::new(image.pixels.iter().map(|&pixel| RgbaPixels::from_pixels(pixel, 128)).collect(), 4, 4, 255)),
+            // 16 bits samples in several strips
+            TiffImage::Rgb(Image::new(wide.clone(), 40, 300, "P6".to_string(), 65535)),
+            TiffImage::Gray(GrayImage::new(wide.iter().map(|pixel| pixel.red).collect(), 40, 300, "P5".to_string(), 65535)),
+        ];
+
+        for image_tiff in &images{
+            for &order in [ByteOrder::LittleEndian, ByteOrder::BigEndian].iter(){
+                for &compression in [TiffCompression::Uncompressed, TiffCompression::PackBits].iter(){
+                    image_tiff.save_with_options(Path::new("test_save.tif"), order, compression)?;
+                    assert_eq!(*image_tiff, TiffImage::new_with_file(Path::new("test_save.tif"))?);
+                }
+            }
+        }
+        fs::remove_file(Path::new("test_save.tif"))?;
+
+        let ppm = Image::new_with_file(Path::new("resource/ppmImage.ppm"))?;
+        let mut bytes = Vec::new();
+        TiffImage::Rgb(ppm).write_to(&mut bytes, ByteOrder::BigEndian, TiffCompression::Uncompressed)?;
+        assert_eq!(image, TiffImage::read_from(&bytes[..])?.to_image());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_read_big_endian(){
+        // a 2x1 gray image of 16 bits with white at zero and the tags in SHORT, without rows by strip
+        let mut data = b"MM\0*\0\0\0\x08\0\x06".to_vec();
+        data.extend_from_slice(&[1, 0, 0, 3, 0, 0, 0, 1, 0, 2, 0, 0]);
+        data.extend_from_slice(&[1, 1, 0, 3, 0, 0, 0, 1, 0, 1, 0, 0]);
+        data.extend_from_slice(&[1, 2, 0, 3, 0, 0, 0, 1, 0, 16, 0, 0]);
+        data.extend_from_slice(&[1, 6, 0, 3, 0, 0, 0, 1, 0, 0, 0, 0]);
+        data.extend_from_slice(&[1, 17, 0, 4, 0, 0, 0, 1, 0, 0, 0, 86]);
+        data.extend_from_slice(&[1, 23, 0, 3, 0, 0, 0, 1, 0, 4, 0, 0]);
+        data.extend_from_slice(&[0, 0, 0, 0]);
+        data.extend_from_slice(&[0x00, 0x00, 0x12, 0x34]);
+
+        let image = GrayImage::new(vec![65535, 65535 - 0x1234], 1, 2, "P5".to_string(), 65535);
+        assert_eq!(TiffImage::Gray(image), TiffImage::read_from(&data[..]).unwrap());
+
+        data.truncate(88);
+        match TiffImage::read_from(&data[..]) {
+            Err(PpmError::Truncated{ .. }) => {},
+            _ => panic!("the strip is truncated"),
+        }
+    }
+
+    #[test]
+    fn test_read_errors(){
+        match TiffImage::read_from(&b"IM*\0\x08\0\0\0"[..]) {
+            Err(PpmError::BadMagic{ magic, .. }) => assert_eq!("IM*\0", magic),
+            _ => panic!("the byte order is wrong"),
+        }
+        match TiffImage::read_from(&b"II*\0\x40\0\0\0"[..]) {
+            Err(PpmError::Corrupt{ .. }) => {},
+            _ => panic!("the directory is after the end"),
+        }
+
+        // a palette image
+        let mut data = b"II*\0\x08\0\0\0\x03\0".to_vec();
+        data.extend_from_slice(&[0, 1, 3, 0, 1, 0, 0, 0, 1, 0, 0, 0]);
+        data.extend_from_slice(&[1, 1, 3, 0, 1, 0, 0, 0, 1, 0, 0, 0]);
+        data.extend_from_slice(&[6, 1, 3, 0, 1, 0, 0, 0, 3, 0, 0, 0]);
+        match TiffImage::read_from(&data[..]) {
+            Err(PpmError::UnsupportedFormat(_)) => {},
+            _ => panic!("the palette images are not supported"),
+        }
+
+        // a strip of 4294967295x8 bytes declared by a run of 2 bytes
+        let mut data = b"II*\0\x08\0\0\0\x07\0".to_vec();
+        data.extend_from_slice(&[0, 1, 4, 0, 1, 0, 0, 0, 0xff, 0xff, 0xff, 0xff]);
+        data.extend_from_slice(&[1, 1, 4, 0, 1, 0, 0, 0, 8, 0, 0, 0]);
+        data.extend_from_slice(&[2, 1, 3, 0, 1, 0, 0, 0, 8, 0, 0, 0]);
+        data.extend_from_slice(&[3, 1, 3, 0, 1, 0, 0, 0, 0x05, 0x80, 0, 0]);
+        data.extend_from_slice(&[6, 1, 3, 0, 1, 0, 0, 0, 1, 0, 0, 0]);
+        data.extend_from_slice(&[0x11, 1, 4, 0, 1, 0, 0, 0, 98, 0, 0, 0]);
+        data.extend_from_slice(&[0x17, 1, 4, 0, 1, 0, 0, 0, 2, 0, 0, 0]);
+        data.extend_from_slice(&[0, 0, 0, 0, 0x81, 0]);
+        match TiffImage::read_from(&data[..]) {
+            Err(PpmError::Truncated{ expected, found, .. }) => assert_eq!((0xffff_ffff * 8, 0), (expected, found)),
+            _ => panic!("the strip is truncated"),
+        }
+        assert_eq!(None, decode_packbits(&[0x81, 0], usize::MAX));
+    }
+}
@@ -0,0 +1,267 @@
+use bit_image::BitImage;
+use error::{PpmError, Position};
+
+use std::fs::File;
+use std::io::BufWriter;
+use std::io::prelude::*;
+use std::path::Path;
+
+/// the start of the XBM files, the first `#define` of the width
+pub(crate) const MAGIC : &[u8] = b"#define";
+/// the number of bytes written by line of the array of bits
+const BYTES_BY_LINE : usize = 12;
+
+impl BitImage {
+
+    ///load the image from the file xbm, the X11 bitmap written as C source
+    ///
+    /// the bits of the X11 (char) and of the X10 (short) bitmaps are read, a bit set to 1 is black
+    ///
+    /// # Arguments
+    ///
+    /// * `filename` - the name of the file
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// let image = BitImage::new_with_xbm(Path::new("icon.xbm"))?;
+    /// ```
+    pub fn new_with_xbm(filename : &Path) -> Result<BitImage, PpmError>{
+        BitImage::read_xbm(File::open(filename)?)
+    }
+
+    ///load the image from a reader of XBM source
+    ///
+    /// # Arguments
+    ///
+    /// * `reader` - the reader of the XBM source
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// let image = BitImage::read_xbm("#define i_width 1\n#define i_height 1\nstatic char i_bits[] = {0x01};".as_bytes())?;
+    /// ```
+    pub fn read_xbm<R : Read>(mut reader : R) -> Result<BitImage, PpmError>{
+        let mut data = Vec::new();
+        reader.read_to_end(&mut data)?;
+        let text = strip_c_comments(&String::from_utf8_lossy(&data));
+        if !text.trim_start().starts_with("#define") {
+            let magic = text.trim_start().chars().take(MAGIC.len()).collect();
+            return Err(PpmError::BadMagic{ magic, position : Position{line : 1, byte : 0} });
+        }
+
+        let (mut width, mut heigth) = (None, None);
+        for line in text.lines(){
+            let tokens : Vec<&str> = line.split_whitespace().collect();
+            if tokens.len() != 3 || tokens[0] != "#define" {
+                continue;
+            }
+            let value = || tokens[2].parse::<usize>().map_err(|_| corrupt(format!("{} {:?} is not a number", tokens[1], tokens[2])));
+            if tokens[1].ends_with("_width") {
+                width = Some(value()?);
+            } else if tokens[1].ends_with("_height") {
+                heigth = Some(value()?);
+            }
+        }
+        let width = width.ok_or_else(|| corrupt("the #define of the width is missing"))?;
+        let heigth = heigth.ok_or_else(|| corrupt("the #define of the height is missing"))?;
+        if width == 0 || heigth == 0 {
+            return Err(corrupt("the image is empty"));
+        }
+
+        // the array after the last #define, of char or of short
+        let start = text.find('{').ok_or_else(|| corrupt("the array of the bits is missing"))?;
+        let short = text[.. start].contains("short");
+        let end = text[start ..].find('}').map(|end| start + end).unwrap_or(text.len());
+        let mut values = Vec::new();
+        for token in text[start + 1 .. end].split(',').map(|token| token.trim()).filter(|token| !token.is_empty()){
+            let value = match token.strip_prefix("0x").or_else(|| token.strip_prefix("0X")) {
+                Some(hex) => u16::from_str_radix(hex, 16),
+                None => token.parse::<u16>(),
+            };
+            values.push(value.map_err(|_| corrupt(format!("{:?} is not a number", token)))?);
+        }
+
+        let bits_by_value = if short { 16 } else { 8 };
+        let values_by_row = width.div_ceil(bits_by_value);
+        let expected = values_by_row.checked_mul(heigth).ok_or_else(|| corrupt("the image is too large"))?;
+        if values.len() < expected {
+            return Err(PpmError::Truncated{ expected, found : values.len(), position : Position{line : text[.. end].lines().count(), byte : end} });
+        }
+        let mut image = BitImage::new(heigth, width, "P4".to_string());
+        for y in 0 .. heigth{
+            for x in 0 .. width{
+                // the first pixel is the lowest bit
+                let value = values[y * values_by_row + x / bits_by_value];
+                if value >> (x % bits_by_value) & 1 != 0 {
+                    image.set(x, y, true);
+                }
+            }
+        }
+        Ok(image)
+    }
+
+    ///save the image in a file of xbm, the name of the C variables is the name of the file
+    ///
+    /// # Arguments
+    ///
+    /// * `filename` - the name of the file
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// BitImage::from_image(&image, 128).save_xbm(Path::new("icon.xbm"))?;
+    /// ```
+    pub fn save_xbm(&self, filename : &Path) -> Result<(), PpmError>{
+        let mut writer = BufWriter::new(File::create(filename)?);
+        self.write_xbm(&mut writer, &c_identifier(filename))?;
+        writer.flush()?;
+        Ok(())
+    }
+
+    ///write the image as XBM source to a writer
+    ///
+    /// # Arguments
+    ///
+    /// * `writer` - the writer of the XBM source
+    /// * `name` - the prefix of the C variables, `name_width`, `name_height` and `name_bits`
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// let mut source = Vec::new();
+    /// image.write_xbm(&mut source, "icon")?;
+    /// ```
+    pub fn write_xbm<W : Write>(&self, mut writer : W, name : &str) -> Result<(), PpmError>{
+        let mut source = format!("#define {}_width {}\n#define {}_height {}\n", name, self.width, name, self.heigth);
+        source += &format!("static unsigned char {}_bits[] = {{\n", name);
+        let mut values = Vec::with_capacity(self.heigth * self.width.div_ceil(8));
+        for y in 0 .. self.heigth{
+            for x in (0 .. self.width).step_by(8){
+                let value = (x .. (x + 8).min(self.width)).filter(|&column| self.get(column, y)).fold(0u8, |value, column| value | 1 << (column - x));
+                values.push(format!("0x{:02x}", value));
+            }
+        }
+        let lines : Vec<String> = values.chunks(BYTES_BY_LINE).map(|line| format!("   {}", line.join(", "))).collect();
+        source += &lines.join(",\n");
+        source += " };\n";
+        writer.write_all(source.as_bytes())?;
+        Ok(())
+    }
+}
+
+/// an error of the XBM source
+fn corrupt<S : Into<String>>(message : S) -> PpmError{
+    PpmError::Corrupt{ format : "XBM", message : message.into() }
+}
+
+/// the C source without its commentaires, each commentaire is replaced by a space and keep its ends of line
+///
+/// # Example
+///
+/// ```
+/// assert_eq!("static \nchar", strip_c_comments("static/* a\n b */char"));
+/// ```
+pub(crate) fn strip_c_comments(source : &str) -> String{
+    let mut out = String::with_capacity(source.len());
+    let mut rest = source;
+    while let Some(start) = rest.find("/*") {
+        out.push_str(&rest[.. start]);
+        out.push(' ');
+        match rest[start + 2 ..].find("*/") {
+            Some(end) => {
+                out.extend(rest[start + 2 .. start + 2 + end].chars().filter(|&c| c == '\n'));
+                rest = &rest[start + 2 + end + 2 ..];
+            },
+            None => rest = "",
+        }
+    }
+    out.push_str(rest);
+    out
+}
+
+/// a name of C variable from the name of a file, the characters that are not allowed become `_`
+///
+/// # Example
+///
+/// ```
+/// assert_eq!("my_icon", c_identifier(Path::new("dir/my-icon.xbm")));
+/// ```
+pub(crate) fn c_identifier(filename : &Path) -> String{
+    let stem = filename.file_stem().map(|stem| stem.to_string_lossy().into_owned()).unwrap_or_default();
+    let mut name : String = stem.chars().map(|c| if c.is_ascii_alphanumeric() { c } else { '_' }).collect();
+    if name.chars().next().is_none_or(|c| c.is_ascii_digit()) {
+        name.insert(0, '_');
+    }
+    name
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn test_c_source(){
+        assert_eq!("static \nchar", strip_c_comments("static/* a\n b */char"));
+        assert_eq!("a   b  ", strip_c_comments("a /**/ b /* unterminated"));
+        assert_eq!("my_icon", c_identifier(Path::new("dir/my-icon.xbm")));
+        assert_eq!("_16x16", c_identifier(Path::new("16x16.xbm")));
+    }
+
+    #[test]
+    fn test_write_xbm() -> Result<(), PpmError>{
+        let mut image = BitImage::new(2, 10, "P4".to_string());
+        image.set(0, 0, true);
+        image.set(9, 0, true);
+        image.set(3, 1, true);
+        let mut source = Vec::new();
+        image.write_xbm(&mut source, "icon")?;
+        assert_eq!("#define icon_width 10\n#define icon_height 2\nstatic unsigned char icon_bits[] = {\n   0x01, 0x02, 0x08, 0x00 };\n",
+            String::from_utf8(source).unwrap());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_round_trip() -> Result<(), PpmError>{
+        let mut image = BitImage::new(7, 21, "P4".to_string());
+        for index in 0 .. 7 * 21{
+            image.set(index % 21, index / 21, index % 3 == 0 || index % 7 == 1);
+        }
+        image.save_xbm(Path::new("test_save.xbm"))?;
+        let source = fs::read_to_string(Path::new("test_save.xbm"))?;
+        assert!(source.starts_with("#define test_save_width 21\n"));
+        assert_eq!(image, BitImage::new_with_xbm(Path::new("test_save.xbm"))?);
+        fs::remove_file(Path::new("test_save.xbm"))?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_read_xbm(){
+        // a X10 bitmap of short with commentaires and decimal values
+        let source = "/* made by hand */\n#define x10_width 17\n#define x10_height 1\n#define x10_x_hot 3\n\
+            static short x10_bits[] = {\n 0x8001, /* the last */ 1};\n";
+        let image = BitImage::read_xbm(source.as_bytes()).unwrap();
+        assert_eq!((1, 17), (image.heigth, image.width));
+        assert_eq!(vec![0, 15, 16], (0 .. 17).filter(|&x| image.get(x, 0)).collect::<Vec<usize>>());
+
+        match BitImage::read_xbm("P1 1 1 0".as_bytes()) {
+            Err(PpmError::BadMagic{ .. }) => {},
+            _ => panic!("the file is not a XBM bitmap"),
+        }
+        match BitImage::read_xbm("#define i_width 9\n#define i_height 2\nstatic char i_bits[] = { 0xff, 0x01, 0x00 };".as_bytes()) {
+            Err(PpmError::Truncated{ expected, found, .. }) => assert_eq!((4, 3), (expected, found)),
+            _ => panic!("a byte is missing"),
+        }
+        match BitImage::read_xbm("#define i_width 9\nstatic char i_bits[] = { 0xff };".as_bytes()) {
+            Err(PpmError::Corrupt{ .. }) => {},
+            _ => panic!("the height is missing"),
+        }
+        match BitImage::read_xbm("#define i_width 0\n#define i_height 100000000000000\nstatic char i_bits[] = { };".as_bytes()) {
+            Err(PpmError::Corrupt{ message, .. }) => assert_eq!("the image is empty", message),
+            _ => panic!("the width is zero"),
+        }
+    }
+}
@@ -0,0 +1,424 @@
+use pixels::Pixels;
+use images::{self, Image};
+use error::{PpmError, Position};
+use rgba_image::{RgbaPixels, RgbaImage};
+use xbm::c_identifier;
+
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::BufWriter;
+use std::io::prelude::*;
+use std::path::Path;
+
+/// the commentaire at the start of the XPM files
+pub(crate) const MAGIC : &[u8] = b"/* XPM */";
+/// the characters of the codes of the colors written, without `"` and `\` that must be escaped in C
+const CODE_CHARS : &[u8] = b" .XoO+@#$%&*=-;:>,<1234567890qwertyuipasdfghjklzxcvbnmMNBVCZASDFGHJKLPIUYTREWQ!~^/()_`'][{}|";
+/// a few colors of the X11 database, the files written only use `#RRGGBB`
+const NAMED_COLORS : [(&str, [u8; 3]); 10] = [
+    ("black", [0, 0, 0]), ("white", [255, 255, 255]), ("red", [255, 0, 0]), ("green", [0, 255, 0]),
+    ("blue", [0, 0, 255]), ("yellow", [255, 255, 0]), ("cyan", [0, 255, 255]), ("magenta", [255, 0, 255]),
+    ("gray", [190, 190, 190]), ("grey", [190, 190, 190])];
+
+/// an image of the XPM format, the X11 pixmap written as C source with a palette of colors
+///
+/// the colors are written with 8 bits samples, the samples of an image with another max
+/// value than 255 are scaled; the transparent color `None` makes a RGBA image
+#[derive(Clone, Debug, PartialEq)]
+pub enum XpmImage{
+    Rgb(Image),
+    Rgba(RgbaImage),
+}
+
+impl XpmImage {
+
+    ///load the image from the file xpm
+    ///
+    /// # Arguments
+    ///
+    /// * `filename` - the name of the file
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// let image = XpmImage::new_with_file(Path::new("icon.xpm"))?.to_image();
+    /// ```
+    pub fn new_with_file(filename: &Path) -> Result<XpmImage, PpmError>{
+        XpmImage::read_from(File::open(filename)?)
+    }
+
+    ///load the image from a reader of XPM source
+    ///
+    /// the colors are found in the `c` key, or else in `g`, `g4` and `m`; they are `None`,
+    /// `#RGB`, `#RRGGBB`, `#RRRRGGGGBBBB` or a few names of colors
+    ///
+    /// # Arguments
+    ///
+    /// * `reader` - the reader of the XPM source
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// let image = XpmImage::read_from(&source[..])?;
+    /// ```
+    pub fn read_from<R : Read>(mut reader : R) -> Result<XpmImage, PpmError>{
+        let mut data = Vec::new();
+        reader.read_to_end(&mut data)?;
+        let text = String::from_utf8_lossy(&data);
+        if !text.trim_start().as_bytes().starts_with(MAGIC) {
+            let magic = text.trim_start().chars().take(MAGIC.len()).collect();
+            return Err(PpmError::BadMagic{ magic, position : Position{line : 1, byte : 0} });
+        }
+
+        let strings = c_strings(&text)?;
+        let (values, _) = strings.first().ok_or_else(|| corrupt("the values are missing"))?;
+        let numbers = values.split_whitespace().map(|value| value.parse::<usize>()).collect::<Result<Vec<usize>, _>>()
+            .map_err(|_| corrupt(format!("the values {:?} are not numbers", values)))?;
+        if numbers.len() < 4 || numbers[3] == 0 {
+            return Err(corrupt(format!("the values {:?} are not the width, the height, the colors and the characters by pixel", values)));
+        }
+        let (width, heigth, colors, chars) = (numbers[0], numbers[1], numbers[2], numbers[3]);
+        if strings.len() < 1 + colors {
+            return Err(corrupt(format!("{} colors are missing", 1 + colors - strings.len())));
+        }
+
+        // the palette, None for the transparent color
+        let mut palette = HashMap::with_capacity(colors);
+        for (color, line) in &strings[1 .. 1 + colors]{
+            let code = color.get(.. chars).ok_or_else(|| corrupt(format!("the color {:?} has no code", color)))?;
+            let value = parse_color(&color[chars ..]).map_err(|message| PpmError::bad_header(message, Position{line : *line, byte : 0}))?;
+            palette.insert(code, value);
+        }
+
+        let size = width.checked_mul(heigth).ok_or_else(|| corrupt("the image is too large"))?;
+        let row_len = width.checked_mul(chars).ok_or_else(|| corrupt("the image is too large"))?;
+        // the values are not trusted, the pixels are allocated for the strings found
+        let rows = &strings[1 + colors ..];
+        let mut pixels = Vec::with_capacity(size.min(rows.iter().take(heigth).map(|(row, _)| row.len() / chars).sum()));
+        for (row, line) in rows.iter().take(heigth){
+            if row.len() != row_len || !row.is_ascii() {
+                return Err(PpmError::bad_header(format!("the row has {} characters instead of {}", row.chars().count(), row_len), Position{line : *line, byte : 0}));
+            }
+            for code in (0 .. width).map(|x| &row[x * chars .. (x + 1) * chars]){
+                let value = palette.get(code).ok_or_else(|| PpmError::bad_header(format!("the color {:?} is not in the palette", code), Position{line : *line, byte : 0}))?;
+                pixels.push(*value);
+            }
+        }
+        if pixels.len() < size {
+            let line = strings.last().map(|(_, line)| *line).unwrap_or(1);
+            return Err(PpmError::Truncated{ expected : size, found : pixels.len(), position : Position{line, byte : 0} });
+        }
+
+        let image = if palette.values().any(|value| value.is_none()) {
+            let pixels = pixels.iter().map(|value| match value {
+                Some(color) => RgbaPixels::new(color[0] as u16, color[1] as u16, color[2] as u16, 255),
+                None => RgbaPixels::new(0, 0, 0, 0),
+            }).collect();
+            XpmImage::Rgba(RgbaImage::new(pixels, heigth, width, 255))
+        } else {
+            let pixels = pixels.iter().flatten().map(|color| Pixels::new(color[0] as u16, color[1] as u16, color[2] as u16)).collect();
+            XpmImage::Rgb(Image::new(pixels, heigth, width, "P6".to_string(), 255))
+        };
+        Ok(image)
+    }
+
+    ///save the image in a file of xpm, the name of the C variable is the name of the file
+    ///
+    /// # Arguments
+    ///
+    /// * `filename` - the name of the file
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// XpmImage::Rgb(image).save(Path::new("icon.xpm"))?;
+    /// ```
+    pub fn save(&self, filename : &Path) -> Result<(), PpmError>{
+        let mut writer = BufWriter::new(File::create(filename)?);
+        self.write_to(&mut writer, &c_identifier(filename))?;
+        writer.flush()?;
+        Ok(())
+    }
+
+    ///write the image as XPM source to a writer
+    ///
+    /// the palette is built from the colors of the image, in the order where they are found,
+    /// with as many characters by pixel as needed; a pixel with an alpha under the half of the
+    /// max value is transparent
+    ///
+    /// # Arguments
+    ///
+    /// * `writer` - the writer of the XPM source
+    /// * `name` - the name of the C variable
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// let mut source = Vec::new();
+    /// XpmImage::Rgb(image).write_to(&mut source, "icon")?;
+    /// ```
+    pub fn write_to<W : Write>(&self, mut writer : W, name : &str) -> Result<(), PpmError>{
+        let (heigth, width, max_value) = self.size();
+        let scale = |sample : u16| images::scale_sample(sample, max_value, 255) as u8;
+        let pixels : Vec<Option<[u8; 3]>> = match self {
+            XpmImage::Rgb(image) => image.pixels.iter().map(|pixel| Some([scale(pixel.red), scale(pixel.green), scale(pixel.blue)])).collect(),
+            XpmImage::Rgba(image) => image.pixels.iter().map(|pixel| if (pixel.alpha as usize) < max_value.div_ceil(2) {
+                None
+            } else {
+                Some([scale(pixel.red), scale(pixel.green), scale(pixel.blue)])
+            }).collect(),
+        };
+
+        let mut colors = Vec::new();
+        let mut indexes = HashMap::new();
+        for &pixel in &pixels{
+            indexes.entry(pixel).or_insert_with(|| {
+                colors.push(pixel);
+                colors.len() - 1
+            });
+        }
+        let mut chars = 1;
+        while CODE_CHARS.len().pow(chars as u32) < colors.len() {
+            chars += 1;
+        }
+        let code = |mut index : usize| -> String {
+            let mut code = String::with_capacity(chars);
+            for _ in 0 .. chars{
+                code.push(CODE_CHARS[index % CODE_CHARS.len()] as char);
+                index /= CODE_CHARS.len();
+            }
+            code
+        };
+
+        let mut source = format!("/* XPM */\nstatic char *{}[] = {{\n/* columns rows colors chars-per-pixel */\n", name);
+        source += &format!("\"{} {} {} {}\",\n", width, heigth, colors.len(), chars);
+        for (index, color) in colors.iter().enumerate(){
+            match color {
+                Some(color) => source += &format!("\"{} c #{:02X}{:02X}{:02X}\",\n", code(index), color[0], color[1], color[2]),
+                None => source += &format!("\"{} c None\",\n", code(index)),
+            }
+        }
+        source += "/* pixels */\n";
+        let codes : Vec<String> = (0 .. colors.len()).map(code).collect();
+        let rows : Vec<String> = pixels.chunks(width.max(1)).take(heigth).map(|row| {
+            let row : String = row.iter().map(|pixel| codes[indexes[pixel]].as_str()).collect();
+            format!("\"{}\"", row)
+        }).collect();
+        source += &rows.join(",\n");
+        source += "\n};\n";
+        writer.write_all(source.as_bytes())?;
+        Ok(())
+    }
+
+    ///transform the image to a RGB image, the alpha channel is dropped
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// let image = XpmImage::new_with_file(Path::new("icon.xpm"))?.to_image();
+    /// ```
+    pub fn to_image(&self) -> Image{
+        match self {
+            XpmImage::Rgb(image) => image.clone(),
+            XpmImage::Rgba(image) => image.to_image(),
+        }
+    }
+
+    /// the heigth, the width and the max value of the image
+    fn size(&self) -> (usize, usize, usize){
+        match self {
+            XpmImage::Rgb(image) => (image.heigth, image.width, image.maxValue),
+            XpmImage::Rgba(image) => (image.heigth, image.width, image.maxValue),
+        }
+    }
+}
+
+/// an error of the XPM source
+fn corrupt<S : Into<String>>(message : S) -> PpmError{
+    PpmError::Corrupt{ format : "XPM", message : message.into() }
+}
+
+/// the strings of the C source with the line where they start, outside of the commentaires
+///
+/// # Example
+///
+/// ```
+/// assert_eq!(vec![("a/*".to_string(), 2)], c_strings("/* \" */\n\"a/*\"")?);
+/// ```
+fn c_strings(source : &str) -> Result<Vec<(String, usize)>, PpmError>{
+    let mut strings = Vec::new();
+    let mut line = 1;
+    let mut chars = source.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '\n' => line += 1,
+            '/' if chars.peek() == Some(&'*') => {
+                chars.next();
+                let mut previous = ' ';
+                for c in chars.by_ref(){
+                    if c == '\n' {
+                        line += 1;
+                    }
+                    if previous == '*' && c == '/' {
+                        break;
+                    }
+                    previous = c;
+                }
+            },
+            '"' => {
+                let start = line;
+                let mut string = String::new();
+                loop {
+                    match chars.next() {
+                        Some('"') => break,
+                        Some('\\') => string.extend(chars.next()),
+                        Some('\n') | None => return Err(PpmError::bad_header("the string is not closed", Position{line : start, byte : 0})),
+                        Some(c) => string.push(c),
+                    }
+                }
+                strings.push((string, start));
+            },
+            _ => {},
+        }
+    }
+    Ok(strings)
+}
+
+/// the color of an entry of the palette after its code, None for the transparent color
+///
+/// # Example
+///
+/// ```
+/// assert_eq!(Ok(Some([255, 0, 0])), parse_color(" s red c #FF0000"));
+/// ```
+fn parse_color(entry : &str) -> Result<Option<[u8; 3]>, String>{
+    // the values are the words after a key until the next key, a name of color may have several words
+    let mut values : Vec<(&str, Vec<&str>)> = Vec::new();
+    for word in entry.split_whitespace(){
+        match (word, values.last_mut()) {
+            ("c" | "m" | "g" | "g4" | "s", _) => values.push((word, Vec::new())),
+            (_, Some((_, words))) => words.push(word),
+            (_, None) => return Err(format!("{:?} is not a key of color", word)),
+        }
+    }
+    let (_, words) = ["c", "g", "g4", "m"].iter().filter_map(|key| values.iter().find(|(found, _)| found == key)).next()
+        .ok_or_else(|| format!("{:?} has no color", entry.trim()))?;
+    let color = words.join(" ");
+
+    if color.eq_ignore_ascii_case("none") {
+        return Ok(None);
+    }
+    if let Some(hex) = color.strip_prefix('#') {
+        let digits = hex.len() / 3;
+        if hex.len() % 3 != 0 || !(1 ..= 4).contains(&digits) || !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+            return Err(format!("{:?} is not a color", color));
+        }
+        let max_value = (1usize << (4 * digits)) - 1;
+        let mut rgb = [0u8; 3];
+        for (channel, sample) in rgb.iter_mut().enumerate(){
+            let value = u16::from_str_radix(&hex[channel * digits .. (channel + 1) * digits], 16).map_err(|_| format!("{:?} is not a color", color))?;
+            *sample = images::scale_sample(value, max_value, 255) as u8;
+        }
+        return Ok(Some(rgb));
+    }
+    NAMED_COLORS.iter().find(|(name, _)| name.eq_ignore_ascii_case(&color)).map(|&(_, rgb)| Some(rgb))
+        .ok_or_else(|| format!("the color {:?} is not known", color))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn test_c_strings() -> Result<(), PpmError>{
+        assert_eq!(vec![("a/*".to_string(), 2), ("\"\\".to_string(), 3)], c_strings("/* \" */\n\"a/*\" /**/\n\"\\\"\\\\\"")?);
+        match c_strings("\"a\n\"") {
+            Err(PpmError::BadHeader{ position, .. }) => assert_eq!(1, position.line),
+            _ => panic!("the string is not closed"),
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_color(){
+        assert_eq!(Ok(Some([255, 0, 0])), parse_color(" s red c #FF0000"));
+        assert_eq!(Ok(Some([0x11, 0x22, 0xff])), parse_color(" c #12f"));
+        assert_eq!(Ok(Some([0x12, 0x56, 0x56])), parse_color(" c #123456785678"));
+        assert_eq!(Ok(None), parse_color(" s mask c None"));
+        assert_eq!(Ok(Some([190, 190, 190])), parse_color(" m white g Grey"));
+        assert_eq!(Ok(Some([255, 255, 255])), parse_color(" m white"));
+        assert!(parse_color(" c #12345").is_err());
+        assert!(parse_color(" c light gray").is_err());
+        assert!(parse_color(" #ffffff").is_err());
+    }
+
+    #[test]
+    fn test_write_palette() -> Result<(), PpmError>{
+        let pixels = vec![Pixels::new(15, 0, 0), Pixels::new(0, 0, 15), Pixels::new(15, 0, 0), Pixels::new(15, 15, 15)];
+        let mut source = Vec::new();
+        XpmImage::Rgb(Image::new(pixels, 2, 2, "P3".to_string(), 15)).write_to(&mut source, "icon")?;
+        let expected = "/* XPM */\nstatic char *icon[] = {\n/* columns rows colors chars-per-pixel */\n\"2 2 3 1\",\n\
+            \"  c #FF0000\",\n\". c #0000FF\",\n\"X c #FFFFFF\",\n/* pixels */\n\" .\",\n\" X\"\n};\n";
+        assert_eq!(expected, String::from_utf8(source).unwrap());
+
+        // more colors than characters, two characters by pixel
+        let pixels : Vec<Pixels> = (0 .. 100).map(|index| Pixels::new(index, 0, 0)).collect();
+        let image = XpmImage::Rgb(Image::new(pixels, 10, 10, "P6".to_string(), 255));
+        let mut source = Vec::new();
+        image.write_to(&mut source, "ramp")?;
+        assert!(String::from_utf8(source.clone()).unwrap().contains("\"10 10 100 2\""));
+        assert_eq!(image, XpmImage::read_from(&source[..])?);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_round_trip() -> Result<(), PpmError>{
+        let ppm = Image::new_with_file(Path::new("resource/ppmImage.ppm"))?;
+        XpmImage::Rgb(ppm.clone()).save(Path::new("test_save.xpm"))?;
+        let image = XpmImage::new_with_file(Path::new("test_save.xpm"))?;
+        assert_eq!(images::ppm_scaled_to(255)?.pixels, image.to_image().pixels);
+        assert!(fs::read_to_string(Path::new("test_save.xpm"))?.contains("static char *test_save[]"));
+
+        let pixels = vec![RgbaPixels::new(1, 2, 3, 255), RgbaPixels::new(9, 9, 9, 0), RgbaPixels::new(4, 5, 6, 200)];
+        let image = XpmImage::Rgba(RgbaImage::new(pixels, 1, 3, 255));
+        image.save(Path::new("test_save.xpm"))?;
+        let pixels = vec![RgbaPixels::new(1, 2, 3, 255), RgbaPixels::new(0, 0, 0, 0), RgbaPixels::new(4, 5, 6, 255)];
+        assert_eq!(XpmImage::Rgba(RgbaImage::new(pixels, 1, 3, 255)), XpmImage::new_with_file(Path::new("test_save.xpm"))?);
+        fs::remove_file(Path::new("test_save.xpm"))?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_read_errors(){
+        match XpmImage::read_from(&b"#define i_width 1"[..]) {
+            Err(PpmError::BadMagic{ .. }) => {},
+            _ => panic!("the file is a XBM bitmap"),
+        }
+        let source = b"/* XPM */\nstatic char *i[] = {\n\"2 2 1 1\",\n\"a c #000000\",\n\"aa\",\n\"ab\"};";
+        match XpmImage::read_from(&source[..]) {
+            Err(PpmError::BadHeader{ position, .. }) => assert_eq!(6, position.line),
+            _ => panic!("b is not in the palette"),
+        }
+        let source = b"/* XPM */\nstatic char *i[] = {\n\"2 2 1 1\",\n\"a c #000000\",\n\"aa\"};";
+        match XpmImage::read_from(&source[..]) {
+            Err(PpmError::Truncated{ expected, found, .. }) => assert_eq!((4, 2), (expected, found)),
+            _ => panic!("a row is missing"),
+        }
+
+        // the values declare 10^12 pixels, the pixels are not allocated before the rows are read
+        let source = b"/* XPM */\nstatic char *i[] = {\n\"1000000000000 1 1 1\",\n\"a c #000000\"};";
+        match XpmImage::read_from(&source[..]) {
+            Err(PpmError::Truncated{ expected, found, .. }) => assert_eq!((1_000_000_000_000, 0), (expected, found)),
+            _ => panic!("the rows are missing"),
+        }
+        let source = b"/* XPM */\nstatic char *i[] = {\n\"1000000000000 1 1 1\",\n\"a c #000000\",\n\"a\"};";
+        match XpmImage::read_from(&source[..]) {
+            Err(PpmError::BadHeader{ position, .. }) => assert_eq!(5, position.line),
+            _ => panic!("the row is too short"),
+        }
+    }
+}
@@ -0,0 +1,562 @@
+use images::{self, Image};
+use pixels::Pixels;
+use error::{PpmError, Position};
+
+use std::fs::File;
+use std::io::{BufReader, BufWriter};
+use std::io::prelude::*;
+use std::path::Path;
+
+/// the magic number at the start of the Y4M streams
+pub(crate) const MAGIC : &[u8] = b"YUV4MPEG2";
+/// the tag at the start of each frame
+const FRAME : &[u8] = b"FRAME";
+
+/// the sampling of the chroma planes of a Y4M stream
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ChromaSampling{
+    /// one sample of Cb and of Cr for 2x2 pixels
+    Chroma420,
+    /// one sample of Cb and of Cr for 2 pixels of a row
+    Chroma422,
+    /// one sample of Cb and of Cr for each pixel
+    Chroma444,
+    /// no chroma planes, the frames are gray
+    Mono,
+}
+
+impl ChromaSampling {
+
+    /// the sampling of a tag of the header, the 4:2:0 tags of the different chroma sitings are read the same
+    fn from_tag(tag : &str) -> Option<ChromaSampling>{
+        match tag {
+            "420" | "420jpeg" | "420paldv" | "420mpeg2" => Some(ChromaSampling::Chroma420),
+            "422" => Some(ChromaSampling::Chroma422),
+            "444" => Some(ChromaSampling::Chroma444),
+            "mono" => Some(ChromaSampling::Mono),
+            _ => None,
+        }
+    }
+
+    /// the tag of the header, the 4:2:0 chroma is the mean of 2x2 pixels like in JPEG
+    fn tag(&self) -> &'static str{
+        match self {
+            ChromaSampling::Chroma420 => "420jpeg",
+            ChromaSampling::Chroma422 => "422",
+            ChromaSampling::Chroma444 => "444",
+            ChromaSampling::Mono => "mono",
+        }
+    }
+
+    /// the number of pixels of a sample of chroma, in width and in heigth
+    fn step(&self) -> (usize, usize){
+        match self {
+            ChromaSampling::Chroma420 => (2, 2),
+            ChromaSampling::Chroma422 => (2, 1),
+            ChromaSampling::Chroma444 | ChromaSampling::Mono => (1, 1),
+        }
+    }
+
+    /// the width and the heigth of the chroma planes of a frame
+    fn plane_size(&self, width : usize, heigth : usize) -> (usize, usize){
+        match self {
+            ChromaSampling::Mono => (0, 0),
+            _ => {
+                let (step_x, step_y) = self.step();
+                (width.div_ceil(step_x), heigth.div_ceil(step_y))
+            },
+        }
+    }
+}
+
+/// an iterator on the frames of a YUV4MPEG2 stream, transformed to RGB
+///
+/// the samples are BT.601 YCbCr of the video range, Y in 16 .. 235 and Cb, Cr in 16 .. 240; the
+/// chroma samples are repeated on the pixels they cover. The iterator stop after the last frame,
+/// or after the first error
+pub struct Y4mReader<R>{
+    reader : BufReader<R>,
+    width : usize,
+    heigth : usize,
+    frame_rate : (u32, u32),
+    chroma : ChromaSampling,
+    /// the bytes read, for the position of the errors
+    byte : usize,
+    done : bool,
+}
+
+impl<R : Read> Y4mReader<R> {
+
+    /// create an iterator on the frames of a reader, the header of the stream is read
+    ///
+    /// # Arguments
+    ///
+    /// * `reader` - the reader of the stream, a file, a pipe or a slice of bytes
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// for image in Y4mReader::new(io::stdin())? {
+    ///     let image = image?;
+    /// }
+    /// ```
+    pub fn new(reader : R) -> Result<Y4mReader<R>, PpmError>{
+        let mut reader = BufReader::new(reader);
+        let mut line = Vec::new();
+        reader.read_until(b'\n', &mut line)?;
+        if !line.starts_with(MAGIC) || line.get(MAGIC.len()).is_some_and(|&c| c != b' ' && c != b'\n') {
+            let magic = String::from_utf8_lossy(&line[.. line.len().min(MAGIC.len())]).into_owned();
+            return Err(PpmError::BadMagic{ magic, position : Position{line : 1, byte : 0} });
+        }
+        if line.last() != Some(&b'\n') {
+            return Err(PpmError::bad_header("the header has no end of line", Position{line : 1, byte : line.len()}));
+        }
+
+        let (mut width, mut heigth, mut frame_rate, mut chroma) = (None, None, (25, 1), ChromaSampling::Chroma420);
+        let mut byte = MAGIC.len() + 1;
+        for parameter in String::from_utf8_lossy(&line[MAGIC.len() .. line.len() - 1]).split(' ').filter(|parameter| !parameter.is_empty()){
+            let position = Position{line : 1, byte};
+            byte += parameter.len() + 1;
+            let mut chars = parameter.chars();
+            let tag = chars.next();
+            let value = chars.as_str();
+            let number = |value : &str| value.parse::<usize>().ok().filter(|&number| number > 0)
+                .ok_or_else(|| PpmError::bad_header(format!("{:?} is not a size", parameter), position));
+            match tag {
+                Some('W') => width = Some(number(value)?),
+                Some('H') => heigth = Some(number(value)?),
+                Some('F') => {
+                    let rate = value.split_once(':').and_then(|(numerator, denominator)| Some((numerator.parse().ok()?, denominator.parse().ok()?)));
+                    frame_rate = rate.filter(|&(_, denominator)| denominator > 0)
+                        .ok_or_else(|| PpmError::bad_header(format!("{:?} is not a frame rate", parameter), position))?;
+                },
+                Some('C') => {
+                    chroma = ChromaSampling::from_tag(value)
+                        .ok_or_else(|| PpmError::UnsupportedFormat(format!("Y4M colorspace {}", value)))?;
+                },
+                // the interlacing, the aspect ratio and the extensions do not change the samples
+                _ => {},
+            }
+        }
+        let width = width.ok_or_else(|| PpmError::bad_header("missing W", Position{line : 1, byte : line.len()}))?;
+        let heigth = heigth.ok_or_else(|| PpmError::bad_header("missing H", Position{line : 1, byte : line.len()}))?;
+        if width.checked_mul(heigth).and_then(|size| size.checked_mul(3)).is_none() {
+            return Err(corrupt("the frames are too large"));
+        }
+        Ok(Y4mReader{reader, width, heigth, frame_rate, chroma, byte : line.len(), done : false})
+    }
+
+    /// the width of the frames
+    pub fn width(&self) -> usize{
+        self.width
+    }
+
+    /// the heigth of the frames
+    pub fn heigth(&self) -> usize{
+        self.heigth
+    }
+
+    /// the number of frames by second, as a fraction
+    pub fn frame_rate(&self) -> (u32, u32){
+        self.frame_rate
+    }
+
+    /// the sampling of the chroma planes
+    pub fn chroma(&self) -> ChromaSampling{
+        self.chroma
+    }
+
+    /// read the next frame, None at the end of the stream
+    fn read_frame(&mut self) -> Result<Option<Image>, PpmError>{
+        let mut line = Vec::new();
+        self.reader.read_until(b'\n', &mut line)?;
+        if line.is_empty() {
+            return Ok(None);
+        }
+        let position = Position{line : 1, byte : self.byte};
+        if !line.starts_with(FRAME) || line.last() != Some(&b'\n') {
+            return Err(corrupt(format!("the frame at the byte {} has no FRAME header", self.byte)));
+        }
+        self.byte += line.len();
+
+        let size = self.width * self.heigth;
+        let (chroma_width, chroma_heigth) = self.chroma.plane_size(self.width, self.heigth);
+        let expected = size + 2 * chroma_width * chroma_heigth;
+        // the frame is read in an empty buffer, it grows only with the bytes found
+        let mut data = Vec::new();
+        let found = self.reader.by_ref().take(expected as u64).read_to_end(&mut data)?;
+        self.byte += found;
+        if found < expected {
+            return Err(PpmError::Truncated{ expected, found, position });
+        }
+
+        let (luma, chroma) = data.split_at(size);
+        let (blue, red) = chroma.split_at(chroma_width * chroma_heigth);
+        let (step_x, step_y) = self.chroma.step();
+        let mut pixels = Vec::with_capacity(size);
+        for y in 0 .. self.heigth{
+            for x in 0 .. self.width{
+                let index = (y / step_y) * chroma_width + x / step_x;
+                let (cb, cr) = match self.chroma {
+                    ChromaSampling::Mono => (128, 128),
+                    _ => (blue[index], red[index]),
+                };
+                pixels.push(ycbcr_to_rgb(luma[y * self.width + x], cb, cr));
+            }
+        }
+        Ok(Some(Image::new(pixels, self.heigth, self.width, "P6".to_string(), 255)))
+    }
+}
+
+impl Y4mReader<File> {
+
+    /// create an iterator on the frames of a file y4m
+    ///
+    /// # Arguments
+    ///
+    /// * `filename` - the name of the file
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// let frames = Y4mReader::new_with_file(Path::new("video.y4m"))?.collect::<Result<Vec<Image>, PpmError>>()?;
+    /// ```
+    pub fn new_with_file(filename : &Path) -> Result<Y4mReader<File>, PpmError>{
+        let f = File::open(filename)?;
+        Y4mReader::new(f)
+    }
+}
+
+impl<R : Read> Iterator for Y4mReader<R> {
+    type Item = Result<Image, PpmError>;
+
+    fn next(&mut self) -> Option<Result<Image, PpmError>>{
+        if self.done {
+            return None;
+        }
+        let image = self.read_frame();
+        if !matches!(image, Ok(Some(_))) {
+            self.done = true;
+        }
+        image.transpose()
+    }
+}
+
+/// a writer of the frames of a YUV4MPEG2 stream, from RGB images
+///
+/// the samples are scaled to 0 .. 255 and transformed to BT.601 YCbCr of the video range; the
+/// chroma samples are the mean of the pixels they cover
+pub struct Y4mWriter<W>{
+    writer : W,
+    width : usize,
+    heigth : usize,
+    chroma : ChromaSampling,
+    frames : usize,
+}
+
+impl<W : Write> Y4mWriter<W> {
+
+    /// create a writer of frames, the header of the stream is written
+    ///
+    /// # Arguments
+    ///
+    /// * `writer` - the writer of the stream, a file, a pipe or a vec of bytes
+    /// * `width` - the width of the frames
+    /// * `heigth` - the heigth of the frames
+    /// * `frame_rate` - the number of frames by second, as a fraction
+    /// * `chroma` - the sampling of the chroma planes
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// let mut frames = Y4mWriter::new(io::stdout(), 640, 480, (30, 1), ChromaSampling::Chroma420)?;
+    /// ```
+    pub fn new(mut writer : W, width : usize, heigth : usize, frame_rate : (u32, u32), chroma : ChromaSampling) -> Result<Y4mWriter<W>, PpmError>{
+        if width == 0 || heigth == 0 || frame_rate.1 == 0 {
+            return Err(PpmError::UnsupportedFormat(format!("Y4M stream of {}x{} pixels at {}:{} frames by second", width, heigth, frame_rate.0, frame_rate.1)));
+        }
+        writeln!(writer, "YUV4MPEG2 W{} H{} F{}:{} Ip A1:1 C{}", width, heigth, frame_rate.0, frame_rate.1, chroma.tag())?;
+        Ok(Y4mWriter{writer, width, heigth, chroma, frames : 0})
+    }
+
+    /// append a frame after the frames already written, the image has the size of the header
+    ///
+    /// # Arguments
+    ///
+    /// * `image` - the image to write
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// let mut frames = Y4mWriter::new(Vec::new(), image.width, image.heigth, (25, 1), ChromaSampling::Chroma444)?;
+    /// frames.write_frame(&image)?;
+    /// assert_eq!(1, frames.frames());
+    /// ```
+    pub fn write_frame(&mut self, image : &Image) -> Result<(), PpmError>{
+        if image.width != self.width {
+            return Err(PpmError::RowLength{ expected : self.width, found : image.width });
+        }
+        if image.heigth != self.heigth {
+            return Err(PpmError::RowCount{ expected : self.heigth, found : image.heigth });
+        }
+
+        let size = self.width * self.heigth;
+        let mut luma = Vec::with_capacity(size);
+        let mut blue = vec![0.0f32; size];
+        let mut red = vec![0.0f32; size];
+        for (index, pixel) in image.pixels.iter().enumerate(){
+            let scale = |sample : u16| images::scale_sample(sample, image.maxValue, 255) as f32;
+            let (y, cb, cr) = rgb_to_ycbcr(scale(pixel.red), scale(pixel.green), scale(pixel.blue));
+            luma.push(y);
+            blue[index] = cb;
+            red[index] = cr;
+        }
+
+        let mut data = luma.iter().map(|&y| clamp_sample(y)).collect::<Vec<u8>>();
+        if self.chroma != ChromaSampling::Mono {
+            let (chroma_width, chroma_heigth) = self.chroma.plane_size(self.width, self.heigth);
+            let (step_x, step_y) = self.chroma.step();
+            let (width, heigth) = (self.width, self.heigth);
+            for plane in [&blue, &red].iter(){
+                for y in 0 .. chroma_heigth{
+                    for x in 0 .. chroma_width{
+                        // the mean of the pixels of the block, smaller on the right and bottom edges
+                        let rows = y * step_y .. ((y + 1) * step_y).min(heigth);
+                        let columns = x * step_x .. ((x + 1) * step_x).min(width);
+                        let count = rows.len() * columns.len();
+                        let sum : f32 = rows.flat_map(|row| columns.clone().map(move |column| plane[row * width + column])).sum();
+                        data.push(clamp_sample(sum / count as f32));
+                    }
+                }
+            }
+        }
+        self.writer.write_all(FRAME)?;
+        self.writer.write_all(b"\n")?;
+        self.writer.write_all(&data)?;
+        self.frames += 1;
+        Ok(())
+    }
+
+    /// the number of frames written
+    pub fn frames(&self) -> usize{
+        self.frames
+    }
+
+    /// give back the writer, after the last frame
+    pub fn into_inner(self) -> W{
+        self.writer
+    }
+}
+
+impl Y4mWriter<BufWriter<File>> {
+
+    /// create a writer of frames in a new file y4m
+    ///
+    /// # Arguments
+    ///
+    /// * `filename` - the name of the file
+    /// * `width` - the width of the frames
+    /// * `heigth` - the heigth of the frames
+    /// * `frame_rate` - the number of frames by second, as a fraction
+    /// * `chroma` - the sampling of the chroma planes
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// let mut frames = Y4mWriter::new_with_file(Path::new("video.y4m"), 640, 480, (30, 1), ChromaSampling::Chroma420)?;
+    /// ```
+    pub fn new_with_file(filename : &Path, width : usize, heigth : usize, frame_rate : (u32, u32), chroma : ChromaSampling) -> Result<Y4mWriter<BufWriter<File>>, PpmError>{
+        let f = File::create(filename)?;
+        Y4mWriter::new(BufWriter::new(f), width, heigth, frame_rate, chroma)
+    }
+}
+
+/// an error of the Y4M stream
+fn corrupt<S : Into<String>>(message : S) -> PpmError{
+    PpmError::Corrupt{ format : "Y4M", message : message.into() }
+}
+
+/// a sample rounded and clamped to 0 .. 255
+fn clamp_sample(sample : f32) -> u8{
+    sample.round().clamp(0.0, 255.0) as u8
+}
+
+/// the BT.601 YCbCr of the video range of a RGB color with samples in 0 .. 255
+fn rgb_to_ycbcr(red : f32, green : f32, blue : f32) -> (f32, f32, f32){
+    let y = 16.0 + (65.481 * red + 128.553 * green + 24.966 * blue) / 255.0;
+    let cb = 128.0 + (-37.797 * red - 74.203 * green + 112.0 * blue) / 255.0;
+    let cr = 128.0 + (112.0 * red - 93.786 * green - 18.214 * blue) / 255.0;
+    (y, cb, cr)
+}
+
+/// the RGB color with samples in 0 .. 255 of a BT.601 YCbCr of the video range
+fn ycbcr_to_rgb(y : u8, cb : u8, cr : u8) -> Pixels{
+    let y = (y as f32 - 16.0) * 255.0 / 219.0;
+    let cb = (cb as f32 - 128.0) * 255.0 / 224.0;
+    let cr = (cr as f32 - 128.0) * 255.0 / 224.0;
+    let red = y + 1.402 * cr;
+    let green = y - 0.344_136 * cb - 0.714_136 * cr;
+    let blue = y + 1.772 * cb;
+    Pixels::new(clamp_sample(red) as u16, clamp_sample(green) as u16, clamp_sample(blue) as u16)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    fn frame(step : u16) -> Image{
+        // a gradient of 5x3 pixels, odd to test the edges of the chroma planes
+        let pixels = (0 .. 15).map(|index| Pixels::new(index * 16 + step, 200 - index * 10, 40 + step * 20)).collect();
+        Image::new(pixels, 3, 5, "P6".to_string(), 255)
+    }
+
+    #[test]
+    fn test_conversion(){
+        assert_eq!((16, 128, 128), {
+            let (y, cb, cr) = rgb_to_ycbcr(0.0, 0.0, 0.0);
+            (clamp_sample(y), clamp_sample(cb), clamp_sample(cr))
+        });
+        assert_eq!(Pixels::new(0, 0, 0), ycbcr_to_rgb(16, 128, 128));
+        assert_eq!(Pixels::new(255, 255, 255), ycbcr_to_rgb(235, 128, 128));
+        // the red of the BT.601 color bars, the samples are rounded to the video range
+        assert_eq!(Pixels::new(254, 0, 0), ycbcr_to_rgb(81, 90, 240));
+
+        // the error of a round trip is less than the step of the video range
+        for red in (0 .. 256).step_by(15){
+            for green in (0 .. 256).step_by(15){
+                for blue in (0 .. 256).step_by(15){
+                    let (y, cb, cr) = rgb_to_ycbcr(red as f32, green as f32, blue as f32);
+                    let pixel = ycbcr_to_rgb(clamp_sample(y), clamp_sample(cb), clamp_sample(cr));
+                    for (sample, expected) in [(pixel.red, red), (pixel.green, green), (pixel.blue, blue)].iter(){
+                        assert!((*sample as i32 - *expected).abs() <= 3, "{:?} for {} {} {}", pixel, red, green, blue);
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_round_trip() -> Result<(), PpmError>{
+        let frames = vec![frame(0), frame(1), frame(2)];
+        let mut writer = Y4mWriter::new(Vec::new(), 5, 3, (30000, 1001), ChromaSampling::Chroma444)?;
+        for image in &frames{
+            writer.write_frame(image)?;
+        }
+        assert_eq!(3, writer.frames());
+        let data = writer.into_inner();
+        assert!(data.starts_with(b"YUV4MPEG2 W5 H3 F30000:1001 Ip A1:1 C444\nFRAME\n"));
+
+        let reader = Y4mReader::new(&data[..])?;
+        assert_eq!((5, 3, (30000, 1001), ChromaSampling::Chroma444), (reader.width(), reader.heigth(), reader.frame_rate(), reader.chroma()));
+        let images = reader.collect::<Result<Vec<Image>, PpmError>>()?;
+        assert_eq!(3, images.len());
+        for (image, expected) in images.iter().zip(frames.iter()){
+            for (pixel, other) in image.pixels.iter().zip(expected.pixels.iter()){
+                for (sample, expected) in [(pixel.red, other.red), (pixel.green, other.green), (pixel.blue, other.blue)].iter(){
+                    assert!((*sample as i32 - *expected as i32).abs() <= 3, "{:?} for {:?}", pixel, other);
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_subsampling() -> Result<(), PpmError>{
+        // the sizes of the frames: 15 luma samples, and 3x2 or 3x3 chroma samples
+        for &(chroma, frame_size) in [(ChromaSampling::Chroma420, 15 + 2 * 6), (ChromaSampling::Chroma422, 15 + 2 * 9),
+            (ChromaSampling::Chroma444, 15 * 3), (ChromaSampling::Mono, 15)].iter(){
+            let mut writer = Y4mWriter::new(Vec::new(), 5, 3, (25, 1), chroma)?;
+            writer.write_frame(&frame(0))?;
+            let data = writer.into_inner();
+            let header = data.iter().position(|&c| c == b'\n').unwrap() + 1;
+            assert_eq!(header + 6 + frame_size, data.len());
+            assert_eq!(Some(chroma), ChromaSampling::from_tag(&String::from_utf8_lossy(&data[header - 1 - chroma.tag().len() .. header - 1])));
+
+            let images = Y4mReader::new(&data[..])?.collect::<Result<Vec<Image>, PpmError>>()?;
+            assert_eq!((3, 5), (images[0].heigth, images[0].width));
+        }
+
+        // the 2x2 pixels of a sample of chroma share its color, the luma is kept
+        let pixels = vec![Pixels::new(255, 0, 0), Pixels::new(255, 0, 0), Pixels::new(0, 0, 255), Pixels::new(0, 0, 255)];
+        let image = Image::new(pixels, 2, 2, "P6".to_string(), 255);
+        let mut writer = Y4mWriter::new(Vec::new(), 2, 2, (25, 1), ChromaSampling::Chroma420)?;
+        writer.write_frame(&image)?;
+        let data = writer.into_inner();
+        let frame = &data[data.len() - 6 ..];
+        assert_eq!(&[81, 81, 41, 41], &frame[.. 4]);
+        assert_eq!(((90 + 240) / 2, (240 + 110) / 2), (frame[4] as i32, frame[5] as i32));
+
+        // a stream written by other tools, with the siting of MPEG-2 and the samples of ppmImage.ppm
+        let ppm = Image::new_with_file(Path::new("resource/ppmImage.ppm"))?;
+        let mut writer = Y4mWriter::new(Vec::new(), 4, 4, (25, 1), ChromaSampling::Chroma420)?;
+        writer.write_frame(&ppm)?;
+        let data = writer.into_inner();
+        let mut header = b"YUV4MPEG2 C420mpeg2 W4 H4 F25:1 Ip A0:0 XYSCSS=420MPEG2\n".to_vec();
+        header.extend_from_slice(&data[data.iter().position(|&c| c == b'\n').unwrap() + 1 ..]);
+        let images = Y4mReader::new(&header[..])?.collect::<Result<Vec<Image>, PpmError>>()?;
+        assert_eq!(1, images.len());
+        assert_eq!((4, 4, 255), (images[0].heigth, images[0].width, images[0].maxValue));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_file() -> Result<(), PpmError>{
+        let mut writer = Y4mWriter::new_with_file(Path::new("test_save.y4m"), 5, 3, (25, 1), ChromaSampling::Chroma420)?;
+        writer.write_frame(&frame(0))?;
+        writer.write_frame(&frame(3))?;
+        match writer.write_frame(&Image::new(vec![Pixels::new(0, 0, 0); 4], 2, 2, "P6".to_string(), 255)) {
+            Err(PpmError::RowLength{ expected, found }) => assert_eq!((5, 2), (expected, found)),
+            _ => panic!("the frame has not the size of the stream"),
+        }
+        drop(writer.into_inner());
+        assert_eq!(2, Y4mReader::new_with_file(Path::new("test_save.y4m"))?.count());
+        fs::remove_file(Path::new("test_save.y4m"))?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_read_errors(){
+        match Y4mReader::new(&b"YUV4MPEG W2 H2\n"[..]) {
+            Err(PpmError::BadMagic{ .. }) => {},
+            _ => panic!("the magic number is wrong"),
+        }
+        match Y4mReader::new(&b"YUV4MPEG2 W2 F25:1\n"[..]) {
+            Err(PpmError::BadHeader{ message, .. }) => assert_eq!("missing H", message),
+            _ => panic!("the heigth is missing"),
+        }
+        match Y4mReader::new(&b"YUV4MPEG2 W2 H2 Wx\n"[..]) {
+            Err(PpmError::BadHeader{ position, .. }) => assert_eq!(Position{line : 1, byte : 16}, position),
+            _ => panic!("the width is not a number"),
+        }
+        match Y4mReader::new(&b"YUV4MPEG2 W2 H2 C420p10\n"[..]) {
+            Err(PpmError::UnsupportedFormat(_)) => {},
+            _ => panic!("the samples of 10 bits are not supported"),
+        }
+
+        let mut images = Y4mReader::new(&b"YUV4MPEG2 W2 H2 C444\nFRAME\n012345678901FRAME\n0123"[..]).unwrap();
+        assert!(images.next().unwrap().is_ok());
+        match images.next() {
+            Some(Err(PpmError::Truncated{ expected, found, position })) => assert_eq!((12, 4, Position{line : 1, byte : 39}), (expected, found, position)),
+            _ => panic!("the second frame is truncated"),
+        }
+        assert!(images.next().is_none());
+
+        // a frame of 200000x200000 pixels declared by 37 bytes
+        let data = b"YUV4MPEG2 W200000 H200000 C444\nFRAME\n";
+        assert_eq!(37, data.len());
+        match Y4mReader::new(&data[..]).unwrap().next() {
+            Some(Err(PpmError::Truncated{ expected, found, .. })) => assert_eq!((200000 * 200000 * 3, 0), (expected, found)),
+            _ => panic!("the frame is truncated"),
+        }
+
+        let mut images = Y4mReader::new(&b"YUV4MPEG2 W1 H1 Cmono\nFRAMX\n0"[..]).unwrap();
+        match images.next() {
+            Some(Err(PpmError::Corrupt{ .. })) => {},
+            _ => panic!("the frame header is wrong"),
+        }
+    }
+}
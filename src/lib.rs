@@ -1,16 +1,119 @@
+extern crate libc;
+
 pub use images::Image;
 #[path = "bin/images.rs"]
 mod images;
 
-pub use pixels::Pixels;
+pub use pixels::{Pixels, GrayMethod};
 #[path = "bin/pixels.rs"]
 mod pixels;
 
+pub use gray_image::GrayImage;
+#[path = "bin/gray_image.rs"]
+mod gray_image;
+
+pub use bit_image::BitImage;
+#[path = "bin/bit_image.rs"]
+mod bit_image;
+
+pub use rgba_image::{RgbaPixels, RgbaImage, GrayAlphaImage};
+#[path = "bin/rgba_image.rs"]
+mod rgba_image;
+
+pub use pam::PamImage;
+#[path = "bin/pam.rs"]
+mod pam;
+
+pub use float_image::FloatImage;
+#[path = "bin/float_image.rs"]
+mod float_image;
+
+pub use image_stream::{ImageStream, FrameWriter};
+#[path = "bin/image_stream.rs"]
+mod image_stream;
+
+pub use row_stream::{RowReader, RowWriter};
+#[path = "bin/row_stream.rs"]
+mod row_stream;
+
+pub use image_view::{ImageView, ViewPixels};
+#[path = "bin/image_view.rs"]
+mod image_view;
+
+#[cfg(unix)]
+pub use mapped_image::{MappedImage, Region, RegionRows};
+#[cfg(unix)]
+#[path = "bin/mapped_image.rs"]
+mod mapped_image;
+
+pub use conformance::{Conformance, Violation, Report, check_lines};
+#[path = "bin/conformance.rs"]
+mod conformance;
+
+pub use png::{PngImage, PngFilter};
+#[path = "bin/png.rs"]
+mod png;
+
+#[path = "bin/deflate.rs"]
+mod deflate;
+
+pub use bmp::{BmpImage, RowOrder};
+#[path = "bin/bmp.rs"]
+mod bmp;
+
+pub use tga::{TgaImage, TgaCompression};
+#[path = "bin/tga.rs"]
+mod tga;
+
+pub use qoi::QoiImage;
+#[path = "bin/qoi.rs"]
+mod qoi;
+
+pub use jpeg::Subsampling;
+#[path = "bin/jpeg.rs"]
+mod jpeg;
+
+#[path = "bin/farbfeld.rs"]
+mod farbfeld;
+
+pub use tiff::{TiffImage, TiffCompression, ByteOrder};
+#[path = "bin/tiff.rs"]
+mod tiff;
+
+#[path = "bin/xbm.rs"]
+mod xbm;
+
+pub use xpm::XpmImage;
+#[path = "bin/xpm.rs"]
+mod xpm;
+
+pub use gif::{GifFrame, GifPalette, Dithering, Repeat, save_gif, write_gif};
+#[path = "bin/gif.rs"]
+mod gif;
+
+pub use y4m::{Y4mReader, Y4mWriter, ChromaSampling};
+#[path = "bin/y4m.rs"]
+mod y4m;
+
+pub use any_image::{AnyImage, Format, open, save};
+#[path = "bin/any_image.rs"]
+mod any_image;
+
+#[path = "bin/header.rs"]
+mod header;
+
+pub use error::{PpmError, Position};
+#[path = "bin/error.rs"]
+mod error;
+
 #[path = "bin/ppm_libc.rs"]
 mod ppm_libc;
 
 use std::os::raw::{c_char,c_int};
-use std::mem::size_of;
+use std::fs;
+use std::io;
+use std::slice;
+use header::PnmReader;
 
 /// unzip the pixel to three color RGB
 /// 
@@ -70,13 +173,53 @@ fn un_zip_pixel(pixels : &Vec<Pixels>) -> (Vec<c_int>,Vec<c_int>,Vec<c_int>){
 fn zip_pixel(r : Vec::<c_int>, g : Vec::<c_int>, b : Vec::<c_int>) -> Vec::<Pixels>{
     let mut pixels = Vec::<Pixels>::new();
     for i in 0 .. r.len(){
-        pixels.push(Pixels::new(r[i] as u8,g[i] as u8, b[i] as u8));
+        pixels.push(Pixels::new(r[i] as u16,g[i] as u16, b[i] as u16));
     }
     pixels
 }
 
+/// check that the lib c can read the file, `ppma_read` stop the process on a malformed file
+/// 
+/// the file must be a valid ASCII (P3) image, with the commentaires of the header on their own lines,
+/// the samples starting on the line after the max value and without commentaires
+/// 
+/// # Arguments
+/// 
+/// `data` - the content of the file
+fn check_libc_file(data : &[u8]) -> Result<(), PpmError>{
+    let image = Image::from_bytes(data)?;
+    if image.fileType != "P3" {
+        return Err(PpmError::BadMagic{ magic : image.fileType, position : Position{line : 1, byte : 0} });
+    }
+    // ppma_read allocate 4 * 4 bytes by sample, the size is computed in an int
+    if image.heigth.checked_mul(image.width).and_then(|size| size.checked_mul(16)).is_none_or(|size| size > c_int::MAX as usize) {
+        return Err(PpmError::Overflow{ value : format!("{}x{}", image.heigth, image.width), position : Position{line : 1, byte : 0} });
+    }
+
+    let mut reader = PnmReader::new(data);
+    reader.read_header()?;
+    let position = reader.position();
+    // the lib c drop the end of the line of the max value, the white space after it is already read
+    if data[position.byte - 1] != b'\n' {
+        if let Some(line) = reader.read_line()? {
+            if !line.trim().is_empty() {
+                return Err(PpmError::bad_header("the samples must start on the line after the max value", position));
+            }
+        }
+    }
+    for (byte, _) in data.iter().enumerate().filter(|&(_, &c)| c == b'#'){
+        if byte >= position.byte || (byte > 0 && data[byte - 1] != b'\n') {
+            let line = data[.. byte].iter().filter(|&&c| c == b'\n').count() + 1;
+            return Err(PpmError::bad_header("the lib c read the commentaires only at the start of a line of the header", Position{line, byte}));
+        }
+    }
+    Ok(())
+}
+
 /// read the image ppm use the lib c
 /// 
+/// the file is checked before the lib c read it, a file the lib c can not read give an error
+/// 
 /// # Arguments
 /// 
 /// `file_name` - the name of file to read
@@ -93,31 +236,37 @@ fn zip_pixel(r : Vec::<c_int>, g : Vec::<c_int>, b : Vec::<c_int>) -> Vec::<Pixe
 /// let image_load : Image;
 /// 
 /// unsafe{
-///    image_load = readPPM_libc("test_read_image_c.ppm".to_string());
+///    image_load = readPPM_libc("test_read_image_c.ppm".to_string())?;
 /// }
 /// ```
-pub unsafe fn readPPM_libc(file_name : String) -> Image{
+pub unsafe fn readPPM_libc(file_name : String) -> Result<Image, PpmError>{
+    check_libc_file(&fs::read(&file_name)?)?;
+
     let mut xsize : c_int = 0;
     let mut ysize : c_int = 0;
     let mut rgb_max : c_int = 0;
     let mut r : *mut c_int = std::ptr::null_mut();
     let mut g : *mut c_int = std::ptr::null_mut();
     let mut b : *mut c_int = std::ptr::null_mut();
-    let mut red_vec = Vec::<c_int>::new(); 
-    let mut green_vec = Vec::<c_int>::new(); 
-    let mut blue_vec = Vec::<c_int>::new(); 
+    let red_vec; 
+    let green_vec; 
+    let blue_vec; 
     unsafe { 
         ppm_libc::ppma_read((file_name + "\0").as_ptr() as *const c_char, &mut xsize,
             &mut ysize , &mut rgb_max, &mut r, &mut g, &mut b);
 
-        let num_pixels = xsize * ysize;
-        red_vec = Vec::from_raw_parts(r, num_pixels as usize, size_of::<c_int>());
-        green_vec = Vec::from_raw_parts(g, num_pixels as usize, size_of::<c_int>());
-        blue_vec = Vec::from_raw_parts(b, num_pixels as usize, size_of::<c_int>());
+        // the buffers are allocated by the malloc of the lib c, they are copied then freed by it
+        let num_pixels = (xsize * ysize) as usize;
+        red_vec = slice::from_raw_parts(r, num_pixels).to_vec();
+        green_vec = slice::from_raw_parts(g, num_pixels).to_vec();
+        blue_vec = slice::from_raw_parts(b, num_pixels).to_vec();
+        libc::free(r as *mut libc::c_void);
+        libc::free(g as *mut libc::c_void);
+        libc::free(b as *mut libc::c_void);
     }
     let pixels = zip_pixel(red_vec, green_vec, blue_vec);
 
-    Image::new(pixels, ysize as usize, xsize as usize, "P3".to_string(), rgb_max as usize)
+    Ok(Image::new(pixels, ysize as usize, xsize as usize, "P3".to_string(), rgb_max as usize))
 }
 
 /// write the image ppm use the lib c
@@ -137,15 +286,20 @@ pub unsafe fn readPPM_libc(file_name : String) -> Image{
 /// let image = Image::new(pixels, 1, 3, "P3".to_string(), 91);
 /// 
 /// unsafe{
-///     writePPM_libc("test_write_image.ppm".to_string(), &image);
+///     writePPM_libc("test_write_image.ppm".to_string(), &image)?;
 /// }
 /// ```
-pub unsafe fn writePPM_libc(file_name : String, image : &Image){
+pub unsafe fn writePPM_libc(file_name : String, image : &Image) -> Result<(), PpmError>{
     let (mut r, mut g, mut b) = un_zip_pixel(&image.pixels);
+    let error;
     unsafe {
-        ppm_libc::ppma_write((file_name + "\0").as_ptr() as *const c_char, image.width as c_int, image.heigth as c_int,
+        error = ppm_libc::ppma_write((file_name + "\0").as_ptr() as *const c_char, image.width as c_int, image.heigth as c_int,
             r.as_mut_ptr() as *mut c_int, g.as_mut_ptr() as *mut c_int, b.as_mut_ptr() as *mut c_int);
     }
+    if error != 0 {
+        return Err(PpmError::Io(io::Error::other("ppma_write can not write the file")));
+    }
+    Ok(())
 }
 
 #[cfg(test)]
@@ -192,7 +346,7 @@ mod tests {
         let image_load : Image;
 
         unsafe{
-            image_load = readPPM_libc("test_read_image_c.ppm".to_string());
+            image_load = readPPM_libc("test_read_image_c.ppm".to_string()).unwrap();
         }
         
         assert_eq!(image, image_load);
@@ -200,6 +354,29 @@ mod tests {
         fs::remove_file(Path::new("test_read_image_c.ppm")).unwrap();
     }
 
+    #[test]
+    fn test_read_ppm_c_malformed() -> Result<(), PpmError>{
+        let files : [&[u8]; 5] = [
+            b"P3\n1 2 91\n7 91 43\n",
+            b"P6\n1 1 255\n\x07\x5b\x2b",
+            b"P3\n1 1 91 7 91 43\n",
+            b"P3 # comment\n1 1 91\n7 91 43\n",
+            b"P3\n1 1 91\n7 91 # comment\n43\n"];
+        for data in files.iter(){
+            fs::write(Path::new("test_read_image_c_malformed.ppm"), data)?;
+            let result = unsafe { readPPM_libc("test_read_image_c_malformed.ppm".to_string()) };
+            assert!(result.is_err(), "{:?} is read by the lib c", String::from_utf8_lossy(data));
+        }
+
+        fs::write(Path::new("test_read_image_c_malformed.ppm"), b"P3\n# comment\n1 1 91\n7 91 43\n")?;
+        let image = unsafe { readPPM_libc("test_read_image_c_malformed.ppm".to_string())? };
+        assert_eq!(vec![Pixels::new(7, 91, 43)], image.pixels);
+
+        fs::remove_file(Path::new("test_read_image_c_malformed.ppm"))?;
+
+        Ok(())
+    }
+
     #[test]
     fn test_write_ppm_c(){
         let mut pixels = Vec::new();
@@ -209,7 +386,7 @@ mod tests {
         let image = Image::new(pixels, 1, 3, "P3".to_string(), 91);
 
         unsafe{
-            writePPM_libc("test_write_image.ppm".to_string(), &image);
+            writePPM_libc("test_write_image.ppm".to_string(), &image).unwrap();
         }
 
         let image_load = Image::new_with_file(Path::new("test_write_image.ppm"));
@@ -218,4 +395,26 @@ mod tests {
 
         fs::remove_file(Path::new("test_write_image.ppm")).unwrap();
     }
+
+    #[test]
+    fn test_ppm_c_16_bits(){
+        let mut pixels = Vec::new();
+        pixels.push(Pixels::new(7, 910, 43));
+        pixels.push(Pixels::new(65535, 256, 0));
+        pixels.push(Pixels::new(300, 40000, 1023));
+        let image = Image::new(pixels, 1, 3, "P3".to_string(), 65535);
+
+        unsafe{
+            writePPM_libc("test_c_image_16.ppm".to_string(), &image).unwrap();
+        }
+        let image_load : Image;
+        unsafe{
+            image_load = readPPM_libc("test_c_image_16.ppm".to_string()).unwrap();
+        }
+
+        assert_eq!(image.pixels, image_load.pixels);
+        assert_eq!(65535, image_load.maxValue);
+
+        fs::remove_file(Path::new("test_c_image_16.ppm")).unwrap();
+    }
 }
\ No newline at end of file